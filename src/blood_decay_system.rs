@@ -0,0 +1,27 @@
+use specs::prelude::*;
+
+use super::{Map, RunState};
+
+/// How much a bloodstain's intensity fades each turn.
+const BLOOD_DECAY_PER_TURN: f32 = 0.02;
+
+/// Fades bloodstains a little every turn, removing them once they're gone.
+pub struct BloodDecaySystem {}
+
+impl<'a> System<'a> for BloodDecaySystem {
+    type SystemData = (WriteExpect<'a, Map>, ReadExpect<'a, RunState>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, runstate) = data;
+
+        // Systems run on both halves of a turn; only decay once per turn.
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        map.bloodstains
+            .values_mut()
+            .for_each(|intensity| *intensity -= BLOOD_DECAY_PER_TURN);
+        map.bloodstains.retain(|_, intensity| *intensity > 0.0);
+    }
+}
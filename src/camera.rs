@@ -0,0 +1,50 @@
+use rltk::Point;
+
+use super::Map;
+
+/// Width of the console area the map is drawn into.
+pub const VIEW_WIDTH: i32 = 80;
+/// Height of the console area the map is drawn into (everything above the UI box).
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// Resource translating between world (map) coordinates and screen coordinates.
+///
+/// The offsets are the world coordinates of the top-left screen tile; they're
+/// kept centered on the player and clamped so the viewport never leaves the map.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct Camera {
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+impl Camera {
+    pub fn new() -> Camera {
+        Camera {
+            x_offset: 0,
+            y_offset: 0,
+        }
+    }
+
+    /// Centers the viewport on `target`, clamped to the bounds of `map`.
+    pub fn center_on(&mut self, map: &Map, target: Point) {
+        let max_x = i32::max(0, map.width - VIEW_WIDTH);
+        let max_y = i32::max(0, map.height - VIEW_HEIGHT);
+        self.x_offset = i32::min(max_x, i32::max(0, target.x - VIEW_WIDTH / 2));
+        self.y_offset = i32::min(max_y, i32::max(0, target.y - VIEW_HEIGHT / 2));
+    }
+
+    /// Converts world coordinates to screen coordinates.
+    pub fn to_screen(&self, x: i32, y: i32) -> (i32, i32) {
+        (x - self.x_offset, y - self.y_offset)
+    }
+
+    /// Converts screen coordinates to world coordinates.
+    pub fn to_world(&self, x: i32, y: i32) -> (i32, i32) {
+        (x + self.x_offset, y + self.y_offset)
+    }
+
+    /// Checks if the given screen coordinates fall inside the map viewport.
+    pub fn on_screen(&self, x: i32, y: i32) -> bool {
+        x >= 0 && x < VIEW_WIDTH && y >= 0 && y < VIEW_HEIGHT
+    }
+}
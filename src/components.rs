@@ -8,7 +8,7 @@ use specs::{
 use specs_derive::*;
 
 /// Component detailing the 2D position of an entity.
-#[derive(Component, ConvertSaveload, Clone, Copy, Debug)]
+#[derive(Component, ConvertSaveload, Clone, Copy, PartialEq, Debug)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -33,6 +33,18 @@ impl From<(i32, i32)> for Position {
     }
 }
 
+impl From<Position> for rltk::Point {
+    fn from(p: Position) -> rltk::Point {
+        rltk::Point::new(p.x, p.y)
+    }
+}
+
+impl From<rltk::Point> for Position {
+    fn from(p: rltk::Point) -> Position {
+        Position { x: p.x, y: p.y }
+    }
+}
+
 /// Component for entities that can be rendered to the screen.
 ///
 /// Entities will be rendered as their glyph, with said glyph having color `fg`
@@ -42,7 +54,63 @@ pub struct Renderable {
     pub glyph: rltk::FontCharType,
     pub fg: RGB,
     pub bg: RGB,
-    pub render_order: i32,
+    pub render_order: RenderOrder,
+}
+
+/// Component cycling an entity's glyph through `frames`, e.g. a flickering brazier.
+///
+/// Each frame is a glyph and how many milliseconds it stays up. Purely cosmetic: it's
+/// drawn in place of the [`Renderable`] glyph (colors still come from the `Renderable`),
+/// and the animation restarts from the first frame after loading.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct AnimatedRenderable {
+    pub frames: Vec<(rltk::FontCharType, f32)>,
+    #[serde(skip)]
+    pub current: usize,
+    #[serde(skip)]
+    pub elapsed_ms: f32,
+}
+
+impl AnimatedRenderable {
+    pub fn new(frames: Vec<(rltk::FontCharType, f32)>) -> AnimatedRenderable {
+        AnimatedRenderable {
+            frames,
+            current: 0,
+            elapsed_ms: 0.0,
+        }
+    }
+
+    /// The glyph to draw right now, if there are any frames.
+    pub fn glyph(&self) -> Option<rltk::FontCharType> {
+        self.frames.get(self.current).map(|(glyph, _)| *glyph)
+    }
+
+    /// Moves the animation on by `frame_time_ms`, skipping as many frames as have passed.
+    pub fn advance(&mut self, frame_time_ms: f32) {
+        if self.frames.is_empty() {
+            return;
+        }
+        self.elapsed_ms += frame_time_ms;
+        // Zero-length frames would never let the loop finish.
+        while self.frames[self.current].1 > 0.0 && self.elapsed_ms >= self.frames[self.current].1 {
+            self.elapsed_ms -= self.frames[self.current].1;
+            self.current = (self.current + 1) % self.frames.len();
+        }
+    }
+}
+
+/// Layer a [`Renderable`] is drawn on when several share a tile.
+///
+/// Layers are drawn in the order they're declared, so each one covers those before it:
+/// the player always shows over the monsters and items around them, and particles flash
+/// over everything.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum RenderOrder {
+    Decoration,
+    Item,
+    Monster,
+    Player,
+    Particle,
 }
 
 /// Component used to "tag" player entities.
@@ -66,6 +134,50 @@ pub struct Viewshed {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+/// What a monster is currently up to.
+///
+/// Positions are stored as map indices.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AiMode {
+    /// Standing around, occasionally deciding to wander.
+    Idle,
+    /// Ambling toward a nearby floor tile.
+    Wandering { dest: usize },
+    /// Chasing the player, remembering where they were last seen for `turns_left` turns.
+    Hunting { last_seen: usize, turns_left: i32 },
+    /// Lost the player at `around`, and poking about nearby for `turns_left` more turns.
+    Searching { around: usize, turns_left: i32 },
+}
+
+/// Per-monster AI state machine: Idle -> Wandering -> Hunting -> Searching.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct AiState {
+    pub mode: AiMode,
+}
+
+/// Monster runs from the player once its HP drops below `hp_threshold` (a fraction of max HP).
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct CanFlee {
+    pub hp_threshold: f32,
+}
+
+/// Which side of a conflict an entity is on.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum FactionKind {
+    Player,
+    Hostile,
+    Neutral,
+}
+
+/// Component marking an entity's allegiance.
+///
+/// Hostile entities pursue and attack the player's faction, while neutral
+/// entities are left alone when bumped into.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Faction {
+    pub kind: FactionKind,
+}
+
 /// Allows for naming of entities.
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Name {
@@ -103,38 +215,185 @@ pub struct AreaOfEffect {
     pub radius: i32,
 }
 
+/// Status effect: takes `damage` poison damage every turn for `turns` turns.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Poisoned {
+    pub turns: i32,
+    pub damage: i32,
+}
+
+/// Status effects that can be cured (or resisted).
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum StatusEffect {
+    Confusion,
+    Poison,
+}
+
+/// Status effects the entity can't be given.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Immunities {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl Immunities {
+    /// Whether `ent` shrugs off `effect`.
+    pub fn blocks(store: &ReadStorage<Immunities>, ent: Entity, effect: StatusEffect) -> bool {
+        store
+            .get(ent)
+            .map_or(false, |immune| immune.effects.contains(&effect))
+    }
+}
+
+/// Consumable that rids its target of each of the listed `effects`.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct CuresStatus {
+    pub effects: Vec<StatusEffect>,
+}
+
+/// Area of effect item that leaves its damage hanging over the tiles it hits for `turns` turns,
+/// instead of dealing it all at once.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Lingers {
+    pub turns: i32,
+}
+
+/// What kind of harm a source of damage does.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Cold,
+    Poison,
+}
+
+impl DamageType {
+    /// Lowercase name of the damage type, for the log.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DamageType::Physical => "physical",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Poison => "poison",
+        }
+    }
+}
+
 /// Flag: entity is able to inflict damage on other entities.
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InflictsDamage {
     pub damage: i32,
+    pub damage_type: DamageType,
+}
+
+/// Scales incoming damage: `resistant` types are halved and `vulnerable` types doubled.
+///
+/// Entities without this component take damage as-is.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Resistances {
+    pub resistant: Vec<DamageType>,
+    pub vulnerable: Vec<DamageType>,
+}
+
+impl Resistances {
+    /// Applies these resistances to `amount` damage of type `damage_type`.
+    pub fn scale(&self, amount: i32, damage_type: DamageType) -> i32 {
+        if self.resistant.contains(&damage_type) {
+            amount / 2
+        } else if self.vulnerable.contains(&damage_type) {
+            amount * 2
+        } else {
+            amount
+        }
+    }
 }
 
 /// Struct used for handling and applying damage to entities.
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
-    pub amount: Vec<i32>,
+    pub amount: Vec<(i32, DamageType)>,
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(
+        store: &mut WriteStorage<SufferDamage>,
+        victim: Entity,
+        amount: i32,
+        damage_type: DamageType,
+    ) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, damage_type));
         } else {
             let dmg = SufferDamage {
-                amount: vec![amount],
+                amount: vec![(amount, damage_type)],
             };
             store.insert(victim, dmg).expect("Unable to insert damage");
         }
     }
 }
 
-/// Intent: entity wants to engage in melee combat against `target`.
-#[derive(Component, Debug, ConvertSaveload, Clone)]
+/// Intent: entity wants to engage in melee combat against each of `targets`.
+#[derive(Component, Debug, Clone)]
 pub struct WantsToMelee {
-    pub target: Entity,
+    pub targets: Vec<Entity>,
+}
+
+/// Saved form of [`WantsToMelee`]; the derive can't convert a `Vec` of entities.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WantsToMeleeData<M>(Vec<M>);
+
+impl<M: Marker + Serialize> ConvertSaveload<M> for WantsToMelee
+where
+    for<'de> M: Deserialize<'de>,
+{
+    type Data = WantsToMeleeData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(WantsToMeleeData(
+            self.targets
+                .iter()
+                .filter_map(|target| ids(*target))
+                .collect(),
+        ))
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(WantsToMelee {
+            targets: data
+                .0
+                .into_iter()
+                .filter_map(|marker| ids(marker))
+                .collect(),
+        })
+    }
 }
 
-/// Intent. Taken on when an entity tries to pick up an item.
+impl WantsToMelee {
+    /// Adds `target` to the entities `attacker` will swing at this turn.
+    pub fn new_attack(store: &mut WriteStorage<WantsToMelee>, attacker: Entity, target: Entity) {
+        if let Some(melee) = store.get_mut(attacker) {
+            if !melee.targets.contains(&target) {
+                melee.targets.push(target);
+            }
+        } else {
+            let melee = WantsToMelee {
+                targets: vec![target],
+            };
+            store
+                .insert(attacker, melee)
+                .expect("Unable to insert attack");
+        }
+    }
+}
+
+/// Intent. Taken on by an item when an entity tries to pick it up, so several
+/// items can be collected in the same turn.
 #[derive(Component, Debug, ConvertSaveload)]
 pub struct WantsToPickupItem {
     pub collected_by: Entity,
@@ -159,16 +418,41 @@ pub struct WantsToUseItem {
     pub target: Option<rltk::Point>,
 }
 
+/// Quick-use slot an item was bound to when the game was saved.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct HotbarSlot {
+    pub slot: usize,
+}
+
 /// Flag: entity with this flag is in the possession (backpack) of `owner`.
 #[derive(Component, Debug, ConvertSaveload)]
 pub struct InBackpack {
     pub owner: Entity,
 }
 
-#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum EquipmentSlot {
     Melee,
     Shield,
+    /// Weapons needing both hands; fills the melee and shield slots.
+    TwoHanded,
+    Head,
+    Body,
+    Hands,
+    Feet,
+}
+
+impl EquipmentSlot {
+    /// Checks if an item in this slot can't be worn at the same time as one in `other`.
+    pub fn conflicts_with(&self, other: EquipmentSlot) -> bool {
+        match (*self, other) {
+            (EquipmentSlot::TwoHanded, EquipmentSlot::Melee)
+            | (EquipmentSlot::TwoHanded, EquipmentSlot::Shield)
+            | (EquipmentSlot::Melee, EquipmentSlot::TwoHanded)
+            | (EquipmentSlot::Shield, EquipmentSlot::TwoHanded) => true,
+            (slot, other) => slot == other,
+        }
+    }
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -201,6 +485,59 @@ pub struct ProvidesHealing {
     pub heal_amount: i32,
 }
 
+/// How a [`Weapon`] reaches its target.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AttackKind {
+    /// Swung at whatever the wielder bumps into.
+    Melee,
+    /// Fired at a target picked out up to the weapon's `range` away.
+    Ranged,
+}
+
+/// Describes a weapon's attack: melee weapons add `damage` to their wielder's bump attacks,
+/// while ranged ones are fired on their own, dealing `damage` out to `range` tiles.
+///
+/// Weapons with `ammo` need some of that kind in their wielder's backpack to fire.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Weapon {
+    pub attack_kind: AttackKind,
+    pub damage: i32,
+    pub range: i32,
+    #[serde(default)]
+    pub ammo: Option<AmmoKind>,
+}
+
+/// What a ranged [`Weapon`] fires.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AmmoKind {
+    Arrow,
+    SlingStone,
+}
+
+impl AmmoKind {
+    /// Name of a bundle of this ammo, as it's spawned and shown in the inventory.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AmmoKind::Arrow => "Arrows",
+            AmmoKind::SlingStone => "Sling Stones",
+        }
+    }
+}
+
+/// A bundle of `count` shots of ammunition. Bundles of the same kind merge when picked up.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ammo {
+    pub kind: AmmoKind,
+    pub count: i32,
+}
+
+/// Intent. Fire the equipped ranged weapon at `target`.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToShoot {
+    pub target: rltk::Point,
+}
+
+/// Melee power granted by wearing something that isn't itself a [`Weapon`].
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct MeleePowerBonus {
     pub power: i32,
@@ -211,6 +548,26 @@ pub struct DefenseBonus {
     pub defense: i32,
 }
 
+/// Gear that wears down a point with each blow it deals or takes, breaking when `current` hits 0.
+///
+/// Equipment without it never wears out.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Durability {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Durability {
+    /// Brand new gear that can take `max` blows.
+    pub fn new(max: i32) -> Durability {
+        Durability { current: max, max }
+    }
+}
+
+/// Consumable that restores the durability of everything its user has equipped.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct RepairsEquipment {}
+
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct ParticleLifetime {
     pub lifetime_ms: f32,
@@ -230,8 +587,123 @@ pub struct HungerClock {
     pub duration: i32,
 }
 
+/// Reveals the level when used; `reveal_traps` also uncovers hidden traps.
+///
+/// With `reachable_only`, the map spreads out from the user along the paths they could walk,
+/// leaving sealed-off areas hidden; otherwise every row is swept top to bottom.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MagicMapper {
+    #[serde(default)]
+    pub reveal_traps: bool,
+    #[serde(default)]
+    pub reachable_only: bool,
+}
+
+/// Lets an entity sense every monster on the level for `turns` turns.
+///
+/// On an item, grants the effect to whoever uses it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Telepathy {
+    pub turns: i32,
+}
+
+/// Flag: monster follows the player's scent when it can't see them.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TracksByScent {}
+
+/// Marks the boss guarding the amulet, so its death can be recorded.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Guardian {}
+
+/// Lets a monster call `minion`s to its side every `cooldown` turns while hunting.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SummonsMinions {
+    pub minion: String,
+    pub cooldown: i32,
+    pub ready_in: i32,
+}
+
+/// Marks a monster that leaves an (edible) corpse behind when it dies.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LeavesCorpse {}
+
+/// How quickly a monster acts relative to the player.
+///
+/// Each turn a monster gains `speed` energy, and every action it takes costs
+/// [`NORMAL_SPEED`](crate::monster_ai_system::NORMAL_SPEED) energy.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Speed {
+    pub speed: i32,
+    pub energy: i32,
+}
+
+/// How many items an entity can carry in its backpack. Without one, there's no limit.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct PackCapacity {
+    pub max_items: usize,
+}
+
+/// How much gold an entity is carrying.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Gold {
+    pub amount: i32,
+}
+
+/// A pile of coins: picking it up adds `amount` to the collector's [`Gold`].
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Currency {
+    pub amount: i32,
+}
+
+/// What an item on sale in a shop costs.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Price {
+    pub value: i32,
+}
+
+/// Bumping into an entity with this opens the shop.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Vendor {}
+
+/// A monster with this may drop an item from the named loot table when it dies.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LootTable {
+    pub table: String,
+}
+
+/// A weapon (or monster) with this shoves whoever it hits up to `distance` tiles away.
+///
+/// A target knocked into a wall or someone else takes extra damage from the impact.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Knockback {
+    pub distance: i32,
+}
+
+/// A weapon with this also strikes the two tiles flanking a bump attack.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Cleave {}
+
+/// A monster with this heals for `fraction` of the melee damage it deals.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LifeDrain {
+    pub fraction: f32,
+}
+
+/// A monster with this lowers its victim's max HP by `amount` with each damaging hit.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct StatDrain {
+    pub amount: i32,
+}
+
+/// A monster with this splits off a weaker `spawn` when it survives a damaging hit.
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
-pub struct MagicMapper {}
+pub struct SplitOnHit {
+    pub spawn: String,
+}
+
+/// Picking this item up wins the game.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WinsGame {}
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Hidden {}
@@ -242,6 +714,25 @@ pub struct EntryTrigger {}
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct EntityMoved {}
 
+/// Intent. Taken on by a trap set off by `victim` without being stepped on, such as by a
+/// botched attempt to disarm it.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Sprung {
+    pub victim: Entity,
+}
+
+/// Trap effect: moves whoever triggers it to a random floor tile.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TeleportsVictim {}
+
+/// Trap effect: alerts every monster on the level to the player.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RaisesAlarm {}
+
+/// Flag: monster knows where the player is, and will chase them even out of sight.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Alerted {}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct SingleActivation {}
 
@@ -251,4 +742,13 @@ pub struct SerializeMe;
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
+    pub seed: super::seed::GameSeed,
+    #[serde(default)]
+    pub difficulty: super::difficulty::Difficulty,
+    /// State of the RNG when the game was saved, so rolls carry on where they left off.
+    #[serde(default)]
+    pub rng: Option<rltk::RandomNumberGenerator>,
+    /// Whether the amulet's guardian has been killed this run.
+    #[serde(default)]
+    pub guardian_slain: bool,
 }
@@ -1,28 +1,78 @@
 use crate::Position;
 use specs::prelude::*;
 
-use super::{gamelog::GameLog, CombatStats, Map, Name, Player, RunState, SufferDamage};
+use super::{
+    gamelog::{GameLog, TurnCounter},
+    map_indexing_system::MapIndexingSystem,
+    particle_system::ParticleBuilder,
+    spawner, CombatStats, Guardian, LeavesCorpse, LootTable, Map, Name, Player, Resistances,
+    RunState, SufferDamage, TileType,
+};
+
+/// Resource recording whether the amulet's guardian has been killed this run.
+#[derive(Default)]
+pub struct GuardianSlain {
+    pub slain: bool,
+}
+
+/// How long the splatter left by a kill lasts, in ms.
+const DEATH_PARTICLE_LIFETIME: f32 = 300.0;
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
         ReadStorage<'a, Position>,
         WriteExpect<'a, Map>,
         Entities<'a>,
+        ReadStorage<'a, Resistances>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage, positions, mut map, entities) = data;
+        let (
+            mut stats,
+            mut damage,
+            positions,
+            mut map,
+            entities,
+            resistances,
+            names,
+            mut log,
+            turn_counter,
+        ) = data;
 
         for (ent, mut stats, damage) in (&entities, &mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+            for (amount, damage_type) in damage.amount.iter() {
+                // Scale the hit by the victim's resistances, letting the player know if it mattered.
+                let scaled = match resistances.get(ent) {
+                    Some(res) => res.scale(*amount, *damage_type),
+                    None => *amount,
+                };
+                if scaled != *amount {
+                    if let Some(name) = names.get(ent) {
+                        let verb = if scaled < *amount {
+                            "resists"
+                        } else {
+                            "is weak to"
+                        };
+                        log.push(
+                            turn_counter.turn,
+                            format!("{} {} the {} damage!", &name.name, verb, damage_type.name()),
+                        );
+                    }
+                }
+                stats.hp -= scaled;
+            }
             // Apply bloodstains to the tile combat took place on.
             if let Some(pos) = positions.get(ent) {
                 let idx = map.xy_idx(pos.x, pos.y);
-                map.bloodstains.insert(idx);
+                map.bloodstains.insert(idx, 1.0);
             }
         }
 
@@ -30,17 +80,31 @@ impl<'a> System<'a> for DamageSystem {
     }
 }
 
-/// Removes dead entities (those with <1 hp) from the world.
+/// Removes everything that's died this turn, leaving corpses and loot behind.
+///
+/// `tile_content` is re-indexed afterwards, so nothing read later in the same tick
+/// sees the dead.
 pub fn delete_the_dead(ecs: &mut World) {
     // Vector to hold out "dead bodies"
     let mut dead: Vec<Entity> = Vec::new();
+    // Where to leave corpses, and whose they are.
+    let mut corpses: Vec<(i32, i32, String)> = Vec::new();
+    // Where to drop loot, and which table to roll it from.
+    let mut loot: Vec<(i32, i32, String)> = Vec::new();
+    // Where things died, to splatter with blood.
+    let mut splatters: Vec<(i32, i32)> = Vec::new();
     // Scoping to appease the almighty borrow-checker
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
+        let positions = ecs.read_storage::<Position>();
+        let leaves_corpse = ecs.read_storage::<LeavesCorpse>();
+        let loot_tables = ecs.read_storage::<LootTable>();
         let players = ecs.read_storage::<Player>();
+        let guardians = ecs.read_storage::<Guardian>();
         let entities = ecs.entities();
         let names = ecs.read_storage::<Name>();
         let mut log = ecs.write_resource::<GameLog>();
+        let turn_counter = ecs.fetch::<TurnCounter>();
 
         for (ent, stats) in (&entities, &combat_stats).join() {
             if stats.hp < 1 {
@@ -49,7 +113,22 @@ pub fn delete_the_dead(ecs: &mut World) {
                     None => {
                         let victim_name = names.get(ent);
                         if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("{} is dead", &victim_name.name));
+                            log.push(turn_counter.turn, format!("{} is dead", &victim_name.name));
+                            if let (Some(pos), Some(_)) =
+                                (positions.get(ent), leaves_corpse.get(ent))
+                            {
+                                corpses.push((pos.x, pos.y, victim_name.name.clone()));
+                            }
+                        }
+                        if let (Some(pos), Some(table)) = (positions.get(ent), loot_tables.get(ent))
+                        {
+                            loot.push((pos.x, pos.y, table.table.clone()));
+                        }
+                        if let Some(pos) = positions.get(ent) {
+                            splatters.push((pos.x, pos.y));
+                        }
+                        if guardians.get(ent).is_some() {
+                            ecs.write_resource::<GuardianSlain>().slain = true;
                         }
                         dead.push(ent)
                     }
@@ -62,8 +141,142 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
+    if dead.is_empty() {
+        return;
+    }
+
     // Remove all dead entities from the world.
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
+
+    // Spray blood over (and around) where they fell.
+    {
+        let mut map = ecs.write_resource::<Map>();
+        let mut particles = ecs.write_resource::<ParticleBuilder>();
+        for (x, y) in splatters.iter() {
+            let idx = map.xy_idx(*x, *y);
+            map.bloodstains.insert(idx, 1.0);
+            particles.request(
+                *x,
+                *y,
+                rltk::RGB::named(rltk::RED),
+                rltk::RGB::named(rltk::BLACK),
+                rltk::to_cp437('*'),
+                DEATH_PARTICLE_LIFETIME,
+            );
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+                let splashed = map.try_xy_idx(x + dx, y + dy);
+                if splashed.map_or(false, |idx| map.tiles[idx] != TileType::Wall) {
+                    particles.request(
+                        x + dx,
+                        y + dy,
+                        rltk::RGB::named(rltk::DARK_RED),
+                        rltk::RGB::named(rltk::BLACK),
+                        rltk::to_cp437('·'),
+                        DEATH_PARTICLE_LIFETIME,
+                    );
+                }
+            }
+        }
+    }
+
+    // Leave the bodies behind where they fell.
+    for (x, y, name) in corpses.iter() {
+        spawner::corpse(ecs, *x, *y, name);
+    }
+
+    // And anything they were carrying.
+    for (x, y, table) in loot.iter() {
+        spawner::drop_loot(ecs, *x, *y, table);
+    }
+
+    // Don't leave the dead in the tile index until the next run of the systems.
+    let mut mapindex = MapIndexingSystem {};
+    mapindex.run_now(ecs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlocksTile, TileType};
+
+    #[test]
+    fn dead_are_removed_from_tile_content() {
+        let mut ecs = World::new();
+        ecs.register::<CombatStats>();
+        ecs.register::<Position>();
+        ecs.register::<BlocksTile>();
+        ecs.register::<LeavesCorpse>();
+        ecs.register::<LootTable>();
+        ecs.register::<Player>();
+        ecs.register::<Name>();
+        ecs.register::<Guardian>();
+        let mut map = Map::new(1, 10, 10);
+        let idx = map.xy_idx(3, 4);
+        map.tiles[idx] = TileType::Floor;
+        ecs.insert(map);
+        ecs.insert(GameLog::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(RunState::PlayerTurn);
+        ecs.insert(ParticleBuilder::new());
+        ecs.insert(GuardianSlain::default());
+
+        let victim = ecs
+            .create_entity()
+            .with(Position { x: 3, y: 4 })
+            .with(BlocksTile {})
+            .with(CombatStats {
+                max_hp: 10,
+                hp: 0,
+                defense: 0,
+                power: 0,
+            })
+            .build();
+        let mut mapindex = MapIndexingSystem {};
+        mapindex.run_now(&ecs);
+        assert!(ecs.fetch::<Map>().tile_content[idx].contains(&victim));
+
+        delete_the_dead(&mut ecs);
+
+        let map = ecs.fetch::<Map>();
+        assert!(!map.tile_content[idx].contains(&victim));
+        assert!(!map.blocked[idx]);
+        // The kill leaves blood behind.
+        assert!(map.bloodstains.contains_key(&idx));
+        // An ordinary monster isn't the guardian.
+        assert!(!ecs.fetch::<GuardianSlain>().slain);
+    }
+
+    #[test]
+    fn killing_the_guardian_is_recorded() {
+        let mut ecs = World::new();
+        ecs.register::<CombatStats>();
+        ecs.register::<Position>();
+        ecs.register::<BlocksTile>();
+        ecs.register::<LeavesCorpse>();
+        ecs.register::<LootTable>();
+        ecs.register::<Player>();
+        ecs.register::<Name>();
+        ecs.register::<Guardian>();
+        ecs.insert(Map::new(1, 10, 10));
+        ecs.insert(GameLog::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(RunState::PlayerTurn);
+        ecs.insert(ParticleBuilder::new());
+        ecs.insert(GuardianSlain::default());
+
+        ecs.create_entity()
+            .with(Guardian {})
+            .with(CombatStats {
+                max_hp: 60,
+                hp: 0,
+                defense: 3,
+                power: 8,
+            })
+            .build();
+        delete_the_dead(&mut ecs);
+
+        assert!(ecs.fetch::<GuardianSlain>().slain);
+    }
 }
@@ -0,0 +1,67 @@
+use rltk::VirtualKeyCode;
+use specs::prelude::*;
+
+use super::{
+    gamelog::{GameLog, TurnCounter},
+    settings::env_flag,
+    Map, RunState,
+};
+
+/// Resource holding whether the playtesting shortcuts are available.
+///
+/// Off unless the `DEBUG_COMMANDS` environment variable is `1` or `true`, and never
+/// available in release builds, so they can't leak into normal play.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DebugCommands {
+    pub enabled: bool,
+}
+
+impl Default for DebugCommands {
+    fn default() -> Self {
+        DebugCommands {
+            enabled: env_flag("DEBUG_COMMANDS", false) && cfg!(debug_assertions),
+        }
+    }
+}
+
+/// Runs the debug command bound to `key`, if debug commands are on.
+///
+/// These sit on the function keys rather than in the rebindable key bindings:
+/// * `F1` descends to a freshly generated next level, wherever the player is standing.
+/// * `F2` regenerates the current level in place from a fresh seed.
+/// * `F3` reveals the whole level at once, without a turn passing.
+pub fn debug_input(ecs: &mut World, key: VirtualKeyCode) -> Option<RunState> {
+    if !ecs.fetch::<DebugCommands>().enabled {
+        return None;
+    }
+    match key {
+        VirtualKeyCode::F1 => Some(RunState::NextLevel),
+        VirtualKeyCode::F2 => Some(RunState::RegenerateLevel),
+        VirtualKeyCode::F3 => {
+            reveal_map(&mut ecs.fetch_mut::<Map>());
+            let turn = ecs.fetch::<TurnCounter>().turn;
+            ecs.fetch_mut::<GameLog>()
+                .push(turn, "[debug] Revealed the whole level.");
+            Some(RunState::AwaitingInput)
+        }
+        _ => None,
+    }
+}
+
+/// Marks every tile as explored, without making any of it visible.
+pub fn reveal_map(map: &mut Map) {
+    map.revealed_tiles.iter_mut().for_each(|seen| *seen = true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reveal_map_explores_without_granting_sight() {
+        let (mut map, _) = Map::from_ascii("####\n#..#\n####").unwrap();
+        reveal_map(&mut map);
+        assert!(map.revealed_tiles.iter().all(|seen| *seen));
+        assert!(map.visible_tiles.iter().all(|seen| !*seen));
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+/// Resource holding how hard the current run is, chosen when starting a new game.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Normal
+    }
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    /// The next option down in the selection menu, wrapping around.
+    pub fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// The next option up in the selection menu, wrapping around.
+    pub fn prev(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Hard,
+            Difficulty::Normal => Difficulty::Easy,
+            Difficulty::Hard => Difficulty::Normal,
+        }
+    }
+
+    /// Extra (or fewer) entities rolled for each room or region.
+    pub fn spawn_modifier(self) -> i32 {
+        match self {
+            Difficulty::Easy => -1,
+            Difficulty::Normal => 0,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    /// Scales a monster's base hit points.
+    pub fn monster_hp(self, base: i32) -> i32 {
+        match self {
+            Difficulty::Easy => base * 3 / 4,
+            Difficulty::Normal => base,
+            Difficulty::Hard => base * 3 / 2,
+        }
+    }
+
+    /// Adjusts a monster's base power.
+    pub fn monster_power(self, base: i32) -> i32 {
+        match self {
+            Difficulty::Easy => base - 1,
+            Difficulty::Normal => base,
+            Difficulty::Hard => base + 1,
+        }
+    }
+
+    /// How many health potions the player starts with.
+    pub fn starting_potions(self) -> usize {
+        match self {
+            Difficulty::Easy => 3,
+            Difficulty::Normal => 2,
+            Difficulty::Hard => 1,
+        }
+    }
+}
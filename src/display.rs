@@ -0,0 +1,49 @@
+use rltk::{Rltk, RGB};
+use serde::{Deserialize, Serialize};
+
+use super::{settings::env_flag, CanFlee, CombatStats};
+
+/// Resource holding the player's display preferences, kept in the
+/// [`GameSettings`](crate::settings::GameSettings).
+///
+/// Status tinting is on unless the `STATUS_TINT` environment variable is `0` or `false`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct DisplaySettings {
+    /// Shade the background behind fighters by their health and state.
+    pub status_tint: bool,
+    /// Draw the retro CRT scanlines (and screen burn) over everything.
+    pub scanlines: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            status_tint: env_flag("STATUS_TINT", true),
+            scanlines: true,
+        }
+    }
+}
+
+impl DisplaySettings {
+    /// Turns the scanline post-processing on or off to match the settings.
+    pub fn apply(&self, ctx: &mut Rltk) {
+        ctx.post_scanlines = self.scanlines;
+        ctx.post_screenburn = self.scanlines;
+    }
+}
+
+/// Background colour showing how a fighter is doing.
+///
+/// Confusion and fleeing take priority; otherwise it fades from green at full health to red
+/// near death. Kept dark so the glyph on top stays readable.
+pub fn status_bg(stats: &CombatStats, confused: bool, flee: Option<&CanFlee>) -> RGB {
+    let health = f32::max(0.0, stats.hp as f32 / stats.max_hp as f32);
+    if confused {
+        RGB::from_f32(0.35, 0.0, 0.35)
+    } else if flee.map_or(false, |coward| health < coward.hp_threshold) {
+        RGB::from_f32(0.4, 0.3, 0.0)
+    } else {
+        RGB::from_f32(0.4, 0.0, 0.0).lerp(RGB::from_f32(0.0, 0.3, 0.0), health)
+    }
+}
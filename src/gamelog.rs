@@ -1,3 +1,49 @@
+/// Default cap on the number of entries a [`GameLog`] holds on to.
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+/// A single message in the [`GameLog`], tagged with the turn it happened on.
+pub struct LogEntry {
+    pub turn: i32,
+    pub text: String,
+}
+
+/// Resource holding the messages shown to the player, oldest first.
 pub struct GameLog {
-    pub entries: Vec<String>,
+    pub entries: Vec<LogEntry>,
+    /// Maximum number of entries to keep around; older ones are dropped.
+    pub max_entries: usize,
+}
+
+impl GameLog {
+    pub fn new(max_entries: usize) -> GameLog {
+        GameLog {
+            entries: Vec::new(),
+            max_entries,
+        }
+    }
+
+    /// Records `text` as happening on `turn`, dropping the oldest entries if
+    /// the log has grown past `max_entries`.
+    pub fn push<S: ToString>(&mut self, turn: i32, text: S) {
+        self.entries.push(LogEntry {
+            turn,
+            text: text.to_string(),
+        });
+        if self.entries.len() > self.max_entries {
+            let excess = self.entries.len() - self.max_entries;
+            self.entries.drain(0..excess);
+        }
+    }
+}
+
+impl Default for GameLog {
+    fn default() -> GameLog {
+        GameLog::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+/// Resource counting the turns the player has taken so far.
+#[derive(Default, Clone, Copy)]
+pub struct TurnCounter {
+    pub turn: i32,
 }
@@ -2,14 +2,27 @@ use rltk::{Point, Rltk, VirtualKeyCode, RGB};
 use specs::prelude::*;
 use std::collections::BTreeMap;
 
-use crate::rex_assets::RexAssets;
+use crate::{
+    camera::Camera,
+    difficulty::Difficulty,
+    monster_ai_system::NORMAL_SPEED,
+    rex_assets::RexAssets,
+    seed::GameSeed,
+    settings::{GameSettings, SettingsOption},
+};
 
-use super::{components::*, gamelog::GameLog, Map, RunState, State};
+use super::{
+    components::*,
+    gamelog::{GameLog, TurnCounter},
+    Map, RunState, State, TileType,
+};
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuSelection {
     NewGame,
+    SeededGame,
     LoadGame,
+    Options,
     Quit,
 }
 
@@ -25,8 +38,42 @@ pub enum GameOverResult {
     QuitToMenu,
 }
 
+/// Resource remembering where the mouse was on the last menu frame.
+#[derive(Default)]
+pub struct MenuMouse {
+    last_pos: (i32, i32),
+}
+
+/// Whether the mouse has moved since the last menu frame. Menus only let hovering take over
+/// the selection when it has, so a resting mouse doesn't fight the arrow keys.
+fn mouse_moved(gs: &State, ctx: &Rltk) -> bool {
+    let pos = ctx.mouse_pos();
+    let mut mouse = gs.ecs.write_resource::<MenuMouse>();
+    let moved = mouse.last_pos != pos;
+    mouse.last_pos = pos;
+    moved
+}
+
+/// Whether the mouse is over `text`, as printed centered on `row`.
+fn hovering_centered(ctx: &Rltk, row: i32, text: &str) -> bool {
+    let (width, _) = ctx.get_char_size();
+    let x = width as i32 / 2 - text.len() as i32 / 2;
+    let (mouse_x, mouse_y) = ctx.mouse_pos();
+    mouse_y == row && mouse_x >= x && mouse_x < x + text.len() as i32
+}
+
+/// Lines of the main menu, in the order they're listed.
+const MAIN_MENU: [(MainMenuSelection, &str); 5] = [
+    (MainMenuSelection::NewGame, "Begin New Game"),
+    (MainMenuSelection::SeededGame, "Begin Game From Seed"),
+    (MainMenuSelection::LoadGame, "Load Game"),
+    (MainMenuSelection::Options, "Options"),
+    (MainMenuSelection::Quit, "Quit"),
+];
+
+/// Draws the main menu, navigated with the arrow keys and ENTER or by pointing and clicking.
 pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
-    let save_exists = super::saveload_system::does_save_exist();
+    let save_exists = super::saveload_system::any_save_exists();
     let runstate = gs.ecs.fetch::<RunState>();
     let assets = gs.ecs.fetch::<RexAssets>();
     ctx.render_xp_sprite(&assets.menu, 0, 0);
@@ -42,49 +89,45 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
         menu_selection: selection,
     } = *runstate
     {
-        if selection == MainMenuSelection::NewGame {
-            ctx.print_color_centered(
-                24,
-                RGB::named(rltk::MAGENTA),
-                RGB::named(rltk::BLACK),
-                "Begin New Game",
-            );
-        } else {
-            ctx.print_color_centered(
-                24,
-                RGB::named(rltk::WHITE),
-                RGB::named(rltk::BLACK),
-                "Begin New Game",
-            );
+        // The mouse picks out whatever it's over, once it moves.
+        let moved = mouse_moved(gs, ctx);
+        let mut hovered = None;
+        for (i, (entry, label)) in MAIN_MENU.iter().enumerate() {
+            let row = 24 + i as i32;
+            if *entry == MainMenuSelection::LoadGame && !save_exists {
+                continue;
+            }
+            if hovering_centered(ctx, row, label) {
+                hovered = Some(*entry);
+            }
         }
-
-        if save_exists {
-            if selection == MainMenuSelection::LoadGame {
-                ctx.print_color_centered(
-                    25,
-                    RGB::named(rltk::MAGENTA),
-                    RGB::named(rltk::BLACK),
-                    "Load Game",
-                );
-            } else {
-                ctx.print_color_centered(
-                    25,
-                    RGB::named(rltk::WHITE),
-                    RGB::named(rltk::BLACK),
-                    "Load Game",
-                );
+        let selection = match hovered {
+            Some(hovered) if moved => hovered,
+            _ => selection,
+        };
+        for (i, (entry, label)) in MAIN_MENU.iter().enumerate() {
+            if *entry == MainMenuSelection::LoadGame && !save_exists {
+                continue;
             }
+            let fg = if *entry == selection {
+                RGB::named(rltk::MAGENTA)
+            } else {
+                RGB::named(rltk::WHITE)
+            };
+            ctx.print_color_centered(24 + i as i32, fg, RGB::named(rltk::BLACK), label);
         }
 
-        if selection == MainMenuSelection::Quit {
-            ctx.print_color_centered(
-                26,
-                RGB::named(rltk::MAGENTA),
-                RGB::named(rltk::BLACK),
-                "Quit",
-            );
-        } else {
-            ctx.print_color_centered(26, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Quit");
+        // Show the seed the current world was generated from so it can be shared.
+        let seed = gs.ecs.fetch::<GameSeed>();
+        ctx.print_color_centered(
+            30,
+            RGB::named(rltk::GRAY),
+            RGB::named(rltk::BLACK),
+            &format!("Seed: {}", seed.seed),
+        );
+
+        if let (Some(selected), true) = (hovered, ctx.left_click) {
+            return MainMenuResult::Selected { selected };
         }
 
         match ctx.key {
@@ -102,11 +145,13 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                 VirtualKeyCode::Up => {
                     let mut new_selection = match selection {
                         MainMenuSelection::NewGame => MainMenuSelection::Quit,
-                        MainMenuSelection::LoadGame => MainMenuSelection::NewGame,
-                        MainMenuSelection::Quit => MainMenuSelection::LoadGame,
+                        MainMenuSelection::SeededGame => MainMenuSelection::NewGame,
+                        MainMenuSelection::LoadGame => MainMenuSelection::SeededGame,
+                        MainMenuSelection::Options => MainMenuSelection::LoadGame,
+                        MainMenuSelection::Quit => MainMenuSelection::Options,
                     };
                     if new_selection == MainMenuSelection::LoadGame && !save_exists {
-                        new_selection = MainMenuSelection::NewGame;
+                        new_selection = MainMenuSelection::SeededGame;
                     }
                     return MainMenuResult::NoSelection {
                         selected: new_selection,
@@ -114,12 +159,14 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                 }
                 VirtualKeyCode::Down => {
                     let mut new_selection = match selection {
-                        MainMenuSelection::NewGame => MainMenuSelection::LoadGame,
-                        MainMenuSelection::LoadGame => MainMenuSelection::Quit,
+                        MainMenuSelection::NewGame => MainMenuSelection::SeededGame,
+                        MainMenuSelection::SeededGame => MainMenuSelection::LoadGame,
+                        MainMenuSelection::LoadGame => MainMenuSelection::Options,
+                        MainMenuSelection::Options => MainMenuSelection::Quit,
                         MainMenuSelection::Quit => MainMenuSelection::NewGame,
                     };
                     if new_selection == MainMenuSelection::LoadGame && !save_exists {
-                        new_selection = MainMenuSelection::NewGame;
+                        new_selection = MainMenuSelection::Options;
                     }
                     return MainMenuResult::NoSelection {
                         selected: new_selection,
@@ -144,6 +191,311 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     }
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum DifficultyMenuResult {
+    Cancel,
+    NoSelection { selected: Difficulty },
+    Selected { selected: Difficulty },
+}
+
+/// Asks how hard the new game should be.
+pub fn difficulty_menu(gs: &mut State, ctx: &mut Rltk) -> DifficultyMenuResult {
+    let runstate = gs.ecs.fetch::<RunState>();
+    let assets = gs.ecs.fetch::<RexAssets>();
+    ctx.render_xp_sprite(&assets.menu, 0, 0);
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Choose a difficulty",
+    );
+
+    let options = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+    let moved = mouse_moved(gs, ctx);
+    let hovered = options
+        .iter()
+        .enumerate()
+        .find(|(i, option)| hovering_centered(ctx, 24 + *i as i32, option.name()))
+        .map(|(_, option)| *option);
+    let selection = match *runstate {
+        RunState::DifficultySelect { selection, .. } => match hovered {
+            Some(hovered) if moved => hovered,
+            _ => selection,
+        },
+        _ => Difficulty::default(),
+    };
+
+    for (i, option) in options.iter().enumerate() {
+        let fg = if *option == selection {
+            RGB::named(rltk::MAGENTA)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color_centered(24 + i as i32, fg, RGB::named(rltk::BLACK), option.name());
+    }
+    ctx.print_color_centered(
+        28,
+        RGB::named(rltk::GRAY),
+        RGB::named(rltk::BLACK),
+        "ENTER to begin, ESCAPE to go back",
+    );
+
+    if let (Some(selected), true) = (hovered, ctx.left_click) {
+        return DifficultyMenuResult::Selected { selected };
+    }
+    match ctx.key {
+        None => DifficultyMenuResult::NoSelection {
+            selected: selection,
+        },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => DifficultyMenuResult::Cancel,
+            VirtualKeyCode::Up => DifficultyMenuResult::NoSelection {
+                selected: selection.prev(),
+            },
+            VirtualKeyCode::Down => DifficultyMenuResult::NoSelection {
+                selected: selection.next(),
+            },
+            VirtualKeyCode::Return => DifficultyMenuResult::Selected {
+                selected: selection,
+            },
+            _ => DifficultyMenuResult::NoSelection {
+                selected: selection,
+            },
+        },
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum OptionsMenuResult {
+    Cancel,
+    NoSelection { selected: usize },
+    Toggle { selected: usize },
+}
+
+/// Lists the settings that can be changed, with their current values.
+pub fn options_menu(gs: &mut State, ctx: &mut Rltk) -> OptionsMenuResult {
+    let options = &SettingsOption::ALL;
+    let runstate = gs.ecs.fetch::<RunState>();
+    let settings = gs.ecs.fetch::<GameSettings>();
+    let assets = gs.ecs.fetch::<RexAssets>();
+    ctx.render_xp_sprite(&assets.menu, 0, 0);
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Options",
+    );
+
+    let labels: Vec<String> = options
+        .iter()
+        .map(|option| format!("{}: {}", option.name(), settings.value(*option)))
+        .collect();
+    let moved = mouse_moved(gs, ctx);
+    let hovered = (0..labels.len()).find(|i| hovering_centered(ctx, 24 + *i as i32, &labels[*i]));
+    let selection = match *runstate {
+        RunState::Options { selection } => match hovered {
+            Some(hovered) if moved => hovered,
+            _ => selection,
+        },
+        _ => 0,
+    };
+    for (i, label) in labels.iter().enumerate() {
+        let fg = if i == selection {
+            RGB::named(rltk::MAGENTA)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color_centered(24 + i as i32, fg, RGB::named(rltk::BLACK), label);
+    }
+    ctx.print_color_centered(
+        24 + options.len() as i32 + 1,
+        RGB::named(rltk::GRAY),
+        RGB::named(rltk::BLACK),
+        "ENTER to toggle, ESCAPE to go back",
+    );
+
+    if let (Some(selected), true) = (hovered, ctx.left_click) {
+        return OptionsMenuResult::Toggle { selected };
+    }
+    match ctx.key {
+        None => OptionsMenuResult::NoSelection {
+            selected: selection,
+        },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => OptionsMenuResult::Cancel,
+            VirtualKeyCode::Up => OptionsMenuResult::NoSelection {
+                selected: (selection + options.len() - 1) % options.len(),
+            },
+            VirtualKeyCode::Down => OptionsMenuResult::NoSelection {
+                selected: (selection + 1) % options.len(),
+            },
+            VirtualKeyCode::Return => OptionsMenuResult::Toggle {
+                selected: selection,
+            },
+            _ => OptionsMenuResult::NoSelection {
+                selected: selection,
+            },
+        },
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LoadMenuResult {
+    Cancel,
+    NoSelection { selected: usize },
+    Selected { selected: usize },
+}
+
+/// Lists the save slots that have a game in them, with the depth and turn each was saved on.
+pub fn load_menu(gs: &mut State, ctx: &mut Rltk) -> LoadMenuResult {
+    let selection = match *gs.ecs.fetch::<RunState>() {
+        RunState::LoadMenu { selection } => Some(selection),
+        _ => None,
+    };
+    save_slot_menu(
+        gs,
+        ctx,
+        "Load Game",
+        "ENTER to load, ESCAPE to go back",
+        selection,
+    )
+}
+
+/// Asks which save to overwrite when a new game finds every slot taken.
+pub fn overwrite_menu(gs: &mut State, ctx: &mut Rltk) -> LoadMenuResult {
+    let selection = match *gs.ecs.fetch::<RunState>() {
+        RunState::OverwriteSlot { selection, .. } => Some(selection),
+        _ => None,
+    };
+    save_slot_menu(
+        gs,
+        ctx,
+        "Every slot is full: pick a save to replace",
+        "ENTER to overwrite, ESCAPE to go back",
+        selection,
+    )
+}
+
+/// Lists the filled save slots under `title`, for picking one of them.
+fn save_slot_menu(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    title: &str,
+    prompt: &str,
+    selection: Option<usize>,
+) -> LoadMenuResult {
+    let headers = gs.ecs.fetch::<super::saveload_system::SaveSlotHeaders>();
+    let assets = gs.ecs.fetch::<RexAssets>();
+    ctx.render_xp_sprite(&assets.menu, 0, 0);
+
+    ctx.print_color_centered(15, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), title);
+
+    let slots: Vec<usize> = headers.headers.iter().map(|(slot, _)| *slot).collect();
+    if slots.is_empty() {
+        return LoadMenuResult::Cancel;
+    }
+
+    // Fall back to the first listed slot if the selected one has gone away.
+    let selection = match selection {
+        Some(selection) if slots.contains(&selection) => selection,
+        _ => slots[0],
+    };
+
+    let lines: Vec<(usize, String, bool)> = headers
+        .headers
+        .iter()
+        .map(|(slot, header)| {
+            let text = if header.is_compatible() {
+                format!(
+                    "Slot {}: Depth {}, Turn {}",
+                    slot + 1,
+                    header.depth,
+                    header.turn
+                )
+            } else {
+                format!("Slot {}: Incompatible save", slot + 1)
+            };
+            (*slot, text, header.is_compatible())
+        })
+        .collect();
+    let moved = mouse_moved(gs, ctx);
+    let hovered = lines
+        .iter()
+        .enumerate()
+        .find(|(i, (_, text, _))| hovering_centered(ctx, 24 + *i as i32, text))
+        .map(|(_, (slot, _, _))| *slot);
+    let selection = match hovered {
+        Some(hovered) if moved => hovered,
+        _ => selection,
+    };
+
+    for (i, (slot, text, compatible)) in lines.iter().enumerate() {
+        let fg = if *slot == selection {
+            RGB::named(rltk::MAGENTA)
+        } else if !compatible {
+            RGB::named(rltk::GRAY)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color_centered(24 + i as i32, fg, RGB::named(rltk::BLACK), text);
+    }
+    ctx.print_color_centered(
+        24 + lines.len() as i32 + 1,
+        RGB::named(rltk::GRAY),
+        RGB::named(rltk::BLACK),
+        prompt,
+    );
+
+    if let (Some(selected), true) = (hovered, ctx.left_click) {
+        return LoadMenuResult::Selected { selected };
+    }
+    let position = slots.iter().position(|slot| *slot == selection).unwrap();
+    match ctx.key {
+        None => LoadMenuResult::NoSelection {
+            selected: selection,
+        },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => LoadMenuResult::Cancel,
+            VirtualKeyCode::Up => LoadMenuResult::NoSelection {
+                selected: slots[(position + slots.len() - 1) % slots.len()],
+            },
+            VirtualKeyCode::Down => LoadMenuResult::NoSelection {
+                selected: slots[(position + 1) % slots.len()],
+            },
+            VirtualKeyCode::Return => LoadMenuResult::Selected {
+                selected: selection,
+            },
+            _ => LoadMenuResult::NoSelection {
+                selected: selection,
+            },
+        },
+    }
+}
+
+/// Icons (glyph and color) for the status effects active on `ent`.
+fn status_effects(ecs: &World, ent: Entity) -> Vec<(char, (u8, u8, u8))> {
+    let mut effects = Vec::new();
+    if ecs.read_storage::<Confusion>().contains(ent) {
+        effects.push(('?', rltk::MAGENTA));
+    }
+    if ecs.read_storage::<Telepathy>().contains(ent) {
+        effects.push(('*', rltk::CYAN));
+    }
+    if ecs.read_storage::<Poisoned>().contains(ent) {
+        effects.push(('♣', rltk::GREEN));
+    }
+    if let Some(speed) = ecs.read_storage::<Speed>().get(ent) {
+        if speed.speed > NORMAL_SPEED {
+            effects.push(('»', rltk::YELLOW));
+        } else if speed.speed < NORMAL_SPEED {
+            effects.push(('«', rltk::LIGHT_BLUE));
+        }
+    }
+    effects
+}
+
 /// Draws the UI to the bottom of the screen.
 pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     ctx.draw_box(
@@ -169,6 +521,27 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         &depth,
     );
 
+    // What the player has to spend.
+    if let Some(gold) = ecs.read_storage::<Gold>().get(*ecs.fetch::<Entity>()) {
+        ctx.print_color(
+            16,
+            49,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            &format!("Gold: {}", gold.amount),
+        );
+    }
+
+    // How long the player has survived, on the bottom edge of the box.
+    let turn = format!("Turn: {}", ecs.fetch::<TurnCounter>().turn);
+    ctx.print_color(
+        2,
+        49,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        &turn,
+    );
+
     for (_player, stats, hc) in (&players, &combat_stats, &hunger).join() {
         let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
         ctx.print_color(
@@ -181,58 +554,46 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         ctx.draw_bar_horizontal(
             28,
             43,
-            51,
+            28,
             stats.hp,
             stats.max_hp,
             RGB::named(rltk::RED),
             RGB::named(rltk::BLACK),
         );
 
-        match hc.state {
-            HungerState::WellFed => {
-                ctx.print_color(
-                    71,
-                    42,
-                    RGB::named(rltk::GREEN),
-                    RGB::named(rltk::BLACK),
-                    "Well Fed",
-                );
-            }
-            HungerState::Normal => {
-                ctx.print_color(
-                    71,
-                    42,
-                    RGB::named(rltk::WHITE),
-                    RGB::named(rltk::BLACK),
-                    "Normal",
-                );
-            }
-            HungerState::Hungry => {
-                ctx.print_color(
-                    71,
-                    42,
-                    RGB::named(rltk::ORANGE),
-                    RGB::named(rltk::BLACK),
-                    "Hungry",
-                );
-            }
-            HungerState::Starving => {
-                ctx.print_color(
-                    71,
-                    42,
-                    RGB::named(rltk::RED),
-                    RGB::named(rltk::BLACK),
-                    "Starving",
-                );
-            }
-        }
+        // Hunger shifts from green to red as the player gets hungrier.
+        let (hunger_color, hunger_text) = match hc.state {
+            HungerState::WellFed => (rltk::GREEN, "Well Fed"),
+            HungerState::Normal => (rltk::WHITE, "Normal"),
+            HungerState::Hungry => (rltk::ORANGE, "Hungry"),
+            HungerState::Starving => (rltk::RED, "Starving"),
+        };
+        ctx.print_color(
+            58,
+            43,
+            RGB::named(hunger_color),
+            RGB::named(rltk::BLACK),
+            hunger_text,
+        );
+    }
+
+    // One icon for each effect the player is under.
+    let player_ent = ecs.fetch::<Entity>();
+    for (i, (glyph, color)) in status_effects(ecs, *player_ent).iter().enumerate() {
+        ctx.set(
+            68 + i as i32,
+            43,
+            RGB::named(*color),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(*glyph),
+        );
     }
 
     let log = ecs.fetch::<GameLog>();
     let mut y = 44;
-    for s in log.entries.iter().rev() {
+    for entry in log.entries.iter().rev() {
         if y < 49 {
-            ctx.print(2, y, s);
+            ctx.print(2, y, &entry.text);
         }
         y += 1;
     }
@@ -247,33 +608,133 @@ pub enum ItemMenuResult {
     Cancel,
     NoResponse,
     Selected,
+    TurnPage { page: usize },
+    Hotkey { slot: usize },
 }
 
-pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-    let player_ent = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let entities = gs.ecs.entities();
+/// Combined melee power and defense an item gives when worn.
+///
+/// A ranged weapon's damage only counts when firing it, so it isn't scored here.
+fn gear_score(ecs: &World, item: Entity) -> i32 {
+    let power = ecs
+        .read_storage::<MeleePowerBonus>()
+        .get(item)
+        .map_or(0, |bonus| bonus.power)
+        + ecs
+            .read_storage::<Weapon>()
+            .get(item)
+            .filter(|weapon| weapon.attack_kind == AttackKind::Melee)
+            .map_or(0, |weapon| weapon.damage);
+    let defense = ecs
+        .read_storage::<DefenseBonus>()
+        .get(item)
+        .map_or(0, |bonus| bonus.defense);
+    power + defense
+}
 
-    // Map item names to the number of each in the player's inventory.
-    let mut inventory: BTreeMap<String, (i32, specs::world::Index)> = BTreeMap::new();
-    for (ent, _, name) in (&entities, &backpack, &names)
+/// Compares an equippable `item` with whatever `owner` is wearing that it would replace,
+/// giving an arrow (or `=`) and its color. Returns `None` for things that can't be worn.
+fn upgrade_hint(ecs: &World, owner: Entity, item: Entity) -> Option<(char, (u8, u8, u8))> {
+    let slot = ecs.read_storage::<Equippable>().get(item)?.slot;
+    let entities = ecs.entities();
+    let equipped = ecs.read_storage::<Equipped>();
+
+    // Two-handed weapons are measured against the weapon and shield together.
+    let incumbent: i32 = (&entities, &equipped)
         .join()
-        .filter(|item| item.1.owner == *player_ent)
-    {
-        if let Some(val) = inventory.get_mut(&name.name) {
-            *val = (val.0 + 1, ent.id());
-        } else {
-            inventory.insert(name.name.clone(), (1, ent.id()));
+        .filter(|(_, worn)| worn.owner == owner && worn.slot.conflicts_with(slot))
+        .map(|(ent, _)| gear_score(ecs, ent))
+        .sum();
+    let candidate = gear_score(ecs, item);
+
+    Some(match candidate.cmp(&incumbent) {
+        std::cmp::Ordering::Greater => ('↑', rltk::GREEN),
+        std::cmp::Ordering::Less => ('↓', rltk::RED),
+        std::cmp::Ordering::Equal => ('=', rltk::GREY),
+    })
+}
+
+/// Number of item stacks shown on each page of the inventory menus.
+const INVENTORY_PAGE_SIZE: usize = 15;
+
+/// `owner`'s backpack stacked by name: each entry is the name, how many of it they carry and
+/// one of those items. Sorted by name.
+fn stacked_backpack(ecs: &World, owner: Entity) -> Vec<(String, i32, Entity)> {
+    let carried: Vec<Entity> = {
+        let backpack = ecs.read_storage::<InBackpack>();
+        let entities = ecs.entities();
+        (&entities, &backpack)
+            .join()
+            .filter(|item| item.1.owner == owner)
+            .map(|(ent, _)| ent)
+            .collect()
+    };
+    stack_items(ecs, carried)
+}
+
+/// Stacks `items` by name, the same way [`stacked_backpack`] does.
+fn stack_items(ecs: &World, items: Vec<Entity>) -> Vec<(String, i32, Entity)> {
+    let names = ecs.read_storage::<Name>();
+    let ammo = ecs.read_storage::<Ammo>();
+
+    // Map item names to the number of each; ammo counts every shot.
+    let mut stacks: BTreeMap<String, (i32, Entity)> = BTreeMap::new();
+    for ent in items {
+        if let Some(name) = names.get(ent) {
+            let stack = stacks.entry(name.name.clone()).or_insert((0, ent));
+            *stack = (stack.0 + ammo.get(ent).map_or(1, |ammo| ammo.count), ent);
         }
     }
-    let count = inventory.len();
+    stacks
+        .into_iter()
+        .map(|(name, (count, ent))| (name, count, ent))
+        .collect()
+}
+
+/// What's shown at the end of each line of an item menu.
+#[derive(PartialEq, Copy, Clone)]
+enum ItemMenuHint {
+    None,
+    /// Gear is flagged by how it compares with what the player is wearing.
+    Upgrades,
+    /// What the shop charges for the item.
+    BuyPrice,
+    /// What the shop pays for the item.
+    SellPrice,
+}
+
+/// Draws one page of a stacked item menu and handles its input.
+///
+/// Letters pick from the current page; PAGE UP/PAGE DOWN flip between pages. `hint` says
+/// what, if anything, goes at the end of each line.
+fn paged_item_menu(
+    ecs: &World,
+    ctx: &mut Rltk,
+    title: &str,
+    stacks: &[(String, i32, Entity)],
+    page: usize,
+    hint: ItemMenuHint,
+) -> (ItemMenuResult, Option<Entity>) {
+    let player_ent = ecs.fetch::<Entity>();
+    let pages = usize::max(
+        1,
+        (stacks.len() + INVENTORY_PAGE_SIZE - 1) / INVENTORY_PAGE_SIZE,
+    );
+    let page = usize::min(page, pages - 1);
+    let shown = &stacks
+        [page * INVENTORY_PAGE_SIZE..usize::min(stacks.len(), (page + 1) * INVENTORY_PAGE_SIZE)];
+    let count = shown.len();
+    // Prices get a column of their own.
+    let width = match hint {
+        ItemMenuHint::BuyPrice | ItemMenuHint::SellPrice => 41,
+        _ => 31,
+    };
 
     let mut y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
-        31,
+        width,
         (count + 3) as i32,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
@@ -283,19 +744,22 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         y - 2,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        "Inventory",
+        title,
     );
+    let footer = if pages > 1 {
+        format!("page {}/{} - ESCAPE to cancel", page + 1, pages)
+    } else {
+        "ESCAPE to cancel".to_string()
+    };
     ctx.print_color(
         18,
         y + count as i32 + 1,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        "ESCAPE to cancel",
+        footer,
     );
 
-    let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (k, v) in inventory.iter() {
+    for (j, (name, stack, item)) in shown.iter().enumerate() {
         ctx.set(
             17,
             y,
@@ -317,23 +781,64 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             RGB::named(rltk::BLACK),
             rltk::to_cp437(')'),
         );
-        ctx.print(21, y, format!("{} ({})", &k, &v.0));
-        equippable.push(entities.entity(v.1));
+        ctx.print(21, y, format!("{} ({})", name, stack));
+        match hint {
+            ItemMenuHint::None => {}
+            // Flag gear that would be an upgrade (or not) over what's being worn.
+            ItemMenuHint::Upgrades => {
+                if let Some((arrow, color)) = upgrade_hint(ecs, *player_ent, *item) {
+                    ctx.set(
+                        44,
+                        y,
+                        RGB::named(color),
+                        RGB::named(rltk::BLACK),
+                        rltk::to_cp437(arrow),
+                    );
+                }
+            }
+            ItemMenuHint::BuyPrice | ItemMenuHint::SellPrice => {
+                let price = if hint == ItemMenuHint::BuyPrice {
+                    ecs.read_storage::<Price>().get(*item).map(|p| p.value)
+                } else {
+                    super::shop::sale_price(ecs, *item)
+                };
+                if let Some(price) = price {
+                    ctx.print_color(
+                        48,
+                        y,
+                        RGB::named(rltk::GOLD),
+                        RGB::named(rltk::BLACK),
+                        &format!("{:>5}", price),
+                    );
+                }
+            }
+        }
         y += 1;
-        j += 1;
     }
 
     match ctx.key {
         None => (ItemMenuResult::NoResponse, None),
         Some(key) => match key {
             VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
+            VirtualKeyCode::PageUp => (
+                ItemMenuResult::TurnPage {
+                    page: page.saturating_sub(1),
+                },
+                None,
+            ),
+            VirtualKeyCode::PageDown => (
+                ItemMenuResult::TurnPage {
+                    page: usize::min(page + 1, pages - 1),
+                },
+                None,
+            ),
             _ => {
+                if let Some(slot) = super::hotbar::slot_for_key(key) {
+                    return (ItemMenuResult::Hotkey { slot }, None);
+                }
                 let selection = rltk::letter_to_option(key);
                 if selection > -1 && selection < count as i32 {
-                    return (
-                        ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
-                    );
+                    return (ItemMenuResult::Selected, Some(shown[selection as usize].2));
                 }
                 (ItemMenuResult::NoResponse, None)
             }
@@ -341,32 +846,145 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     }
 }
 
-pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+/// Shows the player's backpack. Pressing a number key sets `hotkey`, and the next item
+/// picked is bound to that key instead of being used.
+pub fn show_inventory(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    page: usize,
+    hotkey: Option<usize>,
+) -> (ItemMenuResult, Option<Entity>) {
+    let player_ent = *gs.ecs.fetch::<Entity>();
+    let stacks = stacked_backpack(&gs.ecs, player_ent);
+    let carried: i32 = stacks.iter().map(|(_, n, _)| n).sum();
+    let title = match (
+        hotkey,
+        gs.ecs.read_storage::<PackCapacity>().get(player_ent),
+    ) {
+        (Some(slot), _) => format!("Bind What To Key {}?", slot + 1),
+        (None, Some(capacity)) => format!("Inventory ({}/{})", carried, capacity.max_items),
+        (None, None) => "Inventory".to_string(),
+    };
+    paged_item_menu(&gs.ecs, ctx, &title, &stacks, page, ItemMenuHint::Upgrades)
+}
+
+/// Which side of the counter the shop screen is showing.
+#[derive(PartialEq, Copy, Clone)]
+pub enum ShopMode {
+    Buy,
+    Sell,
+}
+
+impl ShopMode {
+    pub fn other(self) -> ShopMode {
+        match self {
+            ShopMode::Buy => ShopMode::Sell,
+            ShopMode::Sell => ShopMode::Buy,
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ShopResult {
+    Cancel,
+    NoResponse,
+    SwitchMode,
+    TurnPage { page: usize },
+    Selected { item: Entity },
+}
+
+/// Shows the shop: the stock for sale when buying, or the player's backpack when selling.
+pub fn show_shop(gs: &mut State, ctx: &mut Rltk, mode: ShopMode, page: usize) -> ShopResult {
+    if ctx.key == Some(VirtualKeyCode::Tab) {
+        return ShopResult::SwitchMode;
+    }
+    let player_ent = *gs.ecs.fetch::<Entity>();
+    let gold = gs
+        .ecs
+        .read_storage::<Gold>()
+        .get(player_ent)
+        .map_or(0, |g| g.amount);
+
+    let (title, stacks, hint) = match mode {
+        ShopMode::Buy => {
+            let stock: Vec<Entity> = {
+                let entities = gs.ecs.entities();
+                let prices = gs.ecs.read_storage::<Price>();
+                let positions = gs.ecs.read_storage::<Position>();
+                (&entities, &prices, &positions)
+                    .join()
+                    .map(|(ent, _, _)| ent)
+                    .collect()
+            };
+            (
+                format!("Buy ({} gold) - TAB to sell", gold),
+                stack_items(&gs.ecs, stock),
+                ItemMenuHint::BuyPrice,
+            )
+        }
+        ShopMode::Sell => (
+            format!("Sell ({} gold) - TAB to buy", gold),
+            stacked_backpack(&gs.ecs, player_ent)
+                .into_iter()
+                .filter(|(_, _, item)| super::shop::sale_price(&gs.ecs, *item).is_some())
+                .collect(),
+            ItemMenuHint::SellPrice,
+        ),
+    };
+
+    match paged_item_menu(&gs.ecs, ctx, &title, &stacks, page, hint) {
+        (ItemMenuResult::Cancel, _) => ShopResult::Cancel,
+        (ItemMenuResult::TurnPage { page }, _) => ShopResult::TurnPage { page },
+        (ItemMenuResult::Selected, Some(item)) => ShopResult::Selected { item },
+        _ => ShopResult::NoResponse,
+    }
+}
+
+pub fn drop_item_menu(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    page: usize,
+) -> (ItemMenuResult, Option<Entity>) {
+    let player_ent = *gs.ecs.fetch::<Entity>();
+    let stacks = stacked_backpack(&gs.ecs, player_ent);
+    paged_item_menu(
+        &gs.ecs,
+        ctx,
+        "Drop Which Item?",
+        &stacks,
+        page,
+        ItemMenuHint::None,
+    )
+}
+
+/// Lists only the food in the player's backpack.
+pub fn eat_menu(gs: &mut State, ctx: &mut Rltk, page: usize) -> (ItemMenuResult, Option<Entity>) {
+    let player_ent = *gs.ecs.fetch::<Entity>();
+    let food = gs.ecs.read_storage::<ProvidesFood>();
+    let stacks: Vec<(String, i32, Entity)> = stacked_backpack(&gs.ecs, player_ent)
+        .into_iter()
+        .filter(|(_, _, item)| food.contains(*item))
+        .collect();
+    paged_item_menu(&gs.ecs, ctx, "Eat What?", &stacks, page, ItemMenuHint::None)
+}
+
+pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_ent = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
+    let backpack = gs.ecs.read_storage::<Equipped>();
     let entities = gs.ecs.entities();
 
-    // Map item names to the number of each in the player's inventory.
-    let mut inventory: BTreeMap<String, (i32, specs::world::Index)> = BTreeMap::new();
-    for (ent, _, name) in (&entities, &backpack, &names)
+    let count = (&backpack, &names)
         .join()
-        .filter(|item| item.1.owner == *player_ent)
-    {
-        if let Some(val) = inventory.get_mut(&name.name) {
-            *val = (val.0 + 1, ent.id());
-        } else {
-            inventory.insert(name.name.clone(), (1, ent.id()));
-        }
-    }
-    let count = inventory.len();
+        .filter(|item| item.0.owner == *player_ent)
+        .count();
 
     let mut y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
         31,
-        (count + 3) as i32,
+        count + 3,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
     );
@@ -375,7 +993,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         y - 2,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        "Drop Which Item?",
+        "Remove Which Items?",
     );
     ctx.print_color(
         18,
@@ -387,7 +1005,10 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
 
     let mut equippable: Vec<Entity> = Vec::new();
     let mut j = 0;
-    for (k, v) in inventory.iter() {
+    for (ent, _pack, name) in (&entities, &backpack, &names)
+        .join()
+        .filter(|item| item.1.owner == *player_ent)
+    {
         ctx.set(
             17,
             y,
@@ -409,8 +1030,9 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             RGB::named(rltk::BLACK),
             rltk::to_cp437(')'),
         );
-        ctx.print(21, y, format!("{} ({})", &k, &v.0));
-        equippable.push(entities.entity(v.1));
+
+        ctx.print(21, y, &name.name.to_string());
+        equippable.push(ent);
         y += 1;
         j += 1;
     }
@@ -433,109 +1055,367 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     }
 }
 
-pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-    let player_ent = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<Equipped>();
+#[derive(PartialEq, Copy, Clone)]
+pub enum LogMenuResult {
+    Cancel,
+    NoResponse { offset: usize },
+}
+
+/// Shows the full message log, scrolled `offset` lines up from the newest entry.
+pub fn show_log(gs: &mut State, ctx: &mut Rltk, offset: usize) -> LogMenuResult {
+    // Number of log lines that fit in the box at once.
+    const PAGE_SIZE: usize = 44;
+
+    let log = gs.ecs.fetch::<GameLog>();
+    let total = log.entries.len();
+    let offset = usize::min(offset, total.saturating_sub(PAGE_SIZE));
+
+    ctx.draw_box(
+        1,
+        1,
+        77,
+        (PAGE_SIZE + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        4,
+        1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Message Log",
+    );
+    ctx.print_color(
+        4,
+        (PAGE_SIZE + 4) as i32,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "PAGE UP/PAGE DOWN to scroll, ESCAPE to close",
+    );
+
+    // Newest entries sit at the bottom of the box, like the log under the map.
+    let end = total - offset;
+    let start = end.saturating_sub(PAGE_SIZE);
+    let mut y = 3;
+    for entry in log.entries[start..end].iter() {
+        ctx.print(3, y, format!("[{:>4}] {}", entry.turn, &entry.text));
+        y += 1;
+    }
+
+    match ctx.key {
+        None => LogMenuResult::NoResponse { offset },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => LogMenuResult::Cancel,
+            VirtualKeyCode::PageUp => LogMenuResult::NoResponse {
+                offset: offset + PAGE_SIZE,
+            },
+            VirtualKeyCode::PageDown => LogMenuResult::NoResponse {
+                offset: offset.saturating_sub(PAGE_SIZE),
+            },
+            _ => LogMenuResult::NoResponse { offset },
+        },
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MapViewResult {
+    Cancel,
+    NoResponse { center: Point },
+}
+
+/// How far the overview pans for each arrow key press.
+const MAP_PAN_STEP: i32 = 5;
+
+/// Shows every revealed tile of the level, centered on `center`, with the player and known items.
+pub fn show_map(gs: &mut State, ctx: &mut Rltk, center: Point) -> MapViewResult {
+    let map = gs.ecs.fetch::<Map>();
     let entities = gs.ecs.entities();
+    let positions = gs.ecs.read_storage::<Position>();
+    let renderables = gs.ecs.read_storage::<Renderable>();
+    let items = gs.ecs.read_storage::<Item>();
+    let hidden = gs.ecs.read_storage::<Hidden>();
+    let player_ent = gs.ecs.fetch::<Entity>();
 
-    let count = (&backpack, &names)
+    // Keep the center on the map, so panning stops at its edges.
+    let center = Point::new(
+        i32::min(map.width - 1, i32::max(0, center.x)),
+        i32::min(map.height - 1, i32::max(0, center.y)),
+    );
+    let mut camera = Camera::new();
+    camera.center_on(&map, center);
+
+    // `draw_map` already limits itself to revealed tiles, stairs included.
+    super::map::draw_map(&map, &camera, ctx);
+
+    let mut data = (&entities, &positions, &renderables, !&hidden)
         .join()
-        .filter(|item| item.0.owner == *player_ent)
-        .count();
+        .collect::<Vec<_>>();
+    data.sort_by_key(|r| r.2.render_order);
+    for (ent, pos, render, _) in data.into_iter() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        let known = ent == *player_ent || (items.contains(ent) && map.revealed_tiles[idx]);
+        let (screen_x, screen_y) = camera.to_screen(pos.x, pos.y);
+        if known && camera.on_screen(screen_x, screen_y) {
+            ctx.set(screen_x, screen_y, render.fg, render.bg, render.glyph);
+        }
+    }
 
-    let mut y = (25 - (count / 2)) as i32;
     ctx.draw_box(
-        15,
+        0,
+        43,
+        79,
+        6,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        2,
+        43,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        &format!("Depth {} Overview", map.depth),
+    );
+    ctx.print_color(
+        2,
+        45,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        "ARROW KEYS to pan, ESCAPE to close",
+    );
+
+    match ctx.key {
+        None => MapViewResult::NoResponse { center },
+        Some(key) => {
+            let (d_x, d_y) = match key {
+                VirtualKeyCode::Escape => return MapViewResult::Cancel,
+                VirtualKeyCode::Left => (-MAP_PAN_STEP, 0),
+                VirtualKeyCode::Right => (MAP_PAN_STEP, 0),
+                VirtualKeyCode::Up => (0, -MAP_PAN_STEP),
+                VirtualKeyCode::Down => (0, MAP_PAN_STEP),
+                _ => (0, 0),
+            };
+            MapViewResult::NoResponse {
+                center: Point::new(center.x + d_x, center.y + d_y),
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CharacterMenuResult {
+    Cancel,
+    NoResponse,
+}
+
+/// Equipment slots in the order they're listed on the character screen.
+const CHARACTER_SLOTS: [EquipmentSlot; 7] = [
+    EquipmentSlot::Melee,
+    EquipmentSlot::Shield,
+    EquipmentSlot::TwoHanded,
+    EquipmentSlot::Head,
+    EquipmentSlot::Body,
+    EquipmentSlot::Hands,
+    EquipmentSlot::Feet,
+];
+
+/// Name of an equipment slot as shown to the player.
+fn slot_name(slot: EquipmentSlot) -> &'static str {
+    match slot {
+        EquipmentSlot::Melee => "Weapon",
+        EquipmentSlot::Shield => "Shield",
+        EquipmentSlot::TwoHanded => "Two-Handed",
+        EquipmentSlot::Head => "Head",
+        EquipmentSlot::Body => "Body",
+        EquipmentSlot::Hands => "Hands",
+        EquipmentSlot::Feet => "Feet",
+    }
+}
+
+/// Shows the player's equipment, effective combat stats, and general condition.
+pub fn show_character(gs: &mut State, ctx: &mut Rltk) -> CharacterMenuResult {
+    let player_ent = gs.ecs.fetch::<Entity>();
+    let names = gs.ecs.read_storage::<Name>();
+    let equipped = gs.ecs.read_storage::<Equipped>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let hunger = gs.ecs.read_storage::<HungerClock>();
+    let melee_power_bonuses = gs.ecs.read_storage::<MeleePowerBonus>();
+    let weapons = gs.ecs.read_storage::<Weapon>();
+    let defense_bonuses = gs.ecs.read_storage::<DefenseBonus>();
+    let durability = gs.ecs.read_storage::<Durability>();
+    let map = gs.ecs.fetch::<Map>();
+
+    let x = 15;
+    let mut y = 10;
+    ctx.draw_box(
+        x,
         y - 2,
-        31,
-        count + 3,
+        50,
+        26,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
     );
     ctx.print_color(
-        18,
+        x + 3,
         y - 2,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        "Remove Which Items?",
+        "Character",
     );
     ctx.print_color(
-        18,
-        y + count as i32 + 1,
+        x + 3,
+        y + 24,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        "ESCAPE to cancel",
+        "ESCAPE to close",
     );
 
-    let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (ent, _pack, name) in (&entities, &backpack, &names)
-        .join()
-        .filter(|item| item.1.owner == *player_ent)
-    {
-        ctx.set(
-            17,
+    // Bonuses come from the same helpers melee combat uses, so the numbers match.
+    if let Some(stats) = combat_stats.get(*player_ent) {
+        let power_bonus = super::melee_combat_system::offense_bonus(
+            *player_ent,
+            &melee_power_bonuses,
+            &weapons,
+            &equipped,
+            &hunger,
+        );
+        let defense_bonus =
+            super::melee_combat_system::defense_bonus(*player_ent, &defense_bonuses, &equipped);
+
+        ctx.print(
+            x + 2,
             y,
-            RGB::named(rltk::WHITE),
-            RGB::named(rltk::BLACK),
-            rltk::to_cp437('('),
+            &format!("HP:      {} / {}", stats.hp, stats.max_hp),
         );
-        ctx.set(
-            18,
+        y += 1;
+        ctx.print(
+            x + 2,
             y,
-            RGB::named(rltk::YELLOW),
-            RGB::named(rltk::BLACK),
-            97 + j as rltk::FontCharType,
+            &format!(
+                "Power:   {} ({} base + {} bonus)",
+                stats.power + power_bonus,
+                stats.power,
+                power_bonus
+            ),
         );
-        ctx.set(
-            19,
+        y += 1;
+        ctx.print(
+            x + 2,
             y,
-            RGB::named(rltk::WHITE),
-            RGB::named(rltk::BLACK),
-            rltk::to_cp437(')'),
+            &format!(
+                "Defense: {} ({} base + {} bonus)",
+                stats.defense + defense_bonus,
+                stats.defense,
+                defense_bonus
+            ),
         );
+        y += 1;
+    }
 
-        ctx.print(21, y, &name.name.to_string());
-        equippable.push(ent);
+    if let Some(hc) = hunger.get(*player_ent) {
+        let hunger_state = match hc.state {
+            HungerState::WellFed => "Well Fed",
+            HungerState::Normal => "Normal",
+            HungerState::Hungry => "Hungry",
+            HungerState::Starving => "Starving",
+        };
+        ctx.print(x + 2, y, &format!("Hunger:  {}", hunger_state));
         y += 1;
-        j += 1;
+    }
+    ctx.print(x + 2, y, &format!("Depth:   {}", map.depth));
+    y += 2;
+
+    ctx.print_color(
+        x + 2,
+        y,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Equipment",
+    );
+    y += 2;
+    for slot in CHARACTER_SLOTS.iter() {
+        let item = (&equipped, &names, durability.maybe())
+            .join()
+            .find(|(eq, _, _)| eq.owner == *player_ent && eq.slot == *slot)
+            .map(|(_, name, wear)| match wear {
+                Some(wear) => format!("{} ({}/{})", name.name, wear.current, wear.max),
+                None => name.name.clone(),
+            });
+        ctx.print(x + 2, y, &format!("{:<11}", slot_name(*slot)));
+        match item {
+            Some(name) => ctx.print(x + 14, y, &name),
+            None => ctx.print_color(
+                x + 14,
+                y,
+                RGB::named(rltk::GRAY),
+                RGB::named(rltk::BLACK),
+                "-",
+            ),
+        }
+        y += 1;
+    }
+    y += 1;
+
+    // Quick-use bindings, in two columns.
+    ctx.print_color(
+        x + 2,
+        y,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Hotkeys",
+    );
+    y += 2;
+    let hotbar = gs.ecs.fetch::<super::hotbar::Hotbar>();
+    for slot in 0..super::hotbar::HOTBAR_SLOTS {
+        let column = x + 2 + (slot as i32 / 5) * 24;
+        let row = y + slot as i32 % 5;
+        ctx.print(column, row, &format!("{}", slot + 1));
+        match hotbar.name(slot) {
+            Some(name) => ctx.print(column + 2, row, name),
+            None => ctx.print_color(
+                column + 2,
+                row,
+                RGB::named(rltk::GRAY),
+                RGB::named(rltk::BLACK),
+                "-",
+            ),
+        }
     }
 
     match ctx.key {
-        None => (ItemMenuResult::NoResponse, None),
-        Some(key) => match key {
-            VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
-            _ => {
-                let selection = rltk::letter_to_option(key);
-                if selection > -1 && selection < count as i32 {
-                    return (
-                        ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
-                    );
-                }
-                (ItemMenuResult::NoResponse, None)
-            }
-        },
+        Some(VirtualKeyCode::Escape) => CharacterMenuResult::Cancel,
+        _ => CharacterMenuResult::NoResponse,
     }
 }
 
+/// Resource holding the keyboard targeting cursor between frames of [`ranged_target`].
+///
+/// `None` means the cursor hasn't been placed yet, and will start on the valid
+/// cell nearest the player.
+#[derive(Default)]
+pub struct TargetingCursor {
+    pub point: Option<Point>,
+}
+
 /// Shows ranged targeting interface.
 pub fn ranged_target(
     gs: &mut State,
     ctx: &mut Rltk,
     range: i32,
+    radius: Option<i32>,
 ) -> (ItemMenuResult, Option<Point>) {
     let player_ent = gs.ecs.fetch::<Entity>();
     let player_pos = gs.ecs.fetch::<Point>();
     let viewsheds = gs.ecs.read_storage::<Viewshed>();
+    let camera = gs.ecs.fetch::<Camera>();
+    let map = gs.ecs.fetch::<Map>();
+    let mut cursor = gs.ecs.write_resource::<TargetingCursor>();
 
     ctx.print_color(
         5,
         0,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        "Select Target:",
+        "Select Target: (move with arrows/hjkl, ENTER to confirm, ESCAPE to cancel)",
     );
 
     let mut available_cells = Vec::new();
@@ -543,7 +1423,13 @@ pub fn ranged_target(
         for idx in visible.visible_tiles.iter() {
             let dist = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
             if dist <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, RGB::named(rltk::BLUE));
+                let (screen_x, screen_y) = camera.to_screen(idx.x, idx.y);
+                // In range, but there's a wall in the way.
+                if !map.has_line_of_fire(*player_pos, *idx) {
+                    ctx.set_bg(screen_x, screen_y, RGB::named(rltk::DARK_RED));
+                    continue;
+                }
+                ctx.set_bg(screen_x, screen_y, RGB::named(rltk::BLUE));
                 available_cells.push(idx);
             }
         }
@@ -552,52 +1438,241 @@ pub fn ranged_target(
     }
 
     let mouse_pos = ctx.mouse_pos();
+    let (mouse_x, mouse_y) = camera.to_world(mouse_pos.0, mouse_pos.1);
     let valid_target = available_cells
         .iter()
-        .any(|idx| idx.x == mouse_pos.0 && idx.y == mouse_pos.1);
+        .any(|idx| idx.x == mouse_x && idx.y == mouse_y);
     if valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            cursor.point = None;
+            return (ItemMenuResult::Selected, Some(Point::new(mouse_x, mouse_y)));
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::RED));
         if ctx.left_click {
+            cursor.point = None;
             return (ItemMenuResult::Cancel, None);
         }
     }
 
-    // if ctx.key.and_then(== VirtualKeyCode) == VirtualKeyCode::Escape { return (ItemMenuResult::Cancel, None); }
-    // if let Some(key) = ctx.key { if key == VirtualKeyCode::Escape { return (ItemMenuResult::Cancel, None); } }
+    // Keyboard cursor starts on the valid cell closest to the player (but not on them).
+    let target = match cursor.point {
+        Some(point) => point,
+        None => available_cells
+            .iter()
+            .filter(|idx| ***idx != *player_pos)
+            .min_by(|a, b| {
+                let dist_a = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, ***a);
+                let dist_b = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, ***b);
+                dist_a.partial_cmp(&dist_b).unwrap()
+            })
+            .map_or(*player_pos, |idx| **idx),
+    };
+    let valid_cursor = available_cells.iter().any(|idx| **idx == target);
+    let (cursor_x, cursor_y) = camera.to_screen(target.x, target.y);
+    if valid_cursor {
+        ctx.set_bg(cursor_x, cursor_y, RGB::named(rltk::CYAN));
+    } else {
+        ctx.set_bg(cursor_x, cursor_y, RGB::named(rltk::RED));
+    }
+
+    // Area effects light up everything they'd catch around whatever's being aimed at.
+    if let Some(radius) = radius {
+        let aim = if valid_target {
+            Point::new(mouse_x, mouse_y)
+        } else {
+            target
+        };
+        let burst = map.last_open_on_line(*player_pos, aim);
+        for tile in map.blast_area(burst, radius).iter() {
+            if *tile != aim {
+                let (screen_x, screen_y) = camera.to_screen(tile.x, tile.y);
+                ctx.set_bg(screen_x, screen_y, RGB::named(rltk::ORANGE));
+            }
+        }
+    }
+
+    // Move the cursor by `(d_x, d_y)`, keeping it on the map.
+    let move_cursor = |d_x: i32, d_y: i32| {
+        let x = i32::min(map.width - 1, i32::max(0, target.x + d_x));
+        let y = i32::min(map.height - 1, i32::max(0, target.y + d_y));
+        Some(Point::new(x, y))
+    };
+
+    cursor.point = match ctx.key {
+        None => Some(target),
+        Some(key) => match key {
+            VirtualKeyCode::Escape => {
+                cursor.point = None;
+                return (ItemMenuResult::Cancel, None);
+            }
+            VirtualKeyCode::Return => {
+                if valid_cursor {
+                    cursor.point = None;
+                    return (ItemMenuResult::Selected, Some(target));
+                }
+                Some(target)
+            }
+            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
+                move_cursor(-1, 0)
+            }
+            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
+                move_cursor(1, 0)
+            }
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => move_cursor(0, -1),
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => move_cursor(0, 1),
+            VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => move_cursor(-1, -1),
+            VirtualKeyCode::Numpad9 | VirtualKeyCode::U => move_cursor(1, -1),
+            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => move_cursor(-1, 1),
+            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => move_cursor(1, 1),
+            _ => Some(target),
+        },
+    };
 
     (ItemMenuResult::NoResponse, None)
 }
 
+/// Finds the entities the player can currently see on the tile at `(x, y)`.
+fn visible_entities_at(ecs: &World, x: i32, y: i32) -> Vec<Entity> {
+    let map = ecs.fetch::<Map>();
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    if x < 0 || x >= map.width || y < 0 || y >= map.height || !map.visible_tiles[map.xy_idx(x, y)] {
+        return Vec::new();
+    }
+    (&entities, &positions, !&hidden)
+        .join()
+        .filter(|(_, pos, _)| pos.x == x && pos.y == y)
+        .map(|(ent, _, _)| ent)
+        .collect()
+}
+
+fn tile_name(tile: TileType) -> &'static str {
+    match tile {
+        TileType::Wall => "Wall",
+        TileType::Floor => "Floor",
+        TileType::DownStairs => "Stairs down",
+        TileType::ShallowWater => "Shallow water",
+        TileType::Mud => "Mud",
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LookResult {
+    Cancel,
+    NoResponse { cursor: Point },
+}
+
+/// Look mode: moves a cursor over the map and describes whatever's under it in detail.
+pub fn look(gs: &mut State, ctx: &mut Rltk, cursor: Point) -> LookResult {
+    let map = gs.ecs.fetch::<Map>();
+    let camera = gs.ecs.fetch::<Camera>();
+    let names = gs.ecs.read_storage::<Name>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let monsters = gs.ecs.read_storage::<Monster>();
+
+    ctx.print_color(
+        5,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Look: (move with arrows/hjkl, ESCAPE to stop looking)",
+    );
+
+    let (cursor_x, cursor_y) = camera.to_screen(cursor.x, cursor.y);
+    ctx.set_bg(cursor_x, cursor_y, RGB::named(rltk::CYAN));
+
+    // Only describe what's been seen; entities only show up while they're in view.
+    let idx = map.xy_idx(cursor.x, cursor.y);
+    let mut lines: Vec<String> = Vec::new();
+    if map.revealed_tiles[idx] {
+        lines.push(tile_name(map.tiles[idx]).to_string());
+        for ent in visible_entities_at(&gs.ecs, cursor.x, cursor.y).iter() {
+            if let Some(name) = names.get(*ent) {
+                lines.push(name.name.to_string());
+            }
+            if let (Some(stats), Some(_)) = (combat_stats.get(*ent), monsters.get(*ent)) {
+                lines.push(format!(
+                    "  HP {}/{}, Power {}, Defense {}",
+                    stats.hp, stats.max_hp, stats.power, stats.defense
+                ));
+            }
+        }
+    } else {
+        lines.push("You haven't seen this spot.".to_string());
+    }
+
+    // Keep the panel out of the way of the cursor.
+    let width = lines.iter().map(|l| l.len()).max().unwrap_or(0) as i32 + 3;
+    let panel_x = if cursor_x > 40 { 1 } else { 78 - width };
+    ctx.draw_box(
+        panel_x,
+        2,
+        width,
+        (lines.len() + 1) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    for (i, line) in lines.iter().enumerate() {
+        ctx.print(panel_x + 2, 3 + i as i32, line);
+    }
+
+    // Move the cursor by `(d_x, d_y)`, keeping it on the map.
+    let move_cursor = |d_x: i32, d_y: i32| {
+        let x = i32::min(map.width - 1, i32::max(0, cursor.x + d_x));
+        let y = i32::min(map.height - 1, i32::max(0, cursor.y + d_y));
+        LookResult::NoResponse {
+            cursor: Point::new(x, y),
+        }
+    };
+
+    match ctx.key {
+        None => LookResult::NoResponse { cursor },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => LookResult::Cancel,
+            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
+                move_cursor(-1, 0)
+            }
+            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
+                move_cursor(1, 0)
+            }
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => move_cursor(0, -1),
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => move_cursor(0, 1),
+            VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => move_cursor(-1, -1),
+            VirtualKeyCode::Numpad9 | VirtualKeyCode::U => move_cursor(1, -1),
+            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => move_cursor(-1, 1),
+            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => move_cursor(1, 1),
+            _ => LookResult::NoResponse { cursor },
+        },
+    }
+}
+
 /// Renders tooltip on mouse-over.
 fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
-    // Get access to names and positions to make tooltips with.
+    // Get access to names to make tooltips with.
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
-    let positions = ecs.read_storage::<Position>();
-    let hidden = ecs.read_storage::<Hidden>();
+    let camera = ecs.fetch::<Camera>();
 
     // Make sure the map cursor is actually on the map.
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height {
+    if !camera.on_screen(mouse_pos.0, mouse_pos.1) {
+        return;
+    }
+    let (mouse_x, mouse_y) = camera.to_world(mouse_pos.0, mouse_pos.1);
+    if mouse_x >= map.width || mouse_y >= map.height {
         return;
     }
 
     // If there's something under the mouse, we'll make a tooltip for it.
-    let mut tooltip: Vec<String> = Vec::new();
-    for (name, pos, _) in (&names, &positions, !&hidden).join() {
-        let idx = map.xy_idx(pos.x, pos.y);
-        if pos.x == mouse_pos.0 && pos.y == mouse_pos.1 && map.visible_tiles[idx] {
-            tooltip.push(name.name.to_string());
-        }
-    }
+    let tooltip: Vec<String> = visible_entities_at(ecs, mouse_x, mouse_y)
+        .iter()
+        .filter_map(|ent| names.get(*ent))
+        .map(|name| name.name.to_string())
+        .collect();
 
     // Make tooltips if we found things to make them for.
     if !tooltip.is_empty() {
@@ -680,6 +1755,100 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     }
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum InGameMenuSelection {
+    Continue,
+    SaveAndQuit,
+    QuitWithoutSaving,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum InGameMenuResult {
+    NoSelection { selected: InGameMenuSelection },
+    Selected { selected: InGameMenuSelection },
+}
+
+/// Shows the pause menu opened with ESCAPE, so saving and quitting are both deliberate choices.
+pub fn in_game_menu(gs: &mut State, ctx: &mut Rltk) -> InGameMenuResult {
+    let runstate = gs.ecs.fetch::<RunState>();
+    let selection = match *runstate {
+        RunState::InGameMenu { menu_selection } => menu_selection,
+        _ => InGameMenuSelection::Continue,
+    };
+
+    let options = [
+        (InGameMenuSelection::Continue, "Continue"),
+        (InGameMenuSelection::SaveAndQuit, "Save & Quit"),
+        (
+            InGameMenuSelection::QuitWithoutSaving,
+            "Quit without saving",
+        ),
+    ];
+
+    let y = 22;
+    ctx.draw_box(
+        28,
+        y - 2,
+        24,
+        (options.len() + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        31,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Paused",
+    );
+    for (i, (option, label)) in options.iter().enumerate() {
+        let fg = if *option == selection {
+            RGB::named(rltk::MAGENTA)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color(30, y + i as i32, fg, RGB::named(rltk::BLACK), label);
+    }
+    ctx.print_color(
+        31,
+        y + options.len() as i32 + 1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to resume",
+    );
+
+    match ctx.key {
+        None => InGameMenuResult::NoSelection {
+            selected: selection,
+        },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => InGameMenuResult::Selected {
+                selected: InGameMenuSelection::Continue,
+            },
+            VirtualKeyCode::Up => InGameMenuResult::NoSelection {
+                selected: match selection {
+                    InGameMenuSelection::Continue => InGameMenuSelection::QuitWithoutSaving,
+                    InGameMenuSelection::SaveAndQuit => InGameMenuSelection::Continue,
+                    InGameMenuSelection::QuitWithoutSaving => InGameMenuSelection::SaveAndQuit,
+                },
+            },
+            VirtualKeyCode::Down => InGameMenuResult::NoSelection {
+                selected: match selection {
+                    InGameMenuSelection::Continue => InGameMenuSelection::SaveAndQuit,
+                    InGameMenuSelection::SaveAndQuit => InGameMenuSelection::QuitWithoutSaving,
+                    InGameMenuSelection::QuitWithoutSaving => InGameMenuSelection::Continue,
+                },
+            },
+            VirtualKeyCode::Return => InGameMenuResult::Selected {
+                selected: selection,
+            },
+            _ => InGameMenuResult::NoSelection {
+                selected: selection,
+            },
+        },
+    }
+}
+
 pub fn game_over(ctx: &mut Rltk) -> GameOverResult {
     ctx.print_color_centered(
         15,
@@ -712,3 +1881,142 @@ pub fn game_over(ctx: &mut Rltk) -> GameOverResult {
         Some(_) => GameOverResult::QuitToMenu,
     }
 }
+
+pub fn victory(gs: &mut State, ctx: &mut Rltk) -> GameOverResult {
+    let guardian_slain = gs.ecs.fetch::<crate::damage_system::GuardianSlain>().slain;
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::GOLD),
+        RGB::named(rltk::BLACK),
+        "You have recovered the Amulet of the Depths!",
+    );
+    ctx.print_color_centered(
+        17,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        if guardian_slain {
+            "Its guardian lies slain, and your journey is complete."
+        } else {
+            "You slipped past its guardian, and your journey is complete."
+        },
+    );
+    ctx.print_color_centered(
+        18,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        "The bards will sing of this for ages to come.",
+    );
+
+    ctx.print_color_centered(
+        20,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        "Press any key to return to the menu.",
+    );
+
+    match ctx.key {
+        None => GameOverResult::NoSelection,
+        Some(_) => GameOverResult::QuitToMenu,
+    }
+}
+
+/// Resource holding the text typed so far on the seed entry screen.
+#[derive(Default)]
+pub struct SeedInput {
+    pub text: String,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum SeedEntryResult {
+    Cancel,
+    NoResponse,
+    Selected { seed: GameSeed },
+}
+
+/// Longest seed string accepted by the entry screen.
+const MAX_SEED_LENGTH: usize = 20;
+
+/// Shows the seed entry screen, collecting letters and digits until ENTER is pressed.
+pub fn seed_entry(gs: &mut State, ctx: &mut Rltk) -> SeedEntryResult {
+    let assets = gs.ecs.fetch::<RexAssets>();
+    let mut input = gs.ecs.write_resource::<SeedInput>();
+    ctx.render_xp_sprite(&assets.menu, 0, 0);
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Enter a seed (letters or numbers)",
+    );
+    ctx.print_color_centered(
+        24,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        &format!("> {}_", input.text),
+    );
+    ctx.print_color_centered(
+        27,
+        RGB::named(rltk::GRAY),
+        RGB::named(rltk::BLACK),
+        "ENTER to begin, ESCAPE to go back",
+    );
+
+    match ctx.key {
+        None => SeedEntryResult::NoResponse,
+        Some(key) => match key {
+            VirtualKeyCode::Escape => {
+                input.text.clear();
+                SeedEntryResult::Cancel
+            }
+            VirtualKeyCode::Back => {
+                input.text.pop();
+                SeedEntryResult::NoResponse
+            }
+            // An empty seed just means "pick one for me".
+            VirtualKeyCode::Return => {
+                let seed = if input.text.is_empty() {
+                    GameSeed::random()
+                } else {
+                    GameSeed::from_text(&input.text)
+                };
+                input.text.clear();
+                SeedEntryResult::Selected { seed }
+            }
+            _ => {
+                if let Some(c) = seed_char(key) {
+                    if input.text.len() < MAX_SEED_LENGTH {
+                        input.text.push(c);
+                    }
+                }
+                SeedEntryResult::NoResponse
+            }
+        },
+    }
+}
+
+/// Maps a key to the character it adds to a seed, if any.
+fn seed_char(key: VirtualKeyCode) -> Option<char> {
+    let digit = match key {
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some('0'),
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some('1'),
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some('2'),
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some('3'),
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some('4'),
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some('5'),
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some('6'),
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some('7'),
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some('8'),
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some('9'),
+        _ => None,
+    };
+    if digit.is_some() {
+        return digit;
+    }
+
+    // Letters are always lowercase, so seeds don't depend on the shift key.
+    match rltk::letter_to_option(key) {
+        -1 => None,
+        letter => Some((b'a' + letter as u8) as char),
+    }
+}
@@ -0,0 +1,83 @@
+use specs::prelude::*;
+
+use super::{
+    gamelog::{GameLog, TurnCounter},
+    CombatStats, DamageType, Immunities, Map, Poisoned, RunState, StatusEffect, SufferDamage,
+};
+
+/// How long breathing in poison gas keeps hurting after leaving the cloud.
+const GAS_POISON_TURNS: i32 = 3;
+
+/// Hurts everything standing in a lingering hazard, and burns the hazards down a turn at a time.
+pub struct HazardSystem {}
+
+impl<'a> System<'a> for HazardSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadExpect<'a, RunState>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
+        WriteStorage<'a, Poisoned>,
+        ReadStorage<'a, Immunities>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut map,
+            runstate,
+            combat_stats,
+            mut suffering,
+            player_ent,
+            mut log,
+            turn_counter,
+            mut poisoned,
+            immunities,
+        ) = data;
+
+        // Systems run on both halves of a turn; only hurt once per turn.
+        if *runstate != RunState::PlayerTurn || map.hazards.is_empty() {
+            return;
+        }
+
+        for (idx, hazard) in map.hazards.iter() {
+            for ent in map.tile_content[*idx].iter() {
+                if combat_stats.get(*ent).is_none() {
+                    continue;
+                }
+                SufferDamage::new_damage(&mut suffering, *ent, hazard.damage, hazard.damage_type);
+                if hazard.damage_type == DamageType::Poison
+                    && !Immunities::blocks(&immunities, *ent, StatusEffect::Poison)
+                {
+                    poisoned
+                        .insert(
+                            *ent,
+                            Poisoned {
+                                turns: GAS_POISON_TURNS,
+                                damage: 1,
+                            },
+                        )
+                        .expect("Unable to insert status");
+                }
+                if *ent == *player_ent {
+                    log.push(
+                        turn_counter.turn,
+                        format!(
+                            "You're caught in the {}, taking {} damage.",
+                            hazard.damage_type.name(),
+                            hazard.damage
+                        ),
+                    );
+                }
+            }
+        }
+
+        map.hazards
+            .values_mut()
+            .for_each(|hazard| hazard.turns -= 1);
+        map.hazards.retain(|_, hazard| hazard.turns > 0);
+    }
+}
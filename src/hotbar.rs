@@ -0,0 +1,112 @@
+use rltk::VirtualKeyCode;
+use specs::prelude::*;
+
+use super::{HotbarSlot, InBackpack, Name};
+
+/// Number of quick-use slots, one for each of the number keys `1` to `9`.
+pub const HOTBAR_SLOTS: usize = 9;
+
+/// An item bound to a quick-use slot. The name is kept so another of the same kind can
+/// stand in once this one is used up.
+#[derive(Clone, Debug)]
+struct Binding {
+    item: Entity,
+    name: String,
+}
+
+/// Resource holding which backpack items are bound to the number keys.
+#[derive(Clone, Debug, Default)]
+pub struct Hotbar {
+    slots: [Option<Binding>; HOTBAR_SLOTS],
+}
+
+impl Hotbar {
+    /// Binds `item` (called `name`) to `slot`, replacing whatever was there.
+    pub fn assign(&mut self, slot: usize, item: Entity, name: &str) {
+        self.slots[slot] = Some(Binding {
+            item,
+            name: name.to_string(),
+        });
+    }
+
+    /// Name of the item bound to `slot`, if any.
+    pub fn name(&self, slot: usize) -> Option<&str> {
+        self.slots[slot].as_ref().map(|bound| bound.name.as_str())
+    }
+}
+
+/// Tags each bound item with its [`HotbarSlot`], so the bindings are saved with the items.
+pub fn tag_bound_items(ecs: &World) {
+    ecs.write_storage::<HotbarSlot>().clear();
+    for slot in 0..HOTBAR_SLOTS {
+        if let Some(item) = hotbar_item(ecs, slot) {
+            ecs.write_storage::<HotbarSlot>()
+                .insert(item, HotbarSlot { slot })
+                .expect("Unable to tag hotbar item");
+        }
+    }
+}
+
+/// Rebuilds the [`Hotbar`] from the [`HotbarSlot`] tags on loaded items, then clears them.
+pub fn restore_bindings(ecs: &World) {
+    let mut hotbar = Hotbar::default();
+    {
+        let entities = ecs.entities();
+        let tags = ecs.read_storage::<HotbarSlot>();
+        let names = ecs.read_storage::<Name>();
+        for (item, tag, name) in (&entities, &tags, &names).join() {
+            if tag.slot < HOTBAR_SLOTS {
+                hotbar.assign(tag.slot, item, &name.name);
+            }
+        }
+    }
+    ecs.write_storage::<HotbarSlot>().clear();
+    *ecs.write_resource::<Hotbar>() = hotbar;
+}
+
+/// The quick-use slot a key selects: `1` is the first slot and `9` the last.
+pub fn slot_for_key(key: VirtualKeyCode) -> Option<usize> {
+    use VirtualKeyCode::*;
+
+    [Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9]
+        .iter()
+        .position(|k| *k == key)
+}
+
+/// The item in the player's backpack that `slot` uses.
+///
+/// If the bound item has been used up or dropped, another with the same name takes its
+/// place; if there are none left, the slot is cleared.
+pub fn hotbar_item(ecs: &World, slot: usize) -> Option<Entity> {
+    let mut hotbar = ecs.write_resource::<Hotbar>();
+    let player_ent = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let names = ecs.read_storage::<Name>();
+
+    let bound = hotbar.slots[slot].as_mut()?;
+    let carried = |item: Entity| {
+        entities.is_alive(item)
+            && backpack
+                .get(item)
+                .map_or(false, |pack| pack.owner == *player_ent)
+    };
+    if carried(bound.item) {
+        return Some(bound.item);
+    }
+
+    let replacement = (&entities, &backpack, &names)
+        .join()
+        .find(|(_, pack, name)| pack.owner == *player_ent && name.name == bound.name)
+        .map(|(ent, _, _)| ent);
+    match replacement {
+        Some(item) => {
+            bound.item = item;
+            Some(item)
+        }
+        None => {
+            hotbar.slots[slot] = None;
+            None
+        }
+    }
+}
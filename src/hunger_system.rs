@@ -1,6 +1,62 @@
-use super::{gamelog::GameLog, HungerClock, HungerState, RunState, SufferDamage};
+use super::{
+    gamelog::{GameLog, TurnCounter},
+    settings::env_flag,
+    DamageType, HungerClock, HungerState, InBackpack, ProvidesFood, RunState, SufferDamage,
+    WantsToUseItem,
+};
 use specs::prelude::*;
 
+/// How many turns a meal keeps its eater well fed.
+pub const WELL_FED_DURATION: i32 = 20;
+
+/// Resource controlling whether a starving player eats from their pack on their own.
+///
+/// On unless the `AUTO_EAT` environment variable is `0` or `false`.
+pub struct AutoEat {
+    pub enabled: bool,
+}
+
+impl Default for AutoEat {
+    fn default() -> Self {
+        AutoEat {
+            enabled: env_flag("AUTO_EAT", true),
+        }
+    }
+}
+
+/// Modifiers a hunger state applies to combat and resting.
+///
+/// Combat and rest code both read these, so they always agree on what hunger does.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct HungerEffects {
+    /// Added to melee power.
+    pub melee_bonus: i32,
+    /// HP regained when skipping a turn with no enemies around.
+    pub rest_heal: i32,
+}
+
+/// Looks up the effects of being in hunger state `state`.
+pub fn hunger_effects(state: HungerState) -> HungerEffects {
+    match state {
+        HungerState::WellFed => HungerEffects {
+            melee_bonus: 1,
+            rest_heal: 2,
+        },
+        HungerState::Normal => HungerEffects {
+            melee_bonus: 0,
+            rest_heal: 1,
+        },
+        HungerState::Hungry => HungerEffects {
+            melee_bonus: 0,
+            rest_heal: 0,
+        },
+        HungerState::Starving => HungerEffects {
+            melee_bonus: -1,
+            rest_heal: 0,
+        },
+    }
+}
+
 pub struct HungerSystem {}
 
 impl<'a> System<'a> for HungerSystem {
@@ -12,10 +68,27 @@ impl<'a> System<'a> for HungerSystem {
         ReadExpect<'a, RunState>,
         WriteStorage<'a, SufferDamage>,
         WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
+        ReadExpect<'a, AutoEat>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, ProvidesFood>,
+        WriteStorage<'a, WantsToUseItem>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut hunger_clock, player_ent, runstate, mut damage, mut log) = data;
+        let (
+            entities,
+            mut hunger_clock,
+            player_ent,
+            runstate,
+            mut damage,
+            mut log,
+            turn_counter,
+            auto_eat,
+            backpack,
+            food,
+            mut wants_use,
+        ) = data;
 
         for (ent, mut clock) in (&entities, &mut hunger_clock).join() {
             let mut proceed = false;
@@ -42,32 +115,48 @@ impl<'a> System<'a> for HungerSystem {
                             clock.state = HungerState::Normal;
                             clock.duration = 200;
                             if ent == *player_ent {
-                                log.entries.push("You are no longer well fed.".to_string());
+                                log.push(turn_counter.turn, "You are no longer well fed.");
                             }
                         }
                         HungerState::Normal => {
                             clock.state = HungerState::Hungry;
                             clock.duration = 200;
                             if ent == *player_ent {
-                                log.entries.push("You are hungry.".to_string());
+                                log.push(turn_counter.turn, "You are hungry.");
                             }
                         }
                         HungerState::Hungry => {
                             clock.state = HungerState::Starving;
                             clock.duration = 200;
                             if ent == *player_ent {
-                                log.entries.push("You are starving!".to_string());
+                                log.push(turn_counter.turn, "You are starving!");
                             }
                         }
                         HungerState::Starving => {
                             if ent == *player_ent {
-                                log.entries
-                                    .push("Your hunger pangs are getting painful!".to_string());
+                                log.push(
+                                    turn_counter.turn,
+                                    "Your hunger pangs are getting painful!",
+                                );
                             }
-                            SufferDamage::new_damage(&mut damage, ent, 1);
+                            SufferDamage::new_damage(&mut damage, ent, 1, DamageType::Physical);
                         }
                     }
                 }
+
+                // A starving player with food in their pack digs in before it's too late.
+                if ent == *player_ent && clock.state == HungerState::Starving && auto_eat.enabled {
+                    let meal = (&entities, &backpack, &food)
+                        .join()
+                        .find(|(_, pack, _)| pack.owner == ent)
+                        .map(|(item, _, _)| item);
+                    if let Some(item) = meal {
+                        log.push(turn_counter.turn, "Starving, you reach for your food.");
+                        wants_use
+                            .insert(ent, WantsToUseItem { item, target: None })
+                            .expect("Unable to insert intent");
+                    }
+                }
             }
         }
     }
@@ -1,6 +1,29 @@
-use super::{components::*, gamelog::GameLog, particle_system::ParticleBuilder, Map, RunState};
-use rltk::{BLACK, GREEN, MAGENTA, ORANGE, RED, RGB};
+use super::{
+    components::*,
+    gamelog::{GameLog, TurnCounter},
+    hunger_system::WELL_FED_DURATION,
+    particle_system::ParticleBuilder,
+    AreaEffectTile, Map, RunState, TileType,
+};
+use rltk::{Point, BLACK, GREEN, MAGENTA, ORANGE, RED, RGB};
 use specs::prelude::*;
+use std::collections::HashMap;
+
+/// How many items `owner` has in their backpack (equipped gear doesn't count).
+pub fn items_carried(ecs: &World, owner: Entity) -> usize {
+    ecs.read_storage::<InBackpack>()
+        .join()
+        .filter(|item| item.owner == owner)
+        .count()
+}
+
+/// Whether `owner` can fit another item in their backpack.
+pub fn pack_has_room(ecs: &World, owner: Entity) -> bool {
+    match ecs.read_storage::<PackCapacity>().get(owner) {
+        Some(capacity) => items_carried(ecs, owner) < capacity.max_items,
+        None => true,
+    }
+}
 
 pub struct ItemCollectionSystem {}
 
@@ -9,16 +32,109 @@ impl<'a> System<'a> for ItemCollectionSystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
         WriteStorage<'a, WantsToPickupItem>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, WinsGame>,
+        WriteExpect<'a, RunState>,
+        Entities<'a>,
+        ReadStorage<'a, Currency>,
+        WriteStorage<'a, Gold>,
+        ReadStorage<'a, PackCapacity>,
+        WriteStorage<'a, Ammo>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player, mut log, mut wants_pickup, mut positions, names, mut backpack) = data;
+        let (
+            player,
+            mut log,
+            turn_counter,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            wins_game,
+            mut runstate,
+            entities,
+            currency,
+            mut gold,
+            capacities,
+            mut ammo,
+        ) = data;
+
+        // Several items can be picked up at once, so keep a running tally of pack sizes.
+        let mut carried: HashMap<Entity, usize> = HashMap::new();
+        for item in backpack.join() {
+            *carried.entry(item.owner).or_insert(0) += 1;
+        }
 
         for pickup in wants_pickup.join() {
+            // Coins go straight into the collector's purse.
+            if let Some(coins) = currency.get(pickup.item) {
+                positions.remove(pickup.item);
+                if let Some(purse) = gold.get_mut(pickup.collected_by) {
+                    purse.amount += coins.amount;
+                }
+                if pickup.collected_by == *player {
+                    log.push(
+                        turn_counter.turn,
+                        format!("You pick up {} gold.", coins.amount),
+                    );
+                }
+                entities
+                    .delete(pickup.item)
+                    .expect("Unable to delete coins");
+                continue;
+            }
+
+            // Ammo joins a bundle of the same kind already in the pack, taking up no more room.
+            if let Some(picked) = ammo.get(pickup.item).cloned() {
+                let bundle = (&entities, &backpack, &ammo)
+                    .join()
+                    .find(|(_, pack, bundle)| {
+                        pack.owner == pickup.collected_by && bundle.kind == picked.kind
+                    })
+                    .map(|(bundle, _, _)| bundle);
+                if let Some(bundle) = bundle {
+                    ammo.get_mut(bundle).unwrap().count += picked.count;
+                    positions.remove(pickup.item);
+                    if pickup.collected_by == *player {
+                        log.push(
+                            turn_counter.turn,
+                            format!(
+                                "You pick up {} {}.",
+                                picked.count,
+                                picked.kind.name().to_lowercase()
+                            ),
+                        );
+                    }
+                    entities
+                        .delete(pickup.item)
+                        .expect("Unable to delete merged ammo");
+                    continue;
+                }
+            }
+
+            // A full pack leaves the item where it lies.
+            let count = carried.entry(pickup.collected_by).or_insert(0);
+            if let Some(capacity) = capacities.get(pickup.collected_by) {
+                if *count >= capacity.max_items {
+                    if pickup.collected_by == *player {
+                        log.push(
+                            turn_counter.turn,
+                            format!(
+                                "Your pack is full; you leave the {}.",
+                                names.get(pickup.item).unwrap().name
+                            ),
+                        );
+                    }
+                    continue;
+                }
+            }
+            *count += 1;
+
             positions.remove(pickup.item);
             backpack
                 .insert(
@@ -30,10 +146,13 @@ impl<'a> System<'a> for ItemCollectionSystem {
                 .expect("Unable to insert into backpack");
 
             if pickup.collected_by == *player {
-                log.entries.push(format!(
-                    "You pick up the {}.",
-                    names.get(pickup.item).unwrap().name
-                ));
+                log.push(
+                    turn_counter.turn,
+                    format!("You pick up the {}.", names.get(pickup.item).unwrap().name),
+                );
+                if wins_game.get(pickup.item).is_some() {
+                    *runstate = RunState::Victory;
+                }
             }
         }
         wants_pickup.clear();
@@ -47,7 +166,8 @@ impl<'a> System<'a> for ItemUseSystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
-        ReadExpect<'a, Map>,
+        ReadExpect<'a, TurnCounter>,
+        WriteExpect<'a, Map>,
         Entities<'a>,
         WriteStorage<'a, WantsToUseItem>,
         ReadStorage<'a, Name>,
@@ -67,6 +187,15 @@ impl<'a> System<'a> for ItemUseSystem {
         WriteStorage<'a, HungerClock>,
         ReadStorage<'a, MagicMapper>,
         WriteExpect<'a, RunState>,
+        WriteStorage<'a, Telepathy>,
+        ReadStorage<'a, Lingers>,
+        (
+            ReadStorage<'a, CuresStatus>,
+            WriteStorage<'a, Poisoned>,
+            ReadStorage<'a, RepairsEquipment>,
+            WriteStorage<'a, Durability>,
+            ReadStorage<'a, Immunities>,
+        ),
     );
 
     #[allow(clippy::clippy::cognitive_complexity)]
@@ -74,7 +203,8 @@ impl<'a> System<'a> for ItemUseSystem {
         let (
             player_ent,
             mut log,
-            map,
+            turn_counter,
+            mut map,
             entities,
             mut wants_use,
             names,
@@ -94,6 +224,9 @@ impl<'a> System<'a> for ItemUseSystem {
             mut hunger_clocks,
             magic_mapper,
             mut runstate,
+            mut telepathy,
+            lingers,
+            (cures, mut poisoned, repairs, mut durability, immunities),
         ) = data;
 
         for (ent, useitem) in (&entities, &wants_use).join() {
@@ -117,18 +250,34 @@ impl<'a> System<'a> for ItemUseSystem {
                         }
                         // In AoE--determine what's in radius of the item's effect.
                         Some(area_effect) => {
+                            // Anything aimed into a wall bursts against its face instead.
+                            let target = match positions.get(ent) {
+                                Some(pos) => {
+                                    map.last_open_on_line(Point::new(pos.x, pos.y), target)
+                                }
+                                None => target,
+                            };
                             // Plot a viewshed from the centroid (target) of appropriate
-                            // radius and constrain to only valid map tiles.
-                            let blast_tiles =
-                                rltk::field_of_view(target, area_effect.radius, &*map)
-                                    .into_iter()
-                                    .filter(|p| {
-                                        p.x > 0
-                                            && p.x < map.width - 1
-                                            && p.y > 0
-                                            && p.y < map.height - 1
-                                    })
-                                    .collect::<Vec<_>>();
+                            // radius and constrain to only valid, open map tiles.
+                            let blast_tiles = map.blast_area(target, area_effect.radius);
+
+                            // Lingering effects settle over the area instead, and do their
+                            // damage turn by turn.
+                            if let (Some(linger), Some(damage)) =
+                                (lingers.get(useitem.item), inflicts_damage.get(useitem.item))
+                            {
+                                for tile_idx in blast_tiles.iter() {
+                                    let idx = map.xy_idx(tile_idx.x, tile_idx.y);
+                                    map.hazards.insert(
+                                        idx,
+                                        AreaEffectTile {
+                                            turns: linger.turns,
+                                            damage: damage.damage,
+                                            damage_type: damage.damage_type,
+                                        },
+                                    );
+                                }
+                            }
 
                             // Look at each tile in the area of effect; content
                             // of these tiles will be added to our targets.
@@ -164,14 +313,16 @@ impl<'a> System<'a> for ItemUseSystem {
                     let mut to_unequip: Vec<Entity> = Vec::new();
                     for (item_ent, already_equipped, name) in (&entities, &equipped, &names).join()
                     {
-                        // Check to see if the needed equipment slot is already filled.
-                        if already_equipped.owner == target && already_equipped.slot == target_slot
+                        // Check to see if the needed equipment slot (or one it shares
+                        // hands with) is already filled.
+                        if already_equipped.owner == target
+                            && already_equipped.slot.conflicts_with(target_slot)
                         {
                             // If so, make a note to take it off (by putting it in our vec).
                             to_unequip.push(item_ent);
                             // If it's the player, let them know they've unequipped an item.
                             if target == *player_ent {
-                                log.entries.push(format!("You unequip {}.", name.name))
+                                log.push(turn_counter.turn, format!("You unequip {}.", name.name))
                             }
                         }
                     }
@@ -197,10 +348,10 @@ impl<'a> System<'a> for ItemUseSystem {
                     backpack.remove(useitem.item);
                     // If it's the player, let them know that they've equipped the item.
                     if target == *player_ent {
-                        log.entries.push(format!(
-                            "You equip the {}.",
-                            names.get(useitem.item).unwrap().name
-                        ));
+                        log.push(
+                            turn_counter.turn,
+                            format!("You equip the {}.", names.get(useitem.item).unwrap().name),
+                        );
                     }
                 }
             }
@@ -209,20 +360,33 @@ impl<'a> System<'a> for ItemUseSystem {
             match provides_food.get(useitem.item) {
                 // None--item isn't food.
                 None => {}
-                Some(_) => {
-                    // Must consume to get benefit--no regurgitation necessary.
-                    item_used = true;
-                    // Update the hunger clock post-consumption.
-                    if let Some(hc) = hunger_clocks.get_mut(targets[0]) {
+                Some(_) => match hunger_clocks.get_mut(targets[0]) {
+                    // Nothing there gets hungry, so the food is left uneaten.
+                    None => item_used = false,
+                    // Don't waste food on anyone who isn't hungry yet.
+                    Some(hc)
+                        if hc.state == HungerState::WellFed || hc.state == HungerState::Normal =>
+                    {
+                        item_used = false;
+                        if ent == *player_ent {
+                            log.push(turn_counter.turn, "You are not hungry.");
+                        }
+                    }
+                    Some(hc) => {
+                        // Must consume to get benefit--no regurgitation necessary.
+                        item_used = true;
+                        // Update the hunger clock post-consumption.
                         hc.state = HungerState::WellFed;
-                        hc.duration = 20;
+                        hc.duration = WELL_FED_DURATION;
                         // Let the user know they ate something.
-                        log.entries.push(format!(
-                            "You eat the {}.",
-                            names.get(useitem.item).unwrap().name
-                        ));
+                        if ent == *player_ent {
+                            log.push(
+                                turn_counter.turn,
+                                format!("You eat the {}.", names.get(useitem.item).unwrap().name),
+                            );
+                        }
                     }
-                }
+                },
             }
 
             // Check if the item provides healing.
@@ -238,11 +402,14 @@ impl<'a> System<'a> for ItemUseSystem {
                             // Heals the target by the items healing amount, up to their max hp.
                             stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
                             if ent == *player_ent {
-                                log.entries.push(format!(
-                                    "You drink the {}, healing {} hp.",
-                                    names.get(useitem.item).unwrap().name,
-                                    healer.heal_amount
-                                ));
+                                log.push(
+                                    turn_counter.turn,
+                                    format!(
+                                        "You drink the {}, healing {} hp.",
+                                        names.get(useitem.item).unwrap().name,
+                                        healer.heal_amount
+                                    ),
+                                );
                             }
                             item_used = true;
                             // Flash a green heart particle to indicate healing.
@@ -264,20 +431,56 @@ impl<'a> System<'a> for ItemUseSystem {
             // Check to see if the item inflicts damage.
             match inflicts_damage.get(useitem.item) {
                 None => {}
+                // Lingering damage was left on the map, for the hazard system to deal out.
+                Some(damage) if lingers.contains(useitem.item) => {
+                    item_used = true;
+                    if ent == *player_ent {
+                        log.push(
+                            turn_counter.turn,
+                            format!(
+                                "A cloud of {} spreads out from the {}.",
+                                damage.damage_type.name(),
+                                names.get(useitem.item).unwrap().name
+                            ),
+                        );
+                    }
+                }
                 // If so, apply damage to the targets we found.
                 Some(damage) => {
                     item_used = false;
 
                     // Apply damage to the targets.
                     for mob in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer, *mob, damage.damage);
-                        if ent == *player_ent {
+                        SufferDamage::new_damage(
+                            &mut suffer,
+                            *mob,
+                            damage.damage,
+                            damage.damage_type,
+                        );
+                        if ent == *player_ent && *mob == ent {
+                            // Self-inflicted, like eating something that's gone off.
+                            log.push(
+                                turn_counter.turn,
+                                format!(
+                                    "The {} makes you sick, dealing {} {} damage.",
+                                    names.get(useitem.item).unwrap().name,
+                                    damage.damage,
+                                    damage.damage_type.name()
+                                ),
+                            );
+                        } else if ent == *player_ent {
                             let mob_name = names.get(*mob).unwrap();
                             let item_name = names.get(useitem.item).unwrap();
-                            log.entries.push(format!(
-                                "You use {} on {}, inflicting {} damage.",
-                                item_name.name, mob_name.name, damage.damage
-                            ));
+                            log.push(
+                                turn_counter.turn,
+                                format!(
+                                    "You use {} on {}, inflicting {} {} damage.",
+                                    item_name.name,
+                                    mob_name.name,
+                                    damage.damage,
+                                    damage.damage_type.name()
+                                ),
+                            );
                             // Flash red `‼` on damaged mob.
                             if let Some(pos) = positions.get(*mob) {
                                 particle_builder.request(
@@ -306,15 +509,29 @@ impl<'a> System<'a> for ItemUseSystem {
                         item_used = false;
                         // Mark mobs to confuse by placing them into the vec.
                         for mob in targets.iter() {
+                            if Immunities::blocks(&immunities, *mob, StatusEffect::Confusion) {
+                                if ent == *player_ent {
+                                    if let Some(mob_name) = names.get(*mob) {
+                                        log.push(
+                                            turn_counter.turn,
+                                            format!("The {} is unaffected.", mob_name.name),
+                                        );
+                                    }
+                                }
+                                continue;
+                            }
                             add_confusion.push((*mob, confusion.turns));
                             // If the player is casting confusion, let them know the effect.
                             if ent == *player_ent {
                                 let mob_name = names.get(*mob).unwrap();
                                 let item_name = names.get(useitem.item).unwrap();
-                                log.entries.push(format!(
-                                    "You use {} on {}, confusing them.",
-                                    item_name.name, mob_name.name
-                                ));
+                                log.push(
+                                    turn_counter.turn,
+                                    format!(
+                                        "You use {} on {}, confusing them.",
+                                        item_name.name, mob_name.name
+                                    ),
+                                );
                                 // Flash magenta `?` on the confused mob.
                                 if let Some(pos) = positions.get(*mob) {
                                     particle_builder.request(
@@ -338,12 +555,88 @@ impl<'a> System<'a> for ItemUseSystem {
                     .expect("Unable to insert status");
             }
 
+            // Cures only get used up if there's something for them to cure.
+            if let Some(cure) = cures.get(useitem.item) {
+                let mut cured = false;
+                for target in targets.iter() {
+                    for effect in cure.effects.iter() {
+                        let removed = match effect {
+                            StatusEffect::Confusion => confused.remove(*target).is_some(),
+                            StatusEffect::Poison => poisoned.remove(*target).is_some(),
+                        };
+                        cured = cured || removed;
+                    }
+                }
+                item_used = cured;
+                if ent == *player_ent {
+                    let item_name = &names.get(useitem.item).unwrap().name;
+                    if cured {
+                        log.push(
+                            turn_counter.turn,
+                            format!("You drink the {}, and feel much better.", item_name),
+                        );
+                    } else {
+                        log.push(
+                            turn_counter.turn,
+                            format!("You sip the {}, but nothing happens.", item_name),
+                        );
+                    }
+                }
+            }
+
+            // Repairs also only get used up if there's something worn to fix.
+            if repairs.contains(useitem.item) {
+                let mut repaired = false;
+                for (item_durability, equipped_by) in (&mut durability, &equipped).join() {
+                    if equipped_by.owner == ent && item_durability.current < item_durability.max {
+                        item_durability.current = item_durability.max;
+                        repaired = true;
+                    }
+                }
+                item_used = repaired;
+                if ent == *player_ent {
+                    let item_name = &names.get(useitem.item).unwrap().name;
+                    if repaired {
+                        log.push(
+                            turn_counter.turn,
+                            format!("You use the {}; your gear is as good as new.", item_name),
+                        );
+                    } else {
+                        log.push(turn_counter.turn, "None of your gear needs repairing.");
+                    }
+                }
+            }
+
             match magic_mapper.get(useitem.item) {
                 None => {}
-                Some(_) => {
+                Some(mapper) => {
                     item_used = true;
-                    log.entries.push("The map is revealed to you!".to_string());
-                    *runstate = RunState::MagicMapReveal { row: 0 };
+                    log.push(turn_counter.turn, "The map is revealed to you!");
+                    *runstate = if mapper.reachable_only {
+                        RunState::MagicMapFlood {
+                            band: 0,
+                            reveal_traps: mapper.reveal_traps,
+                        }
+                    } else {
+                        RunState::MagicMapReveal {
+                            row: 0,
+                            reveal_traps: mapper.reveal_traps,
+                        }
+                    };
+                }
+            }
+
+            // Telepathy lets the user sense monsters for a while.
+            if let Some(turns) = telepathy.get(useitem.item).map(|t| t.turns) {
+                item_used = true;
+                telepathy
+                    .insert(ent, Telepathy { turns })
+                    .expect("Unable to insert status");
+                if ent == *player_ent {
+                    log.push(
+                        turn_counter.turn,
+                        "Your mind opens to the creatures around you.",
+                    );
                 }
             }
 
@@ -369,6 +662,7 @@ impl<'a> System<'a> for ItemDropSystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
         Entities<'a>,
         WriteStorage<'a, WantsToDropItem>,
         ReadStorage<'a, Name>,
@@ -377,8 +671,16 @@ impl<'a> System<'a> for ItemDropSystem {
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_ent, mut log, entities, mut wants_drop, names, mut positions, mut backpack) =
-            data;
+        let (
+            player_ent,
+            mut log,
+            turn_counter,
+            entities,
+            mut wants_drop,
+            names,
+            mut positions,
+            mut backpack,
+        ) = data;
 
         for (ent, to_drop) in (&entities, &wants_drop).join() {
             let mut dropper_pos: Position = Position { x: 0, y: 0 };
@@ -399,10 +701,10 @@ impl<'a> System<'a> for ItemDropSystem {
             backpack.remove(to_drop.item);
 
             if ent == *player_ent {
-                log.entries.push(format!(
-                    "You drop the {}.",
-                    names.get(to_drop.item).unwrap().name
-                ));
+                log.push(
+                    turn_counter.turn,
+                    format!("You drop the {}.", names.get(to_drop.item).unwrap().name),
+                );
             }
         }
 
@@ -433,3 +735,238 @@ impl<'a> System<'a> for ItemRemoveSystem {
         wants_remove.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world laid out from `rows`, with the player standing on its `@`.
+    fn world_with_map(rows: &[&str]) -> (World, Entity) {
+        let mut ecs = World::new();
+        crate::register_components(&mut ecs);
+        let (map, start) = Map::from_ascii(&rows.join("\n")).unwrap();
+        let start = start.expect("map needs an @ for the player");
+        ecs.insert(map);
+        ecs.insert(GameLog::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(ParticleBuilder::new());
+        ecs.insert(RunState::PlayerTurn);
+
+        let player = ecs
+            .create_entity()
+            .with(Position {
+                x: start.x,
+                y: start.y,
+            })
+            .build();
+        ecs.insert(player);
+        (ecs, player)
+    }
+
+    fn monster_at(ecs: &mut World, x: i32, y: i32) -> Entity {
+        let ent = ecs
+            .create_entity()
+            .with(Position { x, y })
+            .with(Name {
+                name: "Goblin".to_string(),
+            })
+            .with(CombatStats {
+                max_hp: 10,
+                hp: 10,
+                defense: 0,
+                power: 1,
+            })
+            .build();
+        let mut map = ecs.fetch_mut::<Map>();
+        let idx = map.xy_idx(x, y);
+        map.tile_content[idx].push(ent);
+        ent
+    }
+
+    #[test]
+    fn blasts_do_not_leak_through_walls() {
+        // Open floor, split down the middle by a wall.
+        let (mut ecs, player) = world_with_map(&[
+            "##########",
+            "#....#...#",
+            "#....#...#",
+            "#....#...#",
+            "#....#...#",
+            "#.@..#...#",
+            "#....#...#",
+            "#....#...#",
+            "#....#...#",
+            "##########",
+        ]);
+        let near = monster_at(&mut ecs, 4, 4);
+        let beyond = monster_at(&mut ecs, 6, 5);
+        let scroll = ecs
+            .create_entity()
+            .with(Name {
+                name: "Fireball Scroll".to_string(),
+            })
+            .with(Item {})
+            .with(InflictsDamage {
+                damage: 5,
+                damage_type: DamageType::Fire,
+            })
+            .with(AreaOfEffect { radius: 2 })
+            .build();
+
+        // Aim straight at the wall.
+        ecs.write_storage::<WantsToUseItem>()
+            .insert(
+                player,
+                WantsToUseItem {
+                    item: scroll,
+                    target: Some(Point::new(5, 5)),
+                },
+            )
+            .unwrap();
+        ItemUseSystem {}.run_now(&ecs);
+
+        let suffering = ecs.read_storage::<SufferDamage>();
+        assert!(suffering.contains(near));
+        assert!(!suffering.contains(beyond));
+    }
+
+    #[test]
+    fn immune_targets_shrug_off_confusion() {
+        let (mut ecs, player) = world_with_map(&["#####", "#@..#", "#####"]);
+        let slime = monster_at(&mut ecs, 3, 1);
+        ecs.write_storage::<Immunities>()
+            .insert(
+                slime,
+                Immunities {
+                    effects: vec![StatusEffect::Confusion],
+                },
+            )
+            .unwrap();
+        let scroll = ecs
+            .create_entity()
+            .with(Name {
+                name: "Confusion Scroll".to_string(),
+            })
+            .with(Item {})
+            .with(Confusion { turns: 4 })
+            .build();
+
+        ecs.write_storage::<WantsToUseItem>()
+            .insert(
+                player,
+                WantsToUseItem {
+                    item: scroll,
+                    target: Some(Point::new(3, 1)),
+                },
+            )
+            .unwrap();
+        ItemUseSystem {}.run_now(&ecs);
+
+        assert!(!ecs.read_storage::<Confusion>().contains(slime));
+    }
+
+    fn ration(ecs: &mut World) -> Entity {
+        ecs.create_entity()
+            .with(Name {
+                name: "Rations".to_string(),
+            })
+            .with(Item {})
+            .with(Consumable {})
+            .with(ProvidesFood {})
+            .build()
+    }
+
+    /// A one-tile room for tests that only care about the player's pack.
+    const CLOSET: [&str; 3] = ["###", "#@#", "###"];
+
+    fn set_hunger(ecs: &mut World, player: Entity, state: HungerState, duration: i32) {
+        ecs.write_storage::<HungerClock>()
+            .insert(player, HungerClock { state, duration })
+            .unwrap();
+    }
+
+    fn eat(ecs: &mut World, eater: Entity, item: Entity) {
+        ecs.write_storage::<WantsToUseItem>()
+            .insert(eater, WantsToUseItem { item, target: None })
+            .unwrap();
+        ItemUseSystem {}.run_now(ecs);
+        ecs.maintain();
+    }
+
+    #[test]
+    fn eating_feeds_a_starving_player() {
+        let (mut ecs, player) = world_with_map(&CLOSET);
+        set_hunger(&mut ecs, player, HungerState::Starving, 0);
+        let food = ration(&mut ecs);
+
+        eat(&mut ecs, player, food);
+
+        let clocks = ecs.read_storage::<HungerClock>();
+        let clock = clocks.get(player).unwrap();
+        assert!(clock.state == HungerState::WellFed);
+        assert_eq!(clock.duration, WELL_FED_DURATION);
+        assert!(!ecs.entities().is_alive(food));
+    }
+
+    #[test]
+    fn food_is_kept_until_the_player_is_hungry() {
+        let (mut ecs, player) = world_with_map(&CLOSET);
+        set_hunger(&mut ecs, player, HungerState::Normal, 100);
+        let food = ration(&mut ecs);
+
+        eat(&mut ecs, player, food);
+
+        assert!(ecs.entities().is_alive(food));
+        assert!(
+            ecs.read_storage::<HungerClock>().get(player).unwrap().state == HungerState::Normal
+        );
+    }
+
+    #[test]
+    fn food_is_kept_when_nothing_can_eat_it() {
+        let (mut ecs, player) = world_with_map(&CLOSET);
+        let food = ration(&mut ecs);
+
+        eat(&mut ecs, player, food);
+
+        assert!(ecs.entities().is_alive(food));
+    }
+
+    #[test]
+    fn full_pack_leaves_items_on_the_ground() {
+        let (mut ecs, player) = world_with_map(&CLOSET);
+        ecs.write_storage::<PackCapacity>()
+            .insert(player, PackCapacity { max_items: 1 })
+            .unwrap();
+        let potions: Vec<Entity> = (0..2)
+            .map(|_| {
+                ecs.create_entity()
+                    .with(Name {
+                        name: "Health Potion".to_string(),
+                    })
+                    .with(Item {})
+                    .with(Position { x: 1, y: 1 })
+                    .build()
+            })
+            .collect();
+        for item in potions.iter() {
+            ecs.write_storage::<WantsToPickupItem>()
+                .insert(
+                    *item,
+                    WantsToPickupItem {
+                        collected_by: player,
+                        item: *item,
+                    },
+                )
+                .unwrap();
+        }
+
+        ItemCollectionSystem {}.run_now(&ecs);
+        ecs.maintain();
+
+        assert_eq!(items_carried(&ecs, player), 1);
+        assert!(!pack_has_room(&ecs, player));
+        assert!(ecs.read_storage::<Position>().get(potions[1]).is_some());
+        assert!(ecs.read_storage::<WantsToPickupItem>().is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+use rltk::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const KEYBINDINGS_FILE: &str = "./keybindings.json";
+
+/// Logical actions the player can take from the main game screen.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Action {
+    MoveWest,
+    MoveEast,
+    MoveNorth,
+    MoveSouth,
+    MoveNorthEast,
+    MoveNorthWest,
+    MoveSouthEast,
+    MoveSouthWest,
+    PickUp,
+    Inventory,
+    Eat,
+    Fire,
+    Drop,
+    Remove,
+    Character,
+    MessageLog,
+    Overview,
+    Look,
+    AutoExplore,
+    Wait,
+    Search,
+    Disarm,
+    Descend,
+    Menu,
+}
+
+/// Every key that can be bound, used to turn key names from the config file back into keys.
+const BINDABLE_KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Numpad0,
+    VirtualKeyCode::Numpad1,
+    VirtualKeyCode::Numpad2,
+    VirtualKeyCode::Numpad3,
+    VirtualKeyCode::Numpad4,
+    VirtualKeyCode::Numpad5,
+    VirtualKeyCode::Numpad6,
+    VirtualKeyCode::Numpad7,
+    VirtualKeyCode::Numpad8,
+    VirtualKeyCode::Numpad9,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Slash,
+    VirtualKeyCode::Semicolon,
+    VirtualKeyCode::Apostrophe,
+    VirtualKeyCode::Minus,
+    VirtualKeyCode::Equals,
+    VirtualKeyCode::LBracket,
+    VirtualKeyCode::RBracket,
+    VirtualKeyCode::Backslash,
+    VirtualKeyCode::Grave,
+];
+
+/// Finds the key with the given name (as written by [`key_name`]).
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|key| key_name(**key) == name)
+        .copied()
+}
+
+/// Name a key is stored under in the bindings file.
+pub fn key_name(key: VirtualKeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// Resource mapping pressed keys to the [`Action`] they trigger.
+#[derive(Clone, Debug)]
+pub struct KeyBindings {
+    keys: HashMap<VirtualKeyCode, Action>,
+}
+
+impl Default for KeyBindings {
+    /// The classic layout: arrows, numpad, vi-keys and WASD all move.
+    fn default() -> Self {
+        use VirtualKeyCode::*;
+
+        let mut bindings = KeyBindings {
+            keys: HashMap::new(),
+        };
+        bindings.bind_all(Action::MoveWest, &[Left, Numpad4, H, A]);
+        bindings.bind_all(Action::MoveEast, &[Right, Numpad6, L, D]);
+        bindings.bind_all(Action::MoveNorth, &[Up, Numpad8, K, W]);
+        bindings.bind_all(Action::MoveSouth, &[Down, Numpad2, J, S]);
+        bindings.bind_all(Action::MoveNorthEast, &[Numpad9, U, E]);
+        bindings.bind_all(Action::MoveNorthWest, &[Numpad7, Y, Q]);
+        bindings.bind_all(Action::MoveSouthEast, &[Numpad3, N]);
+        bindings.bind_all(Action::MoveSouthWest, &[Numpad1, B, Z]);
+        bindings.bind_all(Action::PickUp, &[G]);
+        bindings.bind_all(Action::Inventory, &[I]);
+        // `E` already moves north-east in the WASD layout.
+        bindings.bind_all(Action::Eat, &[F]);
+        bindings.bind_all(Action::Fire, &[Tab]);
+        bindings.bind_all(Action::Drop, &[P]);
+        bindings.bind_all(Action::Remove, &[R]);
+        bindings.bind_all(Action::Character, &[C]);
+        bindings.bind_all(Action::MessageLog, &[Slash]);
+        bindings.bind_all(Action::Overview, &[M]);
+        bindings.bind_all(Action::Look, &[X]);
+        bindings.bind_all(Action::AutoExplore, &[O]);
+        bindings.bind_all(Action::Wait, &[Space]);
+        bindings.bind_all(Action::Search, &[V]);
+        bindings.bind_all(Action::Disarm, &[T]);
+        bindings.bind_all(Action::Descend, &[Period]);
+        bindings.bind_all(Action::Menu, &[Escape]);
+        bindings
+    }
+}
+
+impl KeyBindings {
+    /// Looks up the action bound to `key`, if any.
+    pub fn action_for(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.keys.get(&key).copied()
+    }
+
+    /// Binds `key` to `action`, replacing whatever it was bound to before.
+    pub fn bind(&mut self, key: VirtualKeyCode, action: Action) {
+        self.keys.insert(key, action);
+    }
+
+    fn bind_all(&mut self, action: Action, keys: &[VirtualKeyCode]) {
+        for key in keys.iter() {
+            self.bind(*key, action);
+        }
+    }
+
+    /// Serializes the bindings as a JSON object of action name to key names.
+    pub fn to_json(&self) -> String {
+        let mut by_action: BTreeMap<Action, Vec<String>> = BTreeMap::new();
+        for (key, action) in self.keys.iter() {
+            by_action.entry(*action).or_default().push(key_name(*key));
+        }
+        for keys in by_action.values_mut() {
+            keys.sort();
+        }
+        serde_json::to_string_pretty(&by_action).expect("Unable to serialize key bindings")
+    }
+
+    /// Parses bindings written by [`KeyBindings::to_json`].
+    ///
+    /// Each action the file lists gets exactly the keys listed for it, so default keys can be
+    /// moved or removed. Actions missing from the file (such as ones added since it was
+    /// written) keep their default keys. Unknown key names are skipped.
+    pub fn from_json(json: &str) -> serde_json::Result<KeyBindings> {
+        let by_action: BTreeMap<Action, Vec<String>> = serde_json::from_str(json)?;
+        let mut bindings = KeyBindings::default();
+        bindings
+            .keys
+            .retain(|_, action| !by_action.contains_key(action));
+        for (action, names) in by_action.iter() {
+            for key in names.iter().filter_map(|name| key_from_name(name)) {
+                bindings.bind(key, *action);
+            }
+        }
+        Ok(bindings)
+    }
+
+    /// Loads the bindings file, falling back to the defaults if it's unreadable.
+    ///
+    /// If there's no file yet, the defaults are written out so there's something to edit.
+    /// Failing to write it isn't fatal; the defaults are still used.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> KeyBindings {
+        match std::fs::read_to_string(KEYBINDINGS_FILE) {
+            Ok(json) => KeyBindings::from_json(&json).unwrap_or_default(),
+            Err(_) => {
+                let bindings = KeyBindings::default();
+                bindings.save();
+                bindings
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> KeyBindings {
+        KeyBindings::default()
+    }
+
+    /// Writes the bindings file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) {
+        if let Err(e) = std::fs::write(KEYBINDINGS_FILE, self.to_json()) {
+            rltk::console::log(format!("Unable to write key bindings: {}", e));
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_files_keep_defaults_for_actions_they_do_not_list() {
+        let bindings = KeyBindings::from_json(r#"{ "Inventory": ["Tab"] }"#).unwrap();
+        assert_eq!(
+            bindings.action_for(VirtualKeyCode::Tab),
+            Some(Action::Inventory)
+        );
+        assert_eq!(bindings.action_for(VirtualKeyCode::V), Some(Action::Search));
+        assert_eq!(
+            bindings.action_for(VirtualKeyCode::Numpad9),
+            Some(Action::MoveNorthEast)
+        );
+    }
+
+    #[test]
+    fn listed_actions_lose_the_default_keys_they_leave_out() {
+        let bindings = KeyBindings::from_json(r#"{ "Inventory": ["J"], "Search": [] }"#).unwrap();
+        assert_eq!(
+            bindings.action_for(VirtualKeyCode::J),
+            Some(Action::Inventory)
+        );
+        assert_eq!(bindings.action_for(VirtualKeyCode::I), None);
+        assert_eq!(bindings.action_for(VirtualKeyCode::V), None);
+    }
+}
@@ -23,29 +23,53 @@ pub use map::*;
 pub use map_builder::*;
 pub use rect::Rect;
 
+mod blood_decay_system;
+mod camera;
 mod components;
 mod damage_system;
+mod debug;
+mod difficulty;
+mod display;
 mod gamelog;
 mod gui;
+mod hazard_system;
+mod hotbar;
 mod hunger_system;
 mod inventory_system;
+mod keybindings;
 mod map;
 mod map_builder;
 mod map_indexing_system;
 mod melee_combat_system;
 mod monster_ai_system;
 mod particle_system;
+mod pathing;
 mod player;
 mod random_table;
+mod ranged_combat_system;
 mod rect;
 mod rex_assets;
+mod scent_system;
+mod seed;
+mod settings;
+mod shop;
 mod spawner;
+mod status_system;
 mod trigger_system;
 mod visibility_system;
+mod wandering_spawn_system;
 
 pub mod saveload_system;
 
-const SHOW_MAPGEN_VISUALIZER: bool = true;
+/// Walking distance uncovered by each frame of a [`RunState::MagicMapFlood`].
+const MAGIC_MAP_BAND: f32 = 2.0;
+
+/// Walking distances from the player for the [`RunState::MagicMapFlood`] in
+/// progress, worked out once on its first frame.
+#[derive(Default)]
+struct MagicMapDistances {
+    distances: Vec<f32>,
+}
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum RunState {
@@ -53,8 +77,20 @@ pub enum RunState {
     PreRun,
     PlayerTurn,
     MonsterTurn,
-    ShowInventory,
-    ShowDropItem,
+    ShowInventory {
+        page: usize,
+        hotkey: Option<usize>,
+    },
+    ShowDropItem {
+        page: usize,
+    },
+    ShowEat {
+        page: usize,
+    },
+    ShowShop {
+        mode: gui::ShopMode,
+        page: usize,
+    },
     ShowTargeting {
         range: i32,
         item: Entity,
@@ -64,12 +100,129 @@ pub enum RunState {
     },
     SaveGame,
     NextLevel,
+    /// Debug only: throws away the current level and builds a new one at the same depth.
+    RegenerateLevel,
     ShowRemoveItem,
     MagicMapReveal {
         row: i32,
+        reveal_traps: bool,
+    },
+    /// Magic mapping that spreads out from the player one band of walking distance at a time.
+    MagicMapFlood {
+        band: i32,
+        reveal_traps: bool,
     },
     GameOver,
+    Victory,
     MapGeneration,
+    ShowLog {
+        offset: usize,
+    },
+    ShowMap {
+        x: i32,
+        y: i32,
+    },
+    Looking {
+        x: i32,
+        y: i32,
+    },
+    SeedEntry,
+    ShowCharacter,
+    InGameMenu {
+        menu_selection: gui::InGameMenuSelection,
+    },
+    LoadMenu {
+        selection: usize,
+    },
+    /// Starting a new game with every save slot full, so one has to be given up.
+    OverwriteSlot {
+        selection: usize,
+        seed: Option<seed::GameSeed>,
+    },
+    Options {
+        selection: usize,
+    },
+    DifficultySelect {
+        selection: difficulty::Difficulty,
+        seed: Option<seed::GameSeed>,
+    },
+}
+
+/// Registers every component type with the world.
+fn register_components(ecs: &mut World) {
+    ecs.register::<Position>();
+    ecs.register::<Renderable>();
+    ecs.register::<Player>();
+    ecs.register::<Viewshed>();
+    ecs.register::<Monster>();
+    ecs.register::<Faction>();
+    ecs.register::<Name>();
+    ecs.register::<BlocksTile>();
+    ecs.register::<CombatStats>();
+    ecs.register::<WantsToMelee>();
+    ecs.register::<Ranged>();
+    ecs.register::<AreaOfEffect>();
+    ecs.register::<Confusion>();
+    ecs.register::<HungerClock>();
+    ecs.register::<InflictsDamage>();
+    ecs.register::<SufferDamage>();
+    ecs.register::<Item>();
+    ecs.register::<Consumable>();
+    ecs.register::<ProvidesFood>();
+    ecs.register::<ProvidesHealing>();
+    ecs.register::<InBackpack>();
+    ecs.register::<WantsToPickupItem>();
+    ecs.register::<WantsToDropItem>();
+    ecs.register::<WantsToUseItem>();
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
+    ecs.register::<Equippable>();
+    ecs.register::<Equipped>();
+    ecs.register::<MeleePowerBonus>();
+    ecs.register::<DefenseBonus>();
+    ecs.register::<WantsToRemoveItem>();
+    ecs.register::<ParticleLifetime>();
+    ecs.register::<MagicMapper>();
+    ecs.register::<Hidden>();
+    ecs.register::<EntryTrigger>();
+    ecs.register::<EntityMoved>();
+    ecs.register::<SingleActivation>();
+    ecs.register::<TeleportsVictim>();
+    ecs.register::<RaisesAlarm>();
+    ecs.register::<Alerted>();
+    ecs.register::<CanFlee>();
+    ecs.register::<AiState>();
+    ecs.register::<Resistances>();
+    ecs.register::<Telepathy>();
+    ecs.register::<SummonsMinions>();
+    ecs.register::<WinsGame>();
+    ecs.register::<LeavesCorpse>();
+    ecs.register::<LifeDrain>();
+    ecs.register::<StatDrain>();
+    ecs.register::<SplitOnHit>();
+    ecs.register::<Cleave>();
+    ecs.register::<LootTable>();
+    ecs.register::<Speed>();
+    ecs.register::<Gold>();
+    ecs.register::<Currency>();
+    ecs.register::<Price>();
+    ecs.register::<Vendor>();
+    ecs.register::<Lingers>();
+    ecs.register::<Poisoned>();
+    ecs.register::<CuresStatus>();
+    ecs.register::<Durability>();
+    ecs.register::<RepairsEquipment>();
+    ecs.register::<PackCapacity>();
+    ecs.register::<TracksByScent>();
+    ecs.register::<Immunities>();
+    ecs.register::<Sprung>();
+    ecs.register::<AnimatedRenderable>();
+    ecs.register::<Knockback>();
+    ecs.register::<Weapon>();
+    ecs.register::<WantsToShoot>();
+    ecs.register::<Ammo>();
+    ecs.register::<Guardian>();
+    ecs.register::<HotbarSlot>();
 }
 
 fn main() -> rltk::BError {
@@ -78,8 +231,9 @@ fn main() -> rltk::BError {
         .with_title("Roguelike Tutorial")
         .build()?;
 
-    // Give it a retro vibe, because that's cool.
-    context.with_post_scanlines(true);
+    // Give it a retro vibe, because that's cool (unless the player turned it off).
+    let game_settings = settings::GameSettings::load();
+    game_settings.display.apply(&mut context);
 
     let mut gs = State {
         ecs: World::new(),
@@ -91,55 +245,46 @@ fn main() -> rltk::BError {
         mapgen_timer: 0.0,
     };
 
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Monster>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<CombatStats>();
-    gs.ecs.register::<WantsToMelee>();
-    gs.ecs.register::<Ranged>();
-    gs.ecs.register::<AreaOfEffect>();
-    gs.ecs.register::<Confusion>();
-    gs.ecs.register::<HungerClock>();
-    gs.ecs.register::<InflictsDamage>();
-    gs.ecs.register::<SufferDamage>();
-    gs.ecs.register::<Item>();
-    gs.ecs.register::<Consumable>();
-    gs.ecs.register::<ProvidesFood>();
-    gs.ecs.register::<ProvidesHealing>();
-    gs.ecs.register::<InBackpack>();
-    gs.ecs.register::<WantsToPickupItem>();
-    gs.ecs.register::<WantsToDropItem>();
-    gs.ecs.register::<WantsToUseItem>();
-    gs.ecs.register::<SimpleMarker<SerializeMe>>();
-    gs.ecs.register::<SerializationHelper>();
-    gs.ecs.register::<Equippable>();
-    gs.ecs.register::<Equipped>();
-    gs.ecs.register::<MeleePowerBonus>();
-    gs.ecs.register::<DefenseBonus>();
-    gs.ecs.register::<WantsToRemoveItem>();
-    gs.ecs.register::<ParticleLifetime>();
-    gs.ecs.register::<MagicMapper>();
-    gs.ecs.register::<Hidden>();
-    gs.ecs.register::<EntryTrigger>();
-    gs.ecs.register::<EntityMoved>();
-    gs.ecs.register::<SingleActivation>();
+    register_components(&mut gs.ecs);
 
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
     gs.ecs.insert(rex_assets::RexAssets::new());
-    gs.ecs.insert(Map::new(1));
+    gs.ecs
+        .insert(Map::new(1, MAPWIDTH as i32, MAPHEIGHT as i32));
     gs.ecs.insert(Point::new(0, 0));
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-    let player_ent = spawner::player(&mut gs.ecs, 0, 0);
+    gs.ecs.insert(camera::Camera::new());
+    gs.ecs.insert(gui::TargetingCursor::default());
+    gs.ecs.insert(gui::SeedInput::default());
+    gs.ecs.insert(gui::MenuMouse::default());
+    gs.ecs.insert(AutoExplore::default());
+    gs.ecs.insert(PlayerStepped::default());
+    gs.ecs.insert(Travel::default());
+    gs.ecs.insert(hotbar::Hotbar::default());
+    gs.ecs.insert(scent_system::ScentMap::default());
+    game_settings.apply(&mut gs.ecs);
+    gs.ecs.insert(game_settings);
+    gs.ecs.insert(hunger_system::AutoEat::default());
+    gs.ecs.insert(debug::DebugCommands::default());
+    gs.ecs.insert(keybindings::KeyBindings::load());
+    gs.ecs.insert(saveload_system::SaveSlot::default());
+    gs.ecs.insert(saveload_system::SaveSlotHeaders::default());
+    gs.ecs.insert(difficulty::Difficulty::default());
+    gs.ecs.insert(spawner::SpawnRequests::default());
+    gs.ecs.insert(visibility_system::FovCache::default());
+    gs.ecs.insert(MagicMapDistances::default());
+    gs.ecs.insert(damage_system::GuardianSlain::default());
+    // Record the seed so the world can be reproduced, and drive all randomness from it.
+    let game_seed = seed::GameSeed::random();
+    gs.ecs.insert(game_seed.rng());
+    gs.ecs.insert(game_seed);
+    let player_ent = spawner::player(&mut gs.ecs, 0, 0, &spawner::StartingLoadout::default());
     gs.ecs.insert(player_ent);
 
     // Init the game log, inserting as a resource.
-    gs.ecs.insert(gamelog::GameLog {
-        entries: vec!["Welcome to Rusty Roguelike!".to_string()],
-    });
+    let mut log = gamelog::GameLog::default();
+    log.push(0, "Welcome to Rusty Roguelike!");
+    gs.ecs.insert(log);
+    gs.ecs.insert(gamelog::TurnCounter::default());
     // Game starts in prerun state to set up systems before beginning.
     gs.ecs.insert(particle_system::ParticleBuilder::new());
     // gs.ecs.insert(RunState::MainMenu { menu_selection: gui::MainMenuSelection::NewGame });
@@ -164,6 +309,8 @@ impl State {
     fn run_systems(&mut self) {
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
+        let mut scent = scent_system::ScentSystem {};
+        scent.run_now(&self.ecs);
         let mut mob = MonsterAI {};
         mob.run_now(&self.ecs);
         let mut triggers = trigger_system::TriggerSystem {};
@@ -172,6 +319,8 @@ impl State {
         mapindex.run_now(&self.ecs);
         let mut melee = MeleeCombatSystem {};
         melee.run_now(&self.ecs);
+        let mut ranged = ranged_combat_system::RangedCombatSystem {};
+        ranged.run_now(&self.ecs);
         let mut damage = DamageSystem {};
         damage.run_now(&self.ecs);
         let mut pickup = ItemCollectionSystem {};
@@ -182,10 +331,20 @@ impl State {
         drop_items.run_now(&self.ecs);
         let mut item_remove = ItemRemoveSystem {};
         item_remove.run_now(&self.ecs);
+        let mut hazards = hazard_system::HazardSystem {};
+        hazards.run_now(&self.ecs);
+        // Status particles are requested before this frame's particles are spawned.
+        let mut status = status_system::StatusSystem {};
+        status.run_now(&self.ecs);
         let mut particles = ParticleSpawnSystem {};
         particles.run_now(&self.ecs);
         let mut hunger = HungerSystem {};
         hunger.run_now(&self.ecs);
+        let mut blood_decay = blood_decay_system::BloodDecaySystem {};
+        blood_decay.run_now(&self.ecs);
+        let mut wanderers = wandering_spawn_system::WanderingSpawnSystem {};
+        wanderers.run_now(&self.ecs);
+        spawner::spawn_requested(&mut self.ecs);
 
         self.ecs.maintain();
     }
@@ -194,22 +353,27 @@ impl State {
         self.mapgen_index = 0;
         self.mapgen_timer = 0.0;
         self.mapgen_history.clear();
-        let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
-        let mut builder = map_builder::random_builder(new_depth, &mut rng);
-        builder.build_map(&mut rng);
-        std::mem::drop(rng);
-
-        self.mapgen_history = builder.build_data.history.clone();
-        let player_start = {
-            let mut worldmap_res = self.ecs.write_resource::<Map>();
-            *worldmap_res = builder.build_data.map.clone();
-            builder.build_data.start.as_mut().unwrap().clone()
+        let mut build_data = {
+            let difficulty = *self.ecs.fetch::<difficulty::Difficulty>();
+            let record_history = self.ecs.fetch::<MapgenVisualizer>().enabled;
+            let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+            map_builder::build_level(new_depth, difficulty, record_history, &mut rng)
         };
 
-        builder.spawn_entities(&mut self.ecs);
+        self.mapgen_history = std::mem::take(&mut build_data.history);
+        let player_start = build_data.start.unwrap();
+        *self.ecs.write_resource::<Map>() = build_data.map;
+        // The old level's trail and sight lines mean nothing here.
+        *self.ecs.write_resource::<scent_system::ScentMap>() = scent_system::ScentMap::default();
+        *self.ecs.write_resource::<visibility_system::FovCache>() =
+            visibility_system::FovCache::default();
+
+        for (idx, name) in build_data.spawn_list.iter() {
+            spawner::spawn_entity(&mut self.ecs, &(idx, name));
+        }
         {
             let mut player_position = self.ecs.write_resource::<Point>();
-            *player_position = Point::new(player_start.x, player_start.y);
+            *player_position = player_start.into();
         }
         {
             let mut position_components = self.ecs.write_storage::<Position>();
@@ -266,14 +430,19 @@ impl State {
         to_delete
     }
 
-    /// When using a staircase, generates a new level and sends the player to it.
-    fn goto_next_level(&mut self) {
-        // Delete entities that aren't the player or their equipment.
+    /// Deletes everything that doesn't travel with the player between levels.
+    fn clear_level(&mut self) {
         for target in self.entities_to_remove_on_level_change() {
             self.ecs
                 .delete_entity(target)
                 .expect("Unable to delete entity");
         }
+    }
+
+    /// When using a staircase, generates a new level and sends the player to it.
+    fn goto_next_level(&mut self) {
+        // Delete entities that aren't the player or their equipment.
+        self.clear_level();
 
         let new_depth = {
             let worldmap_res = self.ecs.fetch::<Map>();
@@ -283,16 +452,46 @@ impl State {
 
         // Notify player of level change and give them a health boost.
         let player_ent = self.ecs.fetch::<Entity>();
+        let turn = self.ecs.fetch::<gamelog::TurnCounter>().turn;
         let mut log = self.ecs.fetch_mut::<gamelog::GameLog>();
-        log.entries
-            .push("You descend further into the depths, and take a moment to heal".to_string());
+        log.push(
+            turn,
+            "You descend further into the depths, and take a moment to heal",
+        );
         if let Some(player_stats) = self.ecs.write_storage::<CombatStats>().get_mut(*player_ent) {
             player_stats.hp = i32::max(player_stats.hp, player_stats.max_hp / 2);
         }
     }
 
-    /// Cleans up resources and storage after a game over event, and sets up for a new game.
-    fn game_over_cleanup(&mut self) {
+    /// Debug only: rebuilds the current depth from a freshly picked seed.
+    fn regenerate_level(&mut self) {
+        self.clear_level();
+
+        let seed = seed::GameSeed::random();
+        *self.ecs.write_resource::<rltk::RandomNumberGenerator>() = seed.rng();
+        *self.ecs.write_resource::<seed::GameSeed>() = seed;
+        let depth = self.ecs.fetch::<Map>().depth;
+        self.generate_world_map(depth);
+
+        let turn = self.ecs.fetch::<gamelog::TurnCounter>().turn;
+        self.ecs.fetch_mut::<gamelog::GameLog>().push(
+            turn,
+            format!(
+                "[debug] Regenerated depth {} from seed {}.",
+                depth, seed.seed
+            ),
+        );
+    }
+
+    /// Sets up a new run generated from `seed`, which saves to `slot`.
+    fn start_new_game(&mut self, seed: seed::GameSeed, slot: usize) {
+        self.game_over_cleanup(seed);
+        self.ecs.write_resource::<saveload_system::SaveSlot>().slot = slot;
+    }
+
+    /// Cleans up resources and storage after a game over event, and sets up for a new game
+    /// generated from `seed`.
+    fn game_over_cleanup(&mut self, seed: seed::GameSeed) {
         // Delete all game entities in preparation for new ones.
         let mut to_delete: Vec<Entity> = Vec::new();
         self.ecs.entities().join().for_each(|e| to_delete.push(e));
@@ -302,11 +501,28 @@ impl State {
 
         {
             // Create a new player and get their intended location.
-            let player_ent = spawner::player(&mut self.ecs, 0, 0);
+            let difficulty = *self.ecs.fetch::<difficulty::Difficulty>();
+            let loadout = spawner::StartingLoadout::for_difficulty(difficulty);
+            let player_ent = spawner::player(&mut self.ecs, 0, 0, &loadout);
             let mut player_ent_writer = self.ecs.write_resource::<Entity>();
             *player_ent_writer = player_ent;
         }
 
+        // Don't carry an auto-explore run or a trip over into the new game.
+        self.ecs.write_resource::<AutoExplore>().active = false;
+        self.ecs.write_resource::<Travel>().destination = None;
+        *self.ecs.write_resource::<hotbar::Hotbar>() = hotbar::Hotbar::default();
+        self.ecs
+            .write_resource::<damage_system::GuardianSlain>()
+            .slain = false;
+
+        // Start the new game's turn count over.
+        *self.ecs.write_resource::<gamelog::TurnCounter>() = gamelog::TurnCounter::default();
+
+        // Reseed so the new world is reproducible from its seed.
+        *self.ecs.write_resource::<rltk::RandomNumberGenerator>() = seed.rng();
+        *self.ecs.write_resource::<seed::GameSeed>() = seed;
+
         self.generate_world_map(1);
     }
 }
@@ -322,31 +538,84 @@ impl GameState for State {
         // Clear the active console.
         ctx.cls();
         particle_system::cull_dead_particles(&mut self.ecs, ctx);
+        particle_system::animate_glyphs(&mut self.ecs, ctx);
 
         // Keeps the system from rendering the map behind the main menu.
         match new_runstate {
-            RunState::MainMenu { .. } => {}
+            RunState::MainMenu { .. }
+            | RunState::SeedEntry
+            | RunState::LoadMenu { .. }
+            | RunState::OverwriteSlot { .. }
+            | RunState::Options { .. }
+            | RunState::DifficultySelect { .. }
+            | RunState::ShowMap { .. } => {}
             // If we're not at the main menu, go ahead and render the map.
-            RunState::GameOver { .. } => {}
+            RunState::GameOver { .. } | RunState::Victory => {}
             _ => {
-                draw_map(&self.ecs.fetch::<Map>(), ctx);
+                // Keep the camera centered on the player before drawing anything.
+                {
+                    let map = self.ecs.fetch::<Map>();
+                    let player_pos = self.ecs.fetch::<Point>();
+                    let mut camera = self.ecs.write_resource::<camera::Camera>();
+                    camera.center_on(&map, *player_pos);
+                }
+                draw_map(
+                    &self.ecs.fetch::<Map>(),
+                    &self.ecs.fetch::<camera::Camera>(),
+                    ctx,
+                );
                 {
+                    let entities = self.ecs.entities();
                     let positions = self.ecs.read_storage::<Position>();
                     let renderables = self.ecs.read_storage::<Renderable>();
+                    let animations = self.ecs.read_storage::<AnimatedRenderable>();
                     let hidden = self.ecs.read_storage::<Hidden>();
+                    let monsters = self.ecs.read_storage::<Monster>();
                     let map = self.ecs.fetch::<Map>();
+                    let camera = self.ecs.fetch::<camera::Camera>();
+                    let player_ent = self.ecs.fetch::<Entity>();
+                    let telepathic = self.ecs.read_storage::<Telepathy>().contains(*player_ent);
+                    let status_tint = self.ecs.fetch::<display::DisplaySettings>().status_tint;
+                    let combat_stats = self.ecs.read_storage::<CombatStats>();
+                    let confusion = self.ecs.read_storage::<Confusion>();
+                    let can_flee = self.ecs.read_storage::<CanFlee>();
 
                     // Sort our renderables to allow for a rendering order.
-                    let mut data = (&positions, &renderables, !&hidden)
+                    let mut data = (&entities, &positions, &renderables, !&hidden)
                         .join()
                         .collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
+                    data.sort_by_key(|r| r.2.render_order);
 
                     // Visible tiles.
-                    for (pos, render, _) in data.iter() {
+                    for (ent, pos, render, _) in data.iter() {
                         let idx = map.xy_idx(pos.x, pos.y);
+                        let (screen_x, screen_y) = camera.to_screen(pos.x, pos.y);
+                        if !camera.on_screen(screen_x, screen_y) {
+                            continue;
+                        }
+                        let glyph = animations
+                            .get(*ent)
+                            .and_then(|animation| animation.glyph())
+                            .unwrap_or(render.glyph);
                         if map.visible_tiles[idx] {
-                            ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
+                            let bg = match combat_stats.get(*ent) {
+                                Some(stats) if status_tint => display::status_bg(
+                                    stats,
+                                    confusion.contains(*ent),
+                                    can_flee.get(*ent),
+                                ),
+                                _ => render.bg,
+                            };
+                            ctx.set(screen_x, screen_y, render.fg, bg, glyph);
+                        } else if telepathic && monsters.contains(*ent) {
+                            // Sensed (rather than seen) monsters are tinted purple.
+                            ctx.set(
+                                screen_x,
+                                screen_y,
+                                render.fg.lerp(rltk::RGB::named(rltk::PURPLE), 0.6),
+                                rltk::RGB::named(rltk::BLACK),
+                                glyph,
+                            );
                         }
                     }
                     gui::draw_ui(&self.ecs, ctx);
@@ -357,18 +626,23 @@ impl GameState for State {
         // RunState state machine
         match new_runstate {
             RunState::MapGeneration => {
-                if !SHOW_MAPGEN_VISUALIZER {
+                if self.mapgen_history.is_empty() {
                     new_runstate = self.mapgen_next_state.unwrap();
-                }
-                ctx.cls();
-                draw_map(&self.mapgen_history[self.mapgen_index], ctx);
+                } else {
+                    ctx.cls();
+                    // Frame the visualizer on the center of the map being generated.
+                    let snapshot = &self.mapgen_history[self.mapgen_index];
+                    let mut mapgen_camera = camera::Camera::new();
+                    mapgen_camera.center_on(snapshot, snapshot.center_point());
+                    draw_map(snapshot, &mapgen_camera, ctx);
 
-                self.mapgen_timer += ctx.frame_time_ms;
-                if self.mapgen_timer > 300.0 {
-                    self.mapgen_timer = 0.0;
-                    self.mapgen_index += 1;
-                    if self.mapgen_index >= self.mapgen_history.len() {
-                        new_runstate = self.mapgen_next_state.unwrap();
+                    self.mapgen_timer += ctx.frame_time_ms;
+                    if self.mapgen_timer > 300.0 {
+                        self.mapgen_timer = 0.0;
+                        self.mapgen_index += 1;
+                        if self.mapgen_index >= self.mapgen_history.len() {
+                            new_runstate = self.mapgen_next_state.unwrap();
+                        }
                     }
                 }
             }
@@ -385,12 +659,32 @@ impl GameState for State {
                     gui::MainMenuResult::Selected { selected } => {
                         match selected {
                             // Start up a new game
-                            gui::MainMenuSelection::NewGame => new_runstate = RunState::PreRun,
-                            // Try to load a saved game, and resume play.
+                            gui::MainMenuSelection::NewGame => {
+                                new_runstate = RunState::DifficultySelect {
+                                    selection: self
+                                        .ecs
+                                        .fetch::<settings::GameSettings>()
+                                        .difficulty,
+                                    seed: None,
+                                }
+                            }
+                            // Ask for a seed before generating the world.
+                            gui::MainMenuSelection::SeededGame => {
+                                new_runstate = RunState::SeedEntry
+                            }
+                            // Pick which saved game to load.
                             gui::MainMenuSelection::LoadGame => {
-                                saveload_system::load_game(&mut self.ecs);
-                                new_runstate = RunState::AwaitingInput;
-                                saveload_system::delete_save();
+                                *self
+                                    .ecs
+                                    .write_resource::<saveload_system::SaveSlotHeaders>() =
+                                    saveload_system::SaveSlotHeaders::read();
+                                new_runstate = RunState::LoadMenu {
+                                    selection: saveload_system::DEFAULT_SLOT,
+                                }
+                            }
+                            // Change display settings.
+                            gui::MainMenuSelection::Options => {
+                                new_runstate = RunState::Options { selection: 0 }
                             }
                             // Quits the game
                             gui::MainMenuSelection::Quit => {
@@ -400,14 +694,162 @@ impl GameState for State {
                     }
                 }
             }
+            // Choosing how hard the new game is, before its world is generated.
+            RunState::DifficultySelect { seed, .. } => match gui::difficulty_menu(self, ctx) {
+                gui::DifficultyMenuResult::Cancel => {
+                    new_runstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::NewGame,
+                    }
+                }
+                gui::DifficultyMenuResult::NoSelection { selected } => {
+                    new_runstate = RunState::DifficultySelect {
+                        selection: selected,
+                        seed,
+                    }
+                }
+                // Build the new world (and player) to suit the chosen difficulty.
+                gui::DifficultyMenuResult::Selected { selected } => {
+                    *self.ecs.write_resource::<difficulty::Difficulty>() = selected;
+                    // Start in an empty slot, so the new run doesn't overwrite another.
+                    new_runstate = match saveload_system::first_free_slot() {
+                        Some(slot) => {
+                            self.start_new_game(seed.unwrap_or_else(seed::GameSeed::random), slot);
+                            RunState::PreRun
+                        }
+                        None => {
+                            *self
+                                .ecs
+                                .write_resource::<saveload_system::SaveSlotHeaders>() =
+                                saveload_system::SaveSlotHeaders::read();
+                            RunState::OverwriteSlot {
+                                selection: saveload_system::DEFAULT_SLOT,
+                                seed,
+                            }
+                        }
+                    };
+                }
+            },
+            // Every slot is taken, so ask which save the new run replaces.
+            RunState::OverwriteSlot { seed, .. } => match gui::overwrite_menu(self, ctx) {
+                gui::LoadMenuResult::Cancel => {
+                    new_runstate = RunState::DifficultySelect {
+                        selection: *self.ecs.fetch::<difficulty::Difficulty>(),
+                        seed,
+                    }
+                }
+                gui::LoadMenuResult::NoSelection { selected } => {
+                    new_runstate = RunState::OverwriteSlot {
+                        selection: selected,
+                        seed,
+                    }
+                }
+                gui::LoadMenuResult::Selected { selected } => {
+                    self.start_new_game(seed.unwrap_or_else(seed::GameSeed::random), selected);
+                    new_runstate = RunState::PreRun;
+                }
+            },
+            // Switching settings on and off; changes are saved as they're made.
+            RunState::Options { .. } => match gui::options_menu(self, ctx) {
+                gui::OptionsMenuResult::Cancel => {
+                    new_runstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::Options,
+                    }
+                }
+                gui::OptionsMenuResult::NoSelection { selected } => {
+                    new_runstate = RunState::Options {
+                        selection: selected,
+                    }
+                }
+                gui::OptionsMenuResult::Toggle { selected } => {
+                    let game_settings = {
+                        let mut game_settings = self.ecs.write_resource::<settings::GameSettings>();
+                        game_settings.toggle(settings::SettingsOption::ALL[selected]);
+                        *game_settings
+                    };
+                    game_settings.display.apply(ctx);
+                    game_settings.apply(&mut self.ecs);
+                    game_settings.save();
+                    new_runstate = RunState::Options {
+                        selection: selected,
+                    }
+                }
+            },
+            // Choosing a save slot to resume.
+            RunState::LoadMenu { .. } => match gui::load_menu(self, ctx) {
+                gui::LoadMenuResult::Cancel => {
+                    new_runstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::LoadGame,
+                    }
+                }
+                gui::LoadMenuResult::NoSelection { selected } => {
+                    new_runstate = RunState::LoadMenu {
+                        selection: selected,
+                    }
+                }
+                // Try to load the saved game, and resume play.
+                gui::LoadMenuResult::Selected { selected } => {
+                    match saveload_system::load_game(&mut self.ecs, selected) {
+                        Ok(()) => {
+                            // Loading makes new entities, so rebind the keys to the loaded items.
+                            hotbar::restore_bindings(&self.ecs);
+                            *self.ecs.write_resource::<scent_system::ScentMap>() =
+                                scent_system::ScentMap::default();
+                            *self.ecs.write_resource::<visibility_system::FovCache>() =
+                                visibility_system::FovCache::default();
+                            new_runstate = RunState::AwaitingInput;
+                            saveload_system::delete_save(selected);
+                        }
+                        // Leave the save where it is and go back to the menu.
+                        Err(e) => {
+                            rltk::console::log(format!(
+                                "Unable to load slot {}: {}",
+                                selected + 1,
+                                e
+                            ));
+                            new_runstate = RunState::MainMenu {
+                                menu_selection: gui::MainMenuSelection::LoadGame,
+                            };
+                        }
+                    }
+                }
+            },
+            // Typing in a seed to start a new game from.
+            RunState::SeedEntry => match gui::seed_entry(self, ctx) {
+                gui::SeedEntryResult::Cancel => {
+                    new_runstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::SeededGame,
+                    }
+                }
+                gui::SeedEntryResult::NoResponse => {}
+                // Throw away the pre-generated world and build a new one from the seed.
+                gui::SeedEntryResult::Selected { seed } => {
+                    new_runstate = RunState::DifficultySelect {
+                        selection: self.ecs.fetch::<settings::GameSettings>().difficulty,
+                        seed: Some(seed),
+                    };
+                }
+            },
             // Saves the game in its current state.
             RunState::SaveGame => {
                 // Makes a savegame file and saves to it.
-                saveload_system::save_game(&mut self.ecs);
-                // Send the player back to the main menu on save.
-                new_runstate = RunState::MainMenu {
-                    menu_selection: gui::MainMenuSelection::LoadGame,
-                };
+                let slot = self.ecs.fetch::<saveload_system::SaveSlot>().slot;
+                match saveload_system::save_game(&mut self.ecs, slot) {
+                    // Send the player back to the main menu on save.
+                    Ok(()) => {
+                        new_runstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::LoadGame,
+                        };
+                    }
+                    // Going to the menu would throw the run away, so carry on playing instead.
+                    Err(e) => {
+                        rltk::console::log(format!("Unable to save: {}", e));
+                        let turn = self.ecs.fetch::<gamelog::TurnCounter>().turn;
+                        self.ecs
+                            .fetch_mut::<gamelog::GameLog>()
+                            .push(turn, format!("{}.", e));
+                        new_runstate = RunState::AwaitingInput;
+                    }
+                }
             }
             // Tells the system to run all systems before starting the game.
             RunState::PreRun => {
@@ -421,13 +863,28 @@ impl GameState for State {
             }
             // Player has chosen an action--perform their chosen action by updating all systems.
             RunState::PlayerTurn => {
+                self.ecs.write_resource::<gamelog::TurnCounter>().turn += 1;
                 self.run_systems();
                 self.ecs.maintain();
-                match *self.ecs.fetch::<RunState>() {
-                    RunState::MagicMapReveal { .. } => {
-                        new_runstate = RunState::MagicMapReveal { row: 0 };
+                let runstate = *self.ecs.fetch::<RunState>();
+                match runstate {
+                    RunState::MagicMapReveal { reveal_traps, .. } => {
+                        new_runstate = RunState::MagicMapReveal {
+                            row: 0,
+                            reveal_traps,
+                        };
+                    }
+                    RunState::MagicMapFlood { reveal_traps, .. } => {
+                        new_runstate = RunState::MagicMapFlood {
+                            band: 0,
+                            reveal_traps,
+                        };
+                    }
+                    RunState::Victory => new_runstate = RunState::Victory,
+                    _ => {
+                        monster_ai_system::grant_energy(&mut self.ecs);
+                        new_runstate = RunState::MonsterTurn;
                     }
-                    _ => new_runstate = RunState::MonsterTurn,
                 }
             }
             // Monster's turn to act.
@@ -435,52 +892,75 @@ impl GameState for State {
                 // Monster action is handled by the AI, so just run the systems.
                 self.run_systems();
                 self.ecs.maintain();
-                new_runstate = RunState::AwaitingInput;
+                // Fast monsters may have energy for another action before the player's turn.
+                if monster_ai_system::monsters_ready(&self.ecs) {
+                    new_runstate = RunState::MonsterTurn;
+                } else {
+                    new_runstate = RunState::AwaitingInput;
+                }
             }
             // Open the inventory screen and handle inventory actions.
-            RunState::ShowInventory => {
-                let result = gui::show_inventory(self, ctx);
+            RunState::ShowInventory { page, hotkey } => {
+                let result = gui::show_inventory(self, ctx, page, hotkey);
                 match result.0 {
-                    // Pressed escape--just close the inventory and wait for some other input.
-                    gui::ItemMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
+                    // Pressed escape--stop binding a hotkey, or close the inventory.
+                    gui::ItemMenuResult::Cancel => {
+                        new_runstate = match hotkey {
+                            Some(_) => RunState::ShowInventory { page, hotkey: None },
+                            None => RunState::AwaitingInput,
+                        }
+                    }
                     // Haven't selected yet--loop here until something is chosen.
                     gui::ItemMenuResult::NoResponse => {}
+                    // Flipped to another page of the backpack.
+                    gui::ItemMenuResult::TurnPage { page } => {
+                        new_runstate = RunState::ShowInventory { page, hotkey }
+                    }
+                    // Pressed a number--the next item picked gets bound to it.
+                    gui::ItemMenuResult::Hotkey { slot } => {
+                        new_runstate = RunState::ShowInventory {
+                            page,
+                            hotkey: Some(slot),
+                        }
+                    }
                     // Selected something from the inventory.
                     gui::ItemMenuResult::Selected => {
                         let item_ent = result.1.unwrap();
-                        // If the selected item has the `Ranged` component, send to targeting mode.
-                        if let Some(is_ranged) = self.ecs.read_storage::<Ranged>().get(item_ent) {
-                            new_runstate = RunState::ShowTargeting {
-                                range: is_ranged.range,
-                                item: item_ent,
-                            };
-                        } else {
-                            // If not ranged, use the selected item.
-                            let mut intent = self.ecs.write_storage::<WantsToUseItem>();
-                            intent
-                                .insert(
-                                    *self.ecs.fetch::<Entity>(),
-                                    WantsToUseItem {
-                                        item: item_ent,
-                                        target: None,
-                                    },
-                                )
-                                .expect("Unable to insert intent");
-                            // Counts as player action--run systems in `PlayerTurn` to make
-                            // effects of the item take place.
-                            new_runstate = RunState::PlayerTurn;
+                        match hotkey {
+                            Some(slot) => {
+                                let name = self
+                                    .ecs
+                                    .read_storage::<Name>()
+                                    .get(item_ent)
+                                    .unwrap()
+                                    .name
+                                    .clone();
+                                self.ecs
+                                    .write_resource::<hotbar::Hotbar>()
+                                    .assign(slot, item_ent, &name);
+                                let turn = self.ecs.fetch::<gamelog::TurnCounter>().turn;
+                                self.ecs
+                                    .write_resource::<gamelog::GameLog>()
+                                    .push(turn, format!("Key {} now uses the {}.", slot + 1, name));
+                                new_runstate = RunState::ShowInventory { page, hotkey: None };
+                            }
+                            None => new_runstate = player::use_item(&mut self.ecs, item_ent),
                         }
                     }
                 }
             }
             // Open the menu for dropping items from the player's inventory.
-            RunState::ShowDropItem => {
-                let result = gui::drop_item_menu(self, ctx);
+            RunState::ShowDropItem { page } => {
+                let result = gui::drop_item_menu(self, ctx, page);
                 match result.0 {
                     // Pressed escape--exit the menu and wait for another input from the player.
                     gui::ItemMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
                     // Haven't selected anything yet--loop here until we have a selection.
-                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::NoResponse | gui::ItemMenuResult::Hotkey { .. } => {}
+                    // Flipped to another page of the backpack.
+                    gui::ItemMenuResult::TurnPage { page } => {
+                        new_runstate = RunState::ShowDropItem { page }
+                    }
                     // Selected an item to drop.
                     gui::ItemMenuResult::Selected => {
                         // Insert intent to drop the selected item so the game's systems drop it.
@@ -497,14 +977,49 @@ impl GameState for State {
                     }
                 }
             }
+            // Pick something to eat from the backpack.
+            RunState::ShowEat { page } => {
+                let result = gui::eat_menu(self, ctx, page);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse | gui::ItemMenuResult::Hotkey { .. } => {}
+                    gui::ItemMenuResult::TurnPage { page } => {
+                        new_runstate = RunState::ShowEat { page }
+                    }
+                    // Eating goes through the item systems like any other use.
+                    gui::ItemMenuResult::Selected => {
+                        new_runstate = player::use_item(&mut self.ecs, result.1.unwrap())
+                    }
+                }
+            }
+            // Trading with a shopkeeper; buying and selling don't take a turn.
+            RunState::ShowShop { mode, page } => match gui::show_shop(self, ctx, mode, page) {
+                gui::ShopResult::Cancel => new_runstate = RunState::AwaitingInput,
+                gui::ShopResult::NoResponse => {}
+                gui::ShopResult::SwitchMode => {
+                    new_runstate = RunState::ShowShop {
+                        mode: mode.other(),
+                        page: 0,
+                    }
+                }
+                gui::ShopResult::TurnPage { page } => {
+                    new_runstate = RunState::ShowShop { mode, page }
+                }
+                gui::ShopResult::Selected { item } => match mode {
+                    gui::ShopMode::Buy => shop::buy(&mut self.ecs, item),
+                    gui::ShopMode::Sell => shop::sell(&mut self.ecs, item),
+                },
+            },
             // Open the menu for removing equipped items.
             RunState::ShowRemoveItem => {
                 let result = gui::remove_item_menu(self, ctx);
                 match result.0 {
                     // Pressed escape--exit the menu and wait for another input from the player.
                     gui::ItemMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
-                    // Haven't selected anything yet--loop here until we have a selection.
-                    gui::ItemMenuResult::NoResponse => {}
+                    // Haven't selected anything yet (equipment fits on one page).
+                    gui::ItemMenuResult::NoResponse
+                    | gui::ItemMenuResult::TurnPage { .. }
+                    | gui::ItemMenuResult::Hotkey { .. } => {}
                     // Selected a piece of equipment to remove.
                     gui::ItemMenuResult::Selected => {
                         // Insert intent to remove the selected item so the game can take it off.
@@ -524,30 +1039,101 @@ impl GameState for State {
             // Player has selected a ranged item--show the targeting interface.
             RunState::ShowTargeting { range, item } => {
                 // Target is the tile selected by the player through the targeting interface.
-                let target = gui::ranged_target(self, ctx, range);
+                let radius = self
+                    .ecs
+                    .read_storage::<AreaOfEffect>()
+                    .get(item)
+                    .map(|aoe| aoe.radius);
+                let target = gui::ranged_target(self, ctx, range, radius);
                 match target.0 {
                     // Pressed escape--exit targeting and wait for another input from the player.
                     gui::ItemMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
                     // Haven't selected anything yet--loop here until we have a selection.
-                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::NoResponse
+                    | gui::ItemMenuResult::TurnPage { .. }
+                    | gui::ItemMenuResult::Hotkey { .. } => {}
                     // Selected a target.
                     gui::ItemMenuResult::Selected => {
-                        // Insert intent to use the ranged item.
-                        let mut intent = self.ecs.write_storage::<WantsToUseItem>();
-                        intent
-                            .insert(
-                                *self.ecs.fetch::<Entity>(),
-                                WantsToUseItem {
-                                    item,
-                                    target: target.1,
-                                },
-                            )
-                            .expect("Unable to insert intent");
+                        let player_ent = *self.ecs.fetch::<Entity>();
+                        if self.ecs.read_storage::<Weapon>().contains(item) {
+                            // Firing a weapon shoots it rather than using it up.
+                            self.ecs
+                                .write_storage::<WantsToShoot>()
+                                .insert(
+                                    player_ent,
+                                    WantsToShoot {
+                                        target: target.1.unwrap(),
+                                    },
+                                )
+                                .expect("Unable to insert intent");
+                        } else {
+                            // Insert intent to use the ranged item.
+                            self.ecs
+                                .write_storage::<WantsToUseItem>()
+                                .insert(
+                                    player_ent,
+                                    WantsToUseItem {
+                                        item,
+                                        target: target.1,
+                                    },
+                                )
+                                .expect("Unable to insert intent");
+                        }
                         // Systems handle using the item on the player's turn.
                         new_runstate = RunState::PlayerTurn;
                     }
                 }
             }
+            // Scroll back through the message log.
+            RunState::ShowLog { offset } => match gui::show_log(self, ctx, offset) {
+                // Pressed escape--close the log and wait for another input from the player.
+                gui::LogMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
+                // Still reading--stay here with the (possibly) updated scroll offset.
+                gui::LogMenuResult::NoResponse { offset } => {
+                    new_runstate = RunState::ShowLog { offset }
+                }
+            },
+            // Look over the player's gear and stats.
+            RunState::InGameMenu { .. } => match gui::in_game_menu(self, ctx) {
+                gui::InGameMenuResult::NoSelection { selected } => {
+                    new_runstate = RunState::InGameMenu {
+                        menu_selection: selected,
+                    }
+                }
+                gui::InGameMenuResult::Selected { selected } => match selected {
+                    gui::InGameMenuSelection::Continue => new_runstate = RunState::AwaitingInput,
+                    gui::InGameMenuSelection::SaveAndQuit => new_runstate = RunState::SaveGame,
+                    // Throw the run away, so picking "new game" doesn't resume it.
+                    gui::InGameMenuSelection::QuitWithoutSaving => {
+                        self.game_over_cleanup(seed::GameSeed::random());
+                        new_runstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::NewGame,
+                        };
+                    }
+                },
+            },
+            RunState::Looking { x, y } => match gui::look(self, ctx, Point::new(x, y)) {
+                gui::LookResult::Cancel => new_runstate = RunState::AwaitingInput,
+                gui::LookResult::NoResponse { cursor } => {
+                    new_runstate = RunState::Looking {
+                        x: cursor.x,
+                        y: cursor.y,
+                    }
+                }
+            },
+            RunState::ShowMap { x, y } => match gui::show_map(self, ctx, Point::new(x, y)) {
+                gui::MapViewResult::Cancel => new_runstate = RunState::AwaitingInput,
+                gui::MapViewResult::NoResponse { center } => {
+                    new_runstate = RunState::ShowMap {
+                        x: center.x,
+                        y: center.y,
+                    }
+                }
+            },
+            RunState::ShowCharacter => match gui::show_character(self, ctx) {
+                gui::CharacterMenuResult::Cancel => new_runstate = RunState::AwaitingInput,
+                gui::CharacterMenuResult::NoResponse => {}
+            },
             // Went down some stairs.
             RunState::NextLevel => {
                 // Make a new map for the new depth level and send the player to it.
@@ -555,25 +1141,104 @@ impl GameState for State {
                 // PreRun on the new level to set everything up and in motion.
                 new_runstate = RunState::PreRun;
             }
-            RunState::MagicMapReveal { row } => {
-                let mut map = self.ecs.fetch_mut::<Map>();
-                (0..MAPWIDTH)
-                    .map(|x| map.xy_idx(x as i32, row))
-                    .collect::<Vec<usize>>()
-                    .iter()
-                    .for_each(|idx| map.revealed_tiles[*idx] = true);
-
-                if row as usize == MAPHEIGHT - 1 {
+            RunState::RegenerateLevel => {
+                self.regenerate_level();
+                new_runstate = RunState::PreRun;
+            }
+            RunState::MagicMapReveal { row, reveal_traps } => {
+                let last_row = {
+                    let mut map = self.ecs.fetch_mut::<Map>();
+                    (0..map.width)
+                        .map(|x| map.xy_idx(x, row))
+                        .collect::<Vec<usize>>()
+                        .iter()
+                        .for_each(|idx| map.revealed_tiles[*idx] = true);
+                    map.height - 1
+                };
+
+                // Stronger mappers also uncover anything hidden in the swept row.
+                if reveal_traps {
+                    let entities = self.ecs.entities();
+                    let positions = self.ecs.read_storage::<Position>();
+                    let mut hidden = self.ecs.write_storage::<Hidden>();
+                    let found: Vec<Entity> = (&entities, &positions, &hidden)
+                        .join()
+                        .filter(|(_, pos, _)| pos.y == row)
+                        .map(|(ent, _, _)| ent)
+                        .collect();
+                    for ent in found.iter() {
+                        hidden.remove(*ent);
+                    }
+                }
+
+                if row == last_row {
+                    monster_ai_system::grant_energy(&mut self.ecs);
+                    new_runstate = RunState::MonsterTurn;
+                } else {
+                    new_runstate = RunState::MagicMapReveal {
+                        row: row + 1,
+                        reveal_traps,
+                    };
+                }
+            }
+            RunState::MagicMapFlood { band, reveal_traps } => {
+                let (revealed, finished) = {
+                    let mut map = self.ecs.fetch_mut::<Map>();
+                    let mut flood = self.ecs.fetch_mut::<MagicMapDistances>();
+                    if band == 0 {
+                        let player_pos = self.ecs.fetch::<Point>();
+                        flood.distances =
+                            map.walking_distances(map.xy_idx(player_pos.x, player_pos.y));
+                    }
+                    let distances = &flood.distances;
+                    let (from, to) = (
+                        band as f32 * MAGIC_MAP_BAND,
+                        (band + 1) as f32 * MAGIC_MAP_BAND,
+                    );
+                    let revealed = map.reveal_band(distances, from, to);
+                    let finished = !distances.iter().any(|d| *d >= to && *d < f32::MAX);
+                    (revealed, finished)
+                };
+
+                if reveal_traps {
+                    let map = self.ecs.fetch::<Map>();
+                    let entities = self.ecs.entities();
+                    let positions = self.ecs.read_storage::<Position>();
+                    let mut hidden = self.ecs.write_storage::<Hidden>();
+                    let found: Vec<Entity> = (&entities, &positions, &hidden)
+                        .join()
+                        .filter(|(_, pos, _)| revealed.contains(&map.xy_idx(pos.x, pos.y)))
+                        .map(|(ent, _, _)| ent)
+                        .collect();
+                    for ent in found.iter() {
+                        hidden.remove(*ent);
+                    }
+                }
+
+                if finished {
+                    monster_ai_system::grant_energy(&mut self.ecs);
                     new_runstate = RunState::MonsterTurn;
                 } else {
-                    new_runstate = RunState::MagicMapReveal { row: row + 1 };
+                    new_runstate = RunState::MagicMapFlood {
+                        band: band + 1,
+                        reveal_traps,
+                    };
                 }
             }
-            // Player died.
+            // Player picked up the amulet.
+            RunState::Victory => match gui::victory(self, ctx) {
+                gui::GameOverResult::NoSelection => {}
+                gui::GameOverResult::QuitToMenu => {
+                    self.game_over_cleanup(seed::GameSeed::random());
+                    new_runstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::NewGame,
+                    };
+                }
+            },
             RunState::GameOver => match gui::game_over(ctx) {
                 gui::GameOverResult::NoSelection => {}
                 gui::GameOverResult::QuitToMenu => {
-                    self.game_over_cleanup();
+                    self.game_over_cleanup(seed::GameSeed::random());
                     new_runstate = RunState::MainMenu {
                         menu_selection: gui::MainMenuSelection::NewGame,
                     };
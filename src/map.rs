@@ -1,11 +1,23 @@
 use rltk::*;
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use std::collections::HashSet;
+use std::collections::HashMap;
 
+use super::{camera::Camera, components::DamageType};
+
+/// Default level width, matching the part of the console the map is drawn in.
 pub const MAPWIDTH: usize = 80;
+/// Default level height, matching the part of the console the map is drawn in.
 pub const MAPHEIGHT: usize = 43;
-pub const MAPCOUNT: usize = MAPHEIGHT * MAPWIDTH;
+/// Cost of a diagonal step, relative to a cardinal one.
+pub const DIAGONAL_COST: f32 = 1.45;
+
+/// How an item looked when the player last saw it.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct RememberedItem {
+    pub glyph: FontCharType,
+    pub fg: RGB,
+}
 
 /// Enum differentiating floor tiles from wall tiles.
 #[derive(Eq, PartialEq, Copy, Clone, Hash, Serialize, Deserialize)]
@@ -13,12 +25,105 @@ pub enum TileType {
     Wall,
     Floor,
     DownStairs,
+    /// Walkable, but slow going.
+    ShallowWater,
+    /// Walkable, but slower than floor (if not as slow as water).
+    Mud,
+}
+
+impl TileType {
+    /// Whether anything can stand on the tile; slow ground still counts.
+    pub fn is_walkable(self) -> bool {
+        self != TileType::Wall
+    }
+
+    /// How many turns' worth of effort stepping onto the tile takes; plain floor is 1.
+    pub fn move_cost(self) -> f32 {
+        match self {
+            TileType::ShallowWater => 2.0,
+            TileType::Mud => 1.5,
+            _ => 1.0,
+        }
+    }
+
+    /// Plain-text stand-in for the tile, as used by [`Map::to_ascii`]. Water and mud use the
+    /// same glyphs they're drawn with.
+    pub fn ascii(self) -> char {
+        match self {
+            TileType::Wall => '#',
+            TileType::Floor => '.',
+            TileType::DownStairs => '>',
+            TileType::ShallowWater => '≈',
+            TileType::Mud => '~',
+        }
+    }
+
+    /// The tile a [`TileType::ascii`] character stands for.
+    pub fn from_ascii(glyph: char) -> Option<TileType> {
+        match glyph {
+            '#' => Some(TileType::Wall),
+            '.' => Some(TileType::Floor),
+            '>' => Some(TileType::DownStairs),
+            '≈' => Some(TileType::ShallowWater),
+            '~' => Some(TileType::Mud),
+            _ => None,
+        }
+    }
+}
+
+/// Why [`Map::from_ascii`] couldn't read a map.
+#[derive(PartialEq, Debug)]
+pub enum AsciiMapError {
+    /// The character at `(x, y)` isn't a tile or `@`.
+    UnknownGlyph { x: i32, y: i32, glyph: char },
+    /// There's more than one `@`.
+    MultipleStarts,
+}
+
+/// How diagonal moves past the corners of walls are treated.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum CornerCutting {
+    /// Diagonal moves are allowed anywhere the destination is open.
+    Allowed,
+    /// Diagonal moves can't squeeze between two walls.
+    NotBetweenWalls,
+    /// Diagonal moves can't pass any wall corner; both cardinal tiles must be open.
+    NotPastWalls,
+}
+
+impl Default for CornerCutting {
+    fn default() -> Self {
+        CornerCutting::NotBetweenWalls
+    }
+}
+
+/// A timed hazard (a cloud of gas, a patch of flames) covering a tile, hurting whatever
+/// stands in it each turn until it burns out.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct AreaEffectTile {
+    pub turns: i32,
+    pub damage: i32,
+    pub damage_type: DamageType,
+}
+
+impl AreaEffectTile {
+    /// Background color of a tile covered by the hazard.
+    pub fn tint(&self) -> RGB {
+        match self.damage_type {
+            DamageType::Poison => RGB::from_f32(0.1, 0.4, 0.),
+            DamageType::Fire => RGB::from_f32(0.6, 0.25, 0.),
+            DamageType::Cold => RGB::from_f32(0.2, 0.3, 0.6),
+            DamageType::Physical => RGB::from_f32(0.3, 0.3, 0.3),
+        }
+    }
 }
 
 /// Structure for holding game map-related information.
 ///
 /// `revealed_tiles`: `true` if the tile has been in our fov before, else `false`.
 /// `visible_tiles`: `true` if the tile is currently in our fov, else `false`.
+/// `bloodstains`: maps tile indices to how fresh their bloodstain is, from 1.0 (fresh) down to 0.0.
+/// `hazards`: maps tile indices to the lingering hazard covering them.
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct Map {
     pub tiles: Vec<TileType>,
@@ -28,7 +133,17 @@ pub struct Map {
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
     pub depth: i32,
-    pub bloodstains: HashSet<usize>,
+    pub bloodstains: HashMap<usize, f32>,
+    #[serde(default)]
+    pub hazards: HashMap<usize, AreaEffectTile>,
+    /// Draw floors next to walls with a softer glyph; set by cave builders.
+    #[serde(default)]
+    pub smooth_floors: bool,
+    #[serde(default)]
+    pub corner_cutting: CornerCutting,
+    /// Items the player last saw on each tile, drawn dimmed once the tile is out of sight.
+    #[serde(default)]
+    pub remembered_items: HashMap<usize, RememberedItem>,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
@@ -37,23 +152,36 @@ pub struct Map {
 
 impl Map {
     /// Generates a new, empty map.
-    pub fn new(new_depth: i32) -> Map {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> Map {
+        let tile_count = (width * height) as usize;
         Map {
-            tiles: vec![TileType::Wall; MAPCOUNT],
-            width: MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles: vec![false; MAPCOUNT],
-            visible_tiles: vec![false; MAPCOUNT],
-            blocked: vec![false; MAPCOUNT],
-            tile_content: vec![Vec::new(); MAPCOUNT],
+            tiles: vec![TileType::Wall; tile_count],
+            width,
+            height,
+            revealed_tiles: vec![false; tile_count],
+            visible_tiles: vec![false; tile_count],
+            blocked: vec![false; tile_count],
+            tile_content: vec![Vec::new(); tile_count],
             depth: new_depth,
-            bloodstains: HashSet::new(),
+            bloodstains: HashMap::new(),
+            hazards: HashMap::new(),
+            smooth_floors: false,
+            corner_cutting: CornerCutting::default(),
+            remembered_items: HashMap::new(),
         }
     }
 
-    /// Returns the (x, y) coordinates of the map's center.
-    pub fn center(&self) -> (i32, i32) {
-        (self.width / 2, self.height / 2)
+    /// Returns the position of the map's center.
+    pub fn center(&self) -> super::Position {
+        super::Position {
+            x: self.width / 2,
+            y: self.height / 2,
+        }
+    }
+
+    /// The map's center as a [`Point`], for handing to rltk.
+    pub fn center_point(&self) -> Point {
+        self.center().into()
     }
 
     /// Checks if movement by (d_x, d_y) amount will violate map bounds.
@@ -66,6 +194,16 @@ impl Map {
         (y as usize * self.width as usize) + x as usize
     }
 
+    /// Like [`Map::xy_idx`], but returns `None` for coordinates off the edge of the map
+    /// instead of an index into some other row (or past the end of [`Map::tiles`]).
+    pub fn try_xy_idx(&self, x: i32, y: i32) -> Option<usize> {
+        if self.contains(Point::new(x, y)) {
+            Some(self.xy_idx(x, y))
+        } else {
+            None
+        }
+    }
+
     /// Determines if an index can be entered (is not blocked).
     fn is_exit_valid(&self, x: i32, y: i32) -> bool {
         if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
@@ -75,10 +213,49 @@ impl Map {
         !self.blocked[idx]
     }
 
+    /// Checks if `p` lies on the map.
+    fn contains(&self, p: Point) -> bool {
+        p.x >= 0 && p.x < self.width && p.y >= 0 && p.y < self.height
+    }
+
+    /// Checks that nothing opaque lies on the line from `from` to `to`, `to` itself included.
+    pub fn has_line_of_fire(&self, from: Point, to: Point) -> bool {
+        rltk::line2d(LineAlg::Bresenham, from, to)
+            .into_iter()
+            .filter(|p| *p != from)
+            .all(|p| self.contains(p) && !self.is_opaque(self.xy_idx(p.x, p.y)))
+    }
+
+    /// The last open tile on the line from `from` toward `to`, stopping short of the first
+    /// opaque one. This is where something thrown at `to` ends up.
+    pub fn last_open_on_line(&self, from: Point, to: Point) -> Point {
+        let mut last = from;
+        for p in rltk::line2d(LineAlg::Bresenham, from, to)
+            .into_iter()
+            .filter(|p| *p != from)
+        {
+            if !self.contains(p) || self.is_opaque(self.xy_idx(p.x, p.y)) {
+                break;
+            }
+            last = p;
+        }
+        last
+    }
+
+    /// Checks whether a diagonal step by `(d_x, d_y)` from `(x, y)` gets past the walls around it.
+    pub fn is_diagonal_open(&self, x: i32, y: i32, d_x: i32, d_y: i32) -> bool {
+        let is_wall = |x: i32, y: i32| self.tiles[self.xy_idx(x, y)] == TileType::Wall;
+        match self.corner_cutting {
+            CornerCutting::Allowed => true,
+            CornerCutting::NotBetweenWalls => !(is_wall(x + d_x, y) && is_wall(x, y + d_y)),
+            CornerCutting::NotPastWalls => !(is_wall(x + d_x, y) || is_wall(x, y + d_y)),
+        }
+    }
+
     /// Sets all wall tiles to blocking tiles--can't walk through walls.
     pub fn populate_blocked(&mut self) {
         for (i, tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
+            self.blocked[i] = !tile.is_walkable();
         }
     }
 
@@ -97,9 +274,122 @@ impl Map {
             .collect::<Vec<(i32, i32)>>()
     }
 
+    /// Open tiles caught in a blast of `radius` centered on `center`.
+    pub fn blast_area(&self, center: Point, radius: i32) -> Vec<Point> {
+        rltk::field_of_view(center, radius, self)
+            .into_iter()
+            .filter(|p| {
+                p.x > 0
+                    && p.x < self.width - 1
+                    && p.y > 0
+                    && p.y < self.height - 1
+                    && self.tiles[self.xy_idx(p.x, p.y)] != TileType::Wall
+            })
+            .collect()
+    }
+
     pub fn count_floor_tiles(&self) -> usize {
         self.tiles.iter().filter(|t| **t == TileType::Floor).count()
     }
+
+    /// How far every tile is from `origin` on foot, ignoring anything standing in the way.
+    ///
+    /// Tiles that can't be reached at all are left at `f32::MAX`.
+    pub fn walking_distances(&self, origin: usize) -> Vec<f32> {
+        let mut terrain = self.clone();
+        terrain.populate_blocked();
+        DijkstraMap::new(
+            self.width as usize,
+            self.height as usize,
+            &[origin],
+            &terrain,
+            self.tiles.len() as f32,
+        )
+        .map
+    }
+
+    /// Reveals the tiles whose walking distance (from [`Map::walking_distances`]) is at least
+    /// `from` but less than `to`, along with the walls around them.
+    ///
+    /// Returns the indices of the floor tiles in the band.
+    pub fn reveal_band(&mut self, distances: &[f32], from: f32, to: f32) -> Vec<usize> {
+        let band: Vec<usize> = (0..distances.len())
+            .filter(|idx| distances[*idx] >= from && distances[*idx] < to)
+            .collect();
+        for idx in band.iter() {
+            let (x, y) = (*idx as i32 % self.width, *idx as i32 / self.width);
+            for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+                if let Some(neighbor) = self.try_xy_idx(x + dx, y + dy) {
+                    if neighbor == *idx || self.tiles[neighbor] == TileType::Wall {
+                        self.revealed_tiles[neighbor] = true;
+                    }
+                }
+            }
+        }
+        band
+    }
+
+    /// Reads a map written out like [`Map::to_ascii`] does, for building test fixtures.
+    ///
+    /// The map is as wide as the longest line, with shorter lines padded out with wall. An `@`
+    /// marks the start, standing on floor. Anything that isn't a tile or `@` is an error.
+    pub fn from_ascii(text: &str) -> Result<(Map, Option<Point>), AsciiMapError> {
+        let rows: Vec<&str> = text.lines().collect();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0) as i32;
+        let mut map = Map::new(1, width, rows.len() as i32);
+        let mut start = None;
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, glyph) in row.chars().enumerate() {
+                let (x, y) = (x as i32, y as i32);
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = match glyph {
+                    '@' if start.is_some() => return Err(AsciiMapError::MultipleStarts),
+                    '@' => {
+                        start = Some(Point::new(x, y));
+                        TileType::Floor
+                    }
+                    _ => TileType::from_ascii(glyph).ok_or(AsciiMapError::UnknownGlyph {
+                        x,
+                        y,
+                        glyph,
+                    })?,
+                };
+            }
+        }
+        map.populate_blocked();
+        Ok((map, start))
+    }
+
+    /// Dumps the map as text, one line per row, using [`TileType::ascii`] for each tile.
+    pub fn to_ascii(&self) -> String {
+        self.to_ascii_with(None, &[])
+    }
+
+    /// Like [`Map::to_ascii`], but marks `start` with `@` and each spawn with the first letter
+    /// of its name.
+    pub fn to_ascii_with(&self, start: Option<Point>, spawns: &[(usize, String)]) -> String {
+        let mut glyphs: Vec<char> = self.tiles.iter().map(|tile| tile.ascii()).collect();
+        for (idx, name) in spawns.iter() {
+            if let Some(initial) = name.chars().next() {
+                glyphs[*idx] = initial;
+            }
+        }
+        if let Some(idx) = start.and_then(|p| self.try_xy_idx(p.x, p.y)) {
+            glyphs[idx] = '@';
+        }
+
+        let mut dump = String::with_capacity(glyphs.len() + self.height as usize);
+        for row in glyphs.chunks(self.width as usize) {
+            dump.extend(row.iter());
+            dump.push('\n');
+        }
+        dump
+    }
 }
 
 impl Algorithm2D for Map {
@@ -114,11 +404,13 @@ impl BaseMap for Map {
         self.tiles[idx as usize] == TileType::Wall
     }
 
+    /// Octile distance, so the A* heuristic agrees with the step costs from
+    /// [`BaseMap::get_available_exits`].
     fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
         let w = self.width as usize;
-        let p1 = Point::new(idx1 % w, idx1 / w);
-        let p2 = Point::new(idx2 % w, idx2 / w);
-        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
+        let d_x = ((idx1 % w) as f32 - (idx2 % w) as f32).abs();
+        let d_y = ((idx1 / w) as f32 - (idx2 / w) as f32).abs();
+        f32::max(d_x, d_y) + (DIAGONAL_COST - 1.0) * f32::min(d_x, d_y)
     }
 
     fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
@@ -127,87 +419,163 @@ impl BaseMap for Map {
         let y = idx as i32 / self.width;
         let w = self.width as usize;
 
+        // Slow ground costs more to step onto.
+        let cost = |dest: usize| self.tiles[dest].move_cost();
+
         // Cardinal directions
         if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.))
+            exits.push((idx - 1, cost(idx - 1)))
         };
         if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.))
+            exits.push((idx + 1, cost(idx + 1)))
         };
         if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.))
+            exits.push((idx - w, cost(idx - w)))
         };
         if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.))
+            exits.push((idx + w, cost(idx + w)))
         };
 
         // Diagonal directions
-        if self.is_exit_valid(x - 1, y - 1) {
-            exits.push(((idx - w) - 1, 1.45));
-        }
-        if self.is_exit_valid(x + 1, y - 1) {
-            exits.push(((idx - w) + 1, 1.45));
-        }
-        if self.is_exit_valid(x - 1, y + 1) {
-            exits.push(((idx + w) - 1, 1.45));
-        }
-        if self.is_exit_valid(x + 1, y + 1) {
-            exits.push(((idx + w) + 1, 1.45));
+        for (d_x, d_y) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].iter() {
+            if self.is_exit_valid(x + d_x, y + d_y) && self.is_diagonal_open(x, y, *d_x, *d_y) {
+                let dest = self.xy_idx(x + d_x, y + d_y);
+                exits.push((dest, DIAGONAL_COST * cost(dest)));
+            }
         }
 
         exits
     }
 }
 
-/// Renders the map to the terminal screen.
-pub fn draw_map(map: &Map, ctx: &mut Rltk) {
-    let mut y = 0;
-    let mut x = 0;
+/// Colors the walls and floors of a level are drawn in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Palette {
+    pub floor: RGB,
+    pub wall: RGB,
+}
 
+/// Number of hand-tuned palettes; deeper levels cycle back through them.
+const PALETTE_COUNT: i32 = 5;
+
+impl Palette {
+    /// The palette for levels at `depth`: mossy near the surface, then sandstone, slate,
+    /// amethyst and, deepest of all, cinders.
+    pub fn for_depth(depth: i32) -> Palette {
+        let (floor, wall) = match (depth - 1).rem_euclid(PALETTE_COUNT) {
+            // Teal floors in mossy green walls.
+            0 => ((0.0, 0.5, 0.5), (0.0, 1.0, 0.0)),
+            // Sandstone.
+            1 => ((0.5, 0.42, 0.28), (0.85, 0.7, 0.4)),
+            // Slate.
+            2 => ((0.3, 0.38, 0.55), (0.5, 0.65, 0.85)),
+            // Amethyst.
+            3 => ((0.32, 0.2, 0.42), (0.55, 0.38, 0.75)),
+            // Cinders.
+            _ => ((0.38, 0.18, 0.14), (0.7, 0.3, 0.18)),
+        };
+        Palette {
+            floor: RGB::from_f32(floor.0, floor.1, floor.2),
+            wall: RGB::from_f32(wall.0, wall.1, wall.2),
+        }
+    }
+}
+
+/// Renders the portion of the map inside the camera's viewport to the terminal screen.
+pub fn draw_map(map: &Map, camera: &Camera, ctx: &mut Rltk) {
+    let palette = Palette::for_depth(map.depth);
     for (idx, tile) in map.tiles.iter().enumerate() {
-        // Render a tile depending on its tile type.
-        if map.revealed_tiles[idx] {
+        let x = idx as i32 % map.width;
+        let y = idx as i32 / map.width;
+        let (screen_x, screen_y) = camera.to_screen(x, y);
+
+        // Render a tile depending on its tile type, if it's in view of the camera.
+        if map.revealed_tiles[idx] && camera.on_screen(screen_x, screen_y) {
             // `glyph` and `fg` switches based on TileType.
-            let glyph: FontCharType;
+            let mut glyph: FontCharType;
             let mut fg: RGB;
             let mut bg: RGB = RGB::from_f32(0., 0., 0.);
             match tile {
                 TileType::Floor => {
-                    glyph = rltk::to_cp437('.');
-                    fg = RGB::from_f32(0.0, 0.5, 0.5);
+                    glyph = floor_glyph(&*map, x, y);
+                    fg = palette.floor;
                 }
                 TileType::Wall => {
                     glyph = wall_glyph(&*map, x, y);
-                    fg = RGB::from_f32(0., 1., 0.);
+                    fg = palette.wall;
                 }
                 TileType::DownStairs => {
                     glyph = rltk::to_cp437('>');
-                    fg = RGB::from_f32(0., 1., 0.);
+                    fg = palette.wall;
+                }
+                TileType::ShallowWater => {
+                    glyph = rltk::to_cp437('≈');
+                    fg = RGB::from_f32(0.2, 0.4, 1.0);
+                }
+                TileType::Mud => {
+                    glyph = rltk::to_cp437('~');
+                    fg = RGB::from_f32(0.55, 0.35, 0.15);
                 }
             }
             // If tile isn't currently visible (but has been encountered),
             // render it in greyscale.
             if !map.visible_tiles[idx] {
+                // Show whatever item was lying here when the tile was last in view.
+                if let Some(item) = map.remembered_items.get(&idx) {
+                    glyph = item.glyph;
+                    fg = item.fg;
+                }
                 fg = fg.to_greyscale();
                 bg = RGB::from_f32(0., 0., 0.);
-            } else if map.bloodstains.contains(&idx) {
-                // If this tile is bloodied, render it.
-                bg = RGB::from_f32(0.75, 0., 0.);
+            } else if let Some(hazard) = map.hazards.get(&idx) {
+                bg = hazard.tint();
+            } else if let Some(intensity) = map.bloodstains.get(&idx) {
+                // If this tile is bloodied, render it--older stains are darker.
+                bg = RGB::from_f32(0.75 * intensity, 0., 0.);
             }
-            ctx.set(x, y, fg, bg, glyph);
+            ctx.set(screen_x, screen_y, fg, bg, glyph);
         }
+    }
+}
 
-        // Move the coordinates
-        x += 1;
-        if x > 79 {
-            x = 0;
-            y += 1;
-        }
+/// Picks a wall glyph for `(x, y)` from its 8 neighbors.
+///
+/// A connection toward an orthogonal neighbor is dropped when the walls on both sides of it
+/// (the neighbor's two flanking tiles) are solid too, since that line would run through the
+/// middle of a thick wall rather than along its edge. This keeps corners and cave edges clean.
+/// Falls back to the plain 4-neighbor table when every connection turns out to be interior.
+pub fn wall_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
+    // Stay in the map bounds, please.
+    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 as i32 {
+        return 35;
+    }
+
+    let wall = |dx: i32, dy: i32| is_revealed_and_wall(map, x + dx, y + dy);
+    let (n, s, w, e) = (wall(0, -1), wall(0, 1), wall(-1, 0), wall(1, 0));
+    let (nw, ne, sw, se) = (wall(-1, -1), wall(1, -1), wall(-1, 1), wall(1, 1));
+
+    let mut mask: u8 = 0;
+    if n && !(w && nw && e && ne) {
+        mask += 1;
+    }
+    if s && !(w && sw && e && se) {
+        mask += 2;
     }
+    if w && !(n && nw && s && sw) {
+        mask += 4;
+    }
+    if e && !(n && ne && s && se) {
+        mask += 8;
+    }
+
+    if mask == 0 {
+        return wall_glyph_4(map, x, y);
+    }
+    wall_mask_glyph(mask)
 }
 
-/// Applies bitmask to TileType.
-fn wall_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
+/// Picks a wall glyph for `(x, y)` from its 4 orthogonal neighbors only.
+pub fn wall_glyph_4(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
     // Stay in the map bounds, please.
     if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 as i32 {
         return 35;
@@ -228,6 +596,11 @@ fn wall_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
         mask += 8;
     }
 
+    wall_mask_glyph(mask)
+}
+
+/// Applies a 4-bit (north, south, west, east) wall bitmask to a glyph.
+fn wall_mask_glyph(mask: u8) -> rltk::FontCharType {
     match mask {
         0 => 9,    // Pillar (can't see neighbors)
         1 => 186,  // Wall to the north
@@ -249,7 +622,187 @@ fn wall_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
     }
 }
 
+/// Picks a floor glyph for `(x, y)`.
+///
+/// Maps with `smooth_floors` set (caves) draw floor hugging the walls as a softer dot,
+/// which rounds off the blocky look of cellular/drunkard maps.
+pub fn floor_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
+    if !map.smooth_floors || x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+        return rltk::to_cp437('.');
+    }
+
+    let mut walls = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if (dx != 0 || dy != 0) && map.tiles[map.xy_idx(x + dx, y + dy)] == TileType::Wall {
+                walls += 1;
+            }
+        }
+    }
+
+    if walls >= 3 {
+        rltk::to_cp437('·')
+    } else {
+        rltk::to_cp437('.')
+    }
+}
+
 fn is_revealed_and_wall(map: &Map, x: i32, y: i32) -> bool {
     let idx = map.xy_idx(x, y);
     map.tiles[idx] == TileType::Wall && map.revealed_tiles[idx]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a map from rows of `#` (wall) and `.` (floor).
+    fn map_from_rows(rows: &[&str]) -> Map {
+        Map::from_ascii(&rows.join("\n")).unwrap().0
+    }
+
+    #[test]
+    fn palettes_cycle_with_depth() {
+        let first = Palette::for_depth(1);
+        assert_eq!(first.wall, RGB::from_f32(0.0, 1.0, 0.0));
+        assert_ne!(Palette::for_depth(2), first);
+        assert_eq!(Palette::for_depth(1 + PALETTE_COUNT), first);
+        // Maps made outside the dungeon (depth 0) still get a palette.
+        assert_eq!(Palette::for_depth(0), Palette::for_depth(PALETTE_COUNT));
+    }
+
+    #[test]
+    fn can_not_squeeze_between_walls() {
+        let mut map = map_from_rows(&["#####", "#.###", "##.##", "#####", "#####"]);
+        let start = map.xy_idx(1, 1);
+        let end = map.xy_idx(2, 2);
+
+        let path = rltk::a_star_search(start, end, &map);
+        assert!(!path.success);
+
+        map.corner_cutting = CornerCutting::Allowed;
+        let path = rltk::a_star_search(start, end, &map);
+        assert!(path.success);
+    }
+
+    #[test]
+    fn reachable_reveal_skips_sealed_rooms() {
+        let mut map = map_from_rows(&["##########", "#...#....#", "#...#....#", "##########"]);
+        let distances = map.walking_distances(map.xy_idx(1, 1));
+        map.reveal_band(&distances, 0.0, f32::MAX);
+
+        assert!(map.revealed_tiles[map.xy_idx(3, 2)]);
+        // The walls around the reachable room are revealed too, including the divider...
+        assert!(map.revealed_tiles[map.xy_idx(0, 0)]);
+        assert!(map.revealed_tiles[map.xy_idx(4, 1)]);
+        // ...but nothing on the sealed side of it.
+        assert!(!map.revealed_tiles[map.xy_idx(6, 1)]);
+        assert!(!map.revealed_tiles[map.xy_idx(9, 0)]);
+    }
+
+    /// Exits from `(x, y)`, as sorted coordinates.
+    fn exits_from(map: &Map, x: i32, y: i32) -> Vec<(i32, i32)> {
+        let mut exits: Vec<(i32, i32)> = map
+            .get_available_exits(map.xy_idx(x, y))
+            .iter()
+            .map(|(idx, _)| (*idx as i32 % map.width, *idx as i32 / map.width))
+            .collect();
+        exits.sort();
+        exits
+    }
+
+    #[test]
+    fn pinch_point_exits() {
+        // The center tile only touches floor diagonally, through gaps between walls.
+        let mut map = map_from_rows(&["#####", "#.#.#", "##.##", "#.#.#", "#####"]);
+
+        map.corner_cutting = CornerCutting::Allowed;
+        assert_eq!(exits_from(&map, 2, 2), vec![(1, 1), (1, 3), (3, 1), (3, 3)]);
+
+        map.corner_cutting = CornerCutting::NotBetweenWalls;
+        assert_eq!(exits_from(&map, 2, 2), vec![]);
+
+        map.corner_cutting = CornerCutting::NotPastWalls;
+        assert_eq!(exits_from(&map, 2, 2), vec![]);
+    }
+
+    #[test]
+    fn corner_exits() {
+        // Going from (1, 1) to (2, 2) passes one wall corner, with open floor on the other side.
+        let mut map = map_from_rows(&["#####", "#..##", "##.##", "#####", "#####"]);
+
+        map.corner_cutting = CornerCutting::NotBetweenWalls;
+        assert_eq!(exits_from(&map, 1, 1), vec![(2, 1), (2, 2)]);
+
+        map.corner_cutting = CornerCutting::NotPastWalls;
+        assert_eq!(exits_from(&map, 1, 1), vec![(2, 1)]);
+    }
+
+    #[test]
+    fn corner_indices() {
+        let map = map_from_rows(&["####", "#..#", "####"]);
+        assert_eq!(map.xy_idx(0, 0), 0);
+        assert_eq!(map.xy_idx(3, 0), 3);
+        assert_eq!(map.xy_idx(0, 2), 8);
+        assert_eq!(map.xy_idx(3, 2), map.tiles.len() - 1);
+        for (x, y) in [(0, 0), (3, 0), (0, 2), (3, 2)].iter() {
+            assert_eq!(map.try_xy_idx(*x, *y), Some(map.xy_idx(*x, *y)));
+        }
+    }
+
+    #[test]
+    fn off_map_indices() {
+        let map = map_from_rows(&["####", "#..#", "####"]);
+        assert_eq!(map.try_xy_idx(-1, 0), None);
+        assert_eq!(map.try_xy_idx(0, -1), None);
+        // Would otherwise wrap around onto the start of the next row.
+        assert_eq!(map.try_xy_idx(4, 0), None);
+        assert_eq!(map.try_xy_idx(0, 3), None);
+        assert_eq!(map.try_xy_idx(3, 3), None);
+    }
+
+    #[test]
+    fn ascii_dump() {
+        let map = map_from_rows(&["#####", "#..##", "##.>#", "#####"]);
+        assert_eq!(map.to_ascii(), "#####\n#..##\n##.>#\n#####\n");
+
+        let spawns = vec![(map.xy_idx(2, 1), "Goblin".to_string())];
+        assert_eq!(
+            map.to_ascii_with(Some(Point::new(1, 1)), &spawns),
+            "#####\n#@G##\n##.>#\n#####\n"
+        );
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let text = "######\n#@..>#\n#≈~###\n######\n";
+        let (map, start) = Map::from_ascii(text).unwrap();
+        assert_eq!(start, Some(Point::new(1, 1)));
+        assert!(map.tiles[map.xy_idx(1, 1)] == TileType::Floor);
+        assert_eq!(map.to_ascii_with(start, &[]), text);
+    }
+
+    #[test]
+    fn ascii_pads_short_lines_with_wall() {
+        let (map, start) = Map::from_ascii("####\n#.\n####").unwrap();
+        assert_eq!((map.width, map.height), (4, 3));
+        assert_eq!(start, None);
+        assert_eq!(map.to_ascii(), "####\n#.##\n####\n");
+    }
+
+    #[test]
+    fn ascii_rejects_unknown_glyphs() {
+        assert_eq!(
+            Map::from_ascii("###\n#x#\n###").err(),
+            Some(AsciiMapError::UnknownGlyph {
+                x: 1,
+                y: 1,
+                glyph: 'x'
+            })
+        );
+        assert_eq!(
+            Map::from_ascii("#@@#").err(),
+            Some(AsciiMapError::MultipleStarts)
+        );
+    }
+}
@@ -1,10 +1,10 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use rltk::DistanceAlg;
 
 use crate::{spawner, MetaMapBuilder, Position, TileType};
 
-use super::common::DistanceAlgorithm;
+use super::common::{connected_regions, DistanceAlgorithm};
 
 pub enum XStart {
     LEFT,
@@ -17,6 +17,10 @@ pub enum YStart {
     BOTTOM,
 }
 
+/// Starts the player on the floor nearest a corner, edge or the center of the map.
+///
+/// Only floors in the largest connected region are considered, so the player never lands in a
+/// pocket that [`super::CullUnreachable`] would go on to wall off.
 pub struct AreaStartingPosition {
     x: XStart,
     y: YStart,
@@ -50,9 +54,12 @@ impl AreaStartingPosition {
             YStart::BOTTOM => build_data.map.height - 2,
         };
 
+        let regions = connected_regions(&build_data.map);
+        let main_region = regions.first().map(|r| r.as_slice()).unwrap_or(&[]);
+
         let mut available_floors: Vec<(usize, f32)> = Vec::new();
-        for (idx, tile) in build_data.map.tiles.iter().enumerate() {
-            if *tile == TileType::Floor {
+        for idx in main_region.iter().copied() {
+            if build_data.map.tiles[idx] == TileType::Floor {
                 available_floors.push((
                     idx,
                     DistanceAlgorithm::Pythagoras.apply(
@@ -96,7 +103,8 @@ impl VoronoiSpawning {
     }
 
     fn build(&mut self, rng: &mut rltk::RandomNumberGenerator, build_data: &mut crate::BuildData) {
-        let mut noise_areas: HashMap<i32, Vec<usize>> = HashMap::new();
+        // Ordered, so regions roll their spawns in the same order for the same seed.
+        let mut noise_areas: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
         let mut noise = rltk::FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
         noise.set_noise_type(rltk::NoiseType::Cellular);
         noise.set_frequency(0.08);
@@ -120,8 +128,40 @@ impl VoronoiSpawning {
                 rng,
                 area.1,
                 build_data.map.depth,
+                build_data.difficulty,
                 &mut build_data.spawn_list,
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuilderChain, Map};
+
+    #[test]
+    fn starts_in_the_biggest_cave() {
+        // A small pocket right by the top-left corner, and a much bigger cave further away.
+        let mut build_data = BuilderChain::new(1, 20, 10).build_data;
+        let rows = [
+            "####################",
+            "#..#################",
+            "#.######...........#",
+            "########...........#",
+            "########...........#",
+            "########...........#",
+            "########...........#",
+            "########...........#",
+            "########...........#",
+            "####################",
+        ];
+        build_data.map = Map::from_ascii(&rows.join("\n")).unwrap().0;
+
+        let mut rng = rltk::RandomNumberGenerator::seeded(1);
+        AreaStartingPosition::new(XStart::LEFT, YStart::TOP).build(&mut rng, &mut build_data);
+
+        let start = build_data.start.expect("No start position");
+        assert_eq!((start.x, start.y), (8, 2));
+    }
+}
@@ -1,7 +1,5 @@
 use super::common::{apply_room_to_map, draw_corridor};
-use crate::{
-    spawner, BuildData, InitialMapBuilder, Map, Position, Rect, TileType, SHOW_MAPGEN_VISUALIZER,
-};
+use crate::{spawner, BuildData, InitialMapBuilder, Map, Position, Rect, TileType};
 
 pub struct BspDungeonBuilder {
     rects: Vec<Rect>,
@@ -76,7 +74,7 @@ impl BspDungeonBuilder {
     fn add_subrects(&mut self, rect: Rect) {
         // Each quadrant lies between the boarder and half_width and half_height.
         let half_width = i32::max(rect.width() / 2, 1);
-        let half_height = i32::max(rect.width() / 2, 1);
+        let half_height = i32::max(rect.height() / 2, 1);
 
         // Add all four quadrants to our rect vec.
         self.rects
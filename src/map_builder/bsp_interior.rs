@@ -1,5 +1,5 @@
 use super::{common::draw_corridor, Map, Position, Rect, TileType};
-use crate::{spawner, BuildData, InitialMapBuilder, SHOW_MAPGEN_VISUALIZER};
+use crate::{spawner, BuildData, InitialMapBuilder};
 use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
@@ -1,12 +1,9 @@
-use std::{collections::HashMap, iter::repeat};
+use std::collections::HashMap;
 
 use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
-use crate::{
-    spawner, BuildData, InitialMapBuilder, Map, MetaMapBuilder, Position, TileType,
-    SHOW_MAPGEN_VISUALIZER,
-};
+use crate::{spawner, BuildData, InitialMapBuilder, Map, MetaMapBuilder, Position, TileType};
 
 pub struct CellularAutomataBuilder {}
 
@@ -28,6 +25,7 @@ impl CellularAutomataBuilder {
     }
 
     fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        build_data.map.smooth_floors = true;
         // Randomize the map.
         for y in 1..build_data.map.height - 1 {
             for x in 1..build_data.map.width - 1 {
@@ -55,23 +53,13 @@ impl CellularAutomataBuilder {
         for y in 1..build_data.map.height - 1 {
             for x in 1..build_data.map.width - 1 {
                 let idx = build_data.map.xy_idx(x, y);
-                let mut neighbors = 0;
-                // Indices of all neighboring tiles for `idx`.
-                let neighbor_indices: Vec<usize> = vec![
-                    1,
-                    build_data.map.width as usize,
-                    build_data.map.width as usize - 1,
-                    build_data.map.width as usize + 1,
-                ];
-                // Zip `idx` with neighbor vec and check how many neighbors are walls.
-                neighbor_indices.iter().zip(repeat(idx)).for_each(|(n, i)| {
-                    if build_data.map.tiles[i - n] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                    if build_data.map.tiles[i + n] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                });
+                // Count how many of the (up to) eight surrounding tiles are walls.
+                let neighbors = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .filter(|&(dx, dy)| dx != 0 || dy != 0)
+                    .filter_map(|(dx, dy)| build_data.map.try_xy_idx(x + dx, y + dy))
+                    .filter(|n| build_data.map.tiles[*n] == TileType::Wall)
+                    .count();
                 // 0 or more than 4 neighbors--make it a wall; otherwise, it's a floor tile.
                 if neighbors > 4 || neighbors == 0 {
                     newtiles[idx] = TileType::Wall;
@@ -1,4 +1,4 @@
-use crate::{BuildData, MetaMapBuilder, Position};
+use crate::{BuildData, MetaMapBuilder, FINAL_DEPTH};
 
 use super::{Map, Rect, TileType};
 use rand::{
@@ -8,8 +8,7 @@ use rand::{
 use rltk::RandomNumberGenerator;
 use std::{
     cmp::{max, min},
-    collections::HashMap,
-    iter,
+    collections::{HashMap, VecDeque},
 };
 
 /// Implemented distance algorithm function definitions.
@@ -50,24 +49,60 @@ pub enum Symmetry {
     Both,
 }
 
+/// Reflects coordinate `v` across a span ending (exclusively) at `end`, e.g. `0` <-> `end - 1`.
+pub fn reflect(v: i32, end: i32) -> i32 {
+    end - (v + 1)
+}
+
+/// Splits the map's open (non-wall) tiles into orthogonally connected regions, largest first.
+pub fn connected_regions(map: &Map) -> Vec<Vec<usize>> {
+    let mut region_of: Vec<Option<usize>> = vec![None; map.tiles.len()];
+    let mut regions: Vec<Vec<usize>> = Vec::new();
+
+    for start in 0..map.tiles.len() {
+        if map.tiles[start] == TileType::Wall || region_of[start].is_some() {
+            continue;
+        }
+        // Flood fill outward from the first tile not yet claimed by a region.
+        let mut region = vec![start];
+        region_of[start] = Some(regions.len());
+        let mut next = 0;
+        while next < region.len() {
+            let (x, y) = (
+                region[next] as i32 % map.width,
+                region[next] as i32 / map.width,
+            );
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+                if let Some(idx) = map.try_xy_idx(x + dx, y + dy) {
+                    if map.tiles[idx] != TileType::Wall && region_of[idx].is_none() {
+                        region_of[idx] = Some(regions.len());
+                        region.push(idx);
+                    }
+                }
+            }
+            next += 1;
+        }
+        regions.push(region);
+    }
+
+    regions.sort_by(|a, b| b.len().cmp(&a.len()));
+    regions
+}
+
 /// Places a rectangular room onto the [`Map`] by setting all tiles within its
 /// boundaries to [`TileType::Floor`] tiles.
 pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
-    (room.y1 + 1..=room.y2)
-        .map(|y| iter::repeat(y).zip(room.x1 + 1..=room.x2))
-        .flatten()
-        .for_each(|(y, x)| {
-            let idx = map.xy_idx(x, y);
-            map.tiles[idx] = TileType::Floor
-        });
+    room.iter().for_each(|(x, y)| {
+        let idx = map.xy_idx(x, y);
+        map.tiles[idx] = TileType::Floor
+    });
 }
 
 /// Places a horizontal tunnel between two coordinates on the same `y` level.
 pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
     (min(x1, x2)..=max(x1, x2)).for_each(|x| {
-        let idx = map.xy_idx(x, y);
-        if idx > 0 && idx < map.width as usize * map.height as usize {
-            map.tiles[idx as usize] = TileType::Floor;
+        if let Some(idx) = map.try_xy_idx(x, y) {
+            map.tiles[idx] = TileType::Floor;
         }
     });
 }
@@ -75,9 +110,8 @@ pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
 /// Places a vertical tunnel between two points on the same `x` level.
 pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
     (min(y1, y2)..=max(y1, y2)).for_each(|y| {
-        let idx = map.xy_idx(x, y);
-        if idx > 0 && idx < map.width as usize * map.height as usize {
-            map.tiles[idx as usize] = TileType::Floor;
+        if let Some(idx) = map.try_xy_idx(x, y) {
+            map.tiles[idx] = TileType::Floor;
         }
     })
 }
@@ -139,6 +173,89 @@ impl CullUnreachable {
     }
 }
 
+/// Alternative to [`CullUnreachable`] that keeps every open area by joining it to the rest.
+///
+/// Repeatedly carves the shortest possible corridor from the largest region to whichever other
+/// region is nearest, until only one region is left.
+pub struct ConnectRegions {}
+
+impl MetaMapBuilder for ConnectRegions {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        self.build(rng, build_data);
+    }
+}
+
+impl ConnectRegions {
+    pub fn new() -> Box<ConnectRegions> {
+        Box::new(ConnectRegions {})
+    }
+
+    fn build(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        loop {
+            let regions = connected_regions(&build_data.map);
+            if regions.len() < 2 {
+                break;
+            }
+            match ConnectRegions::shortest_tunnel(&build_data.map, &regions[0]) {
+                Some(tunnel) => {
+                    for idx in tunnel {
+                        build_data.map.tiles[idx] = TileType::Floor;
+                    }
+                    build_data.take_snapshot();
+                }
+                None => break,
+            }
+        }
+        build_data.map.populate_blocked();
+    }
+
+    /// Walls to dig out to join `main` to its nearest neighbouring region.
+    ///
+    /// Breadth-first search outward from the whole of `main` at once; the first open tile reached
+    /// that isn't part of `main` belongs to the closest region. The map's outer edge is never dug.
+    fn shortest_tunnel(map: &Map, main: &[usize]) -> Option<Vec<usize>> {
+        let mut came_from: Vec<Option<usize>> = vec![None; map.tiles.len()];
+        let mut visited = vec![false; map.tiles.len()];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        for idx in main.iter() {
+            visited[*idx] = true;
+            queue.push_back(*idx);
+        }
+
+        while let Some(idx) = queue.pop_front() {
+            let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 1 || nx > map.width - 2 || ny < 1 || ny > map.height - 2 {
+                    continue;
+                }
+                let next = map.xy_idx(nx, ny);
+                if visited[next] {
+                    continue;
+                }
+                visited[next] = true;
+                came_from[next] = Some(idx);
+
+                if map.tiles[next] != TileType::Wall {
+                    // Reached another region; walk back to `main`, collecting the walls passed.
+                    let mut tunnel = Vec::new();
+                    let mut step = came_from[next];
+                    while let Some(tile) = step {
+                        if map.tiles[tile] != TileType::Wall {
+                            break;
+                        }
+                        tunnel.push(tile);
+                        step = came_from[tile];
+                    }
+                    return Some(tunnel);
+                }
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+}
+
 /// Returns the furthest reachable point on the map from its starting position.
 ///
 /// Uses Dijkstra's algorithm to both calculate the reachable distance and reachability.
@@ -181,12 +298,27 @@ impl DistantExit {
             }
         }
 
-        let stairs = exit_tile.0;
-        build_data.map.tiles[stairs] = TileType::DownStairs;
+        place_exit(build_data, exit_tile.0);
         build_data.take_snapshot();
     }
 }
 
+/// What [`place_exit`] spawns on the final depth in place of stairs.
+pub const FINAL_EXIT_SPAWNS: [&str; 2] = ["Amulet Guardian", "Amulet of the Depths"];
+
+/// Places the level's exit at `idx`.
+///
+/// That's usually a staircase down, but on the final depth it's the amulet, guarded by a boss.
+pub fn place_exit(build_data: &mut BuildData, idx: usize) {
+    if build_data.map.depth >= FINAL_DEPTH {
+        for name in FINAL_EXIT_SPAWNS.iter() {
+            build_data.spawn_list.push((idx, name.to_string()));
+        }
+    } else {
+        build_data.map.tiles[idx] = TileType::DownStairs;
+    }
+}
+
 pub trait Digger {
     fn get_position(&self) -> (i32, i32);
     fn get_position_mut(&mut self) -> (&mut i32, &mut i32);
@@ -237,7 +369,7 @@ impl Distribution<Symmetry> for Standard {
 }
 
 pub fn paint(map: &mut Map, mode: Symmetry, brush_size: i32, x: i32, y: i32) {
-    let center = Position::from(map.center());
+    let center = map.center();
     let idx = map.xy_idx(x, y);
     map.tiles[idx] = TileType::Floor;
 
@@ -310,3 +442,44 @@ fn apply_paint(map: &mut Map, brush_size: i32, x: i32, y: i32) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuilderChain;
+
+    #[test]
+    fn connecting_keeps_every_cave() {
+        let rows = [
+            "##############################",
+            "##############################",
+            "##.....#######################",
+            "##.....#####.....#############",
+            "##.....#####.....#############",
+            "##.....#####.....#############",
+            "##.....##############.........",
+            "##.....##############........#",
+            "##.....##############........#",
+            "##.....##############........#",
+            "#####################........#",
+            "##############################",
+        ];
+        let mut build_data = BuilderChain::new(1, 30, 12).build_data;
+        build_data.record_history = false;
+        build_data.map = Map::from_ascii(&rows.join("\n")).unwrap().0;
+        let before = build_data.map.clone();
+        assert_eq!(connected_regions(&build_data.map).len(), 3);
+
+        let mut rng = RandomNumberGenerator::seeded(1);
+        ConnectRegions::new().build(&mut rng, &mut build_data);
+
+        assert_eq!(connected_regions(&build_data.map).len(), 1);
+        // Only tunnels were added; nothing that was open got filled in.
+        assert!(build_data.map.count_floor_tiles() > before.count_floor_tiles());
+        for (idx, tile) in before.tiles.iter().enumerate() {
+            if *tile == TileType::Floor {
+                assert!(build_data.map.tiles[idx] == TileType::Floor);
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+
+use rltk::RandomNumberGenerator;
+
+use crate::{BuildData, MetaMapBuilder, TileType};
+
+/// Decorations that can be walked over.
+const SCATTER: &[&str] = &["Rubble", "Bones", "Mushrooms"];
+/// Decorations that block movement, and so only go in open floor.
+const FEATURES: &[&str] = &["Statue", "Pillar", "Brazier"];
+/// One in this many decorations is a blocking feature.
+const FEATURE_CHANCE: i32 = 5;
+
+/// Scatters decorations through room interiors (or all open floor, if there are no rooms).
+///
+/// Each eligible tile gets a decoration with probability `density`. The start tile, the
+/// exit, doorways and anything already in the spawn list are left alone, and blocking
+/// features are only placed where every neighbor is open floor so they can't cut off paths.
+pub struct DecorationBuilder {
+    density: f32,
+}
+
+impl MetaMapBuilder for DecorationBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        self.build(rng, build_data);
+    }
+}
+
+impl DecorationBuilder {
+    pub fn new(density: f32) -> Box<DecorationBuilder> {
+        Box::new(DecorationBuilder { density })
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        let map = &build_data.map;
+        let mut taken: HashSet<usize> = build_data.spawn_list.iter().map(|(idx, _)| *idx).collect();
+        if let Some(start) = &build_data.start {
+            taken.insert(map.xy_idx(start.x, start.y));
+        }
+
+        let candidates: Vec<usize> = match &build_data.rooms {
+            Some(rooms) => rooms
+                .iter()
+                .flat_map(|room| {
+                    (room.y1 + 1..room.y2)
+                        .flat_map(move |y| (room.x1 + 1..room.x2).map(move |x| (x, y)))
+                })
+                .map(|(x, y)| map.xy_idx(x, y))
+                .collect(),
+            None => (0..map.tiles.len()).collect(),
+        };
+
+        let mut blocking: HashSet<usize> = HashSet::new();
+        let mut decorations: Vec<(usize, String)> = Vec::new();
+        for idx in candidates.iter() {
+            let (x, y) = (*idx as i32 % map.width, *idx as i32 / map.width);
+            if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+                continue;
+            }
+            if map.tiles[*idx] != TileType::Floor
+                || taken.contains(idx)
+                || is_doorway(build_data, x, y)
+            {
+                continue;
+            }
+            if rng.range(0.0, 1.0) >= self.density {
+                continue;
+            }
+
+            let open_around = neighbors(x, y)
+                .iter()
+                .map(|(n_x, n_y)| map.xy_idx(*n_x, *n_y))
+                .all(|n| map.tiles[n] == TileType::Floor && !blocking.contains(&n));
+            let name = if open_around && rng.roll_dice(1, FEATURE_CHANCE) == 1 {
+                blocking.insert(*idx);
+                rng.random_slice_entry(FEATURES)
+            } else {
+                rng.random_slice_entry(SCATTER)
+            };
+            if let Some(name) = name {
+                taken.insert(*idx);
+                decorations.push((*idx, name.to_string()));
+            }
+        }
+
+        build_data.spawn_list.append(&mut decorations);
+        build_data.take_snapshot();
+    }
+}
+
+/// The eight tiles around `(x, y)`.
+fn neighbors(x: i32, y: i32) -> [(i32, i32); 8] {
+    [
+        (x - 1, y - 1),
+        (x, y - 1),
+        (x + 1, y - 1),
+        (x - 1, y),
+        (x + 1, y),
+        (x - 1, y + 1),
+        (x, y + 1),
+        (x + 1, y + 1),
+    ]
+}
+
+/// True if `(x, y)` is a gap between walls on opposite sides, such as a door or corridor.
+fn is_doorway(build_data: &BuildData, x: i32, y: i32) -> bool {
+    let map = &build_data.map;
+    let wall = |x: i32, y: i32| map.tiles[map.xy_idx(x, y)] == TileType::Wall;
+    (wall(x - 1, y) && wall(x + 1, y)) || (wall(x, y - 1) && wall(x, y + 1))
+}
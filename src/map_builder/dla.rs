@@ -1,14 +1,13 @@
 use rand::{
     distributions::{Distribution, Standard},
-    Rng,
+    rngs::StdRng,
+    Rng, SeedableRng,
 };
 use std::collections::HashMap;
 
 use rltk::RandomNumberGenerator;
 
-use crate::{
-    spawner, BuildData, InitialMapBuilder, Map, Position, TileType, SHOW_MAPGEN_VISUALIZER,
-};
+use crate::{spawner, BuildData, InitialMapBuilder, Map, Position, TileType};
 
 use super::common::{paint, Digger, Symmetry};
 
@@ -33,9 +32,9 @@ impl InitialMapBuilder for DLABuilder {
 }
 
 impl DLABuilder {
-    pub fn new() -> Box<DLABuilder> {
-        match RandomNumberGenerator::new().roll_dice(1, 5) {
-            1 => DLABuilder::new_random(),
+    pub fn new(rng: &mut RandomNumberGenerator) -> Box<DLABuilder> {
+        match rng.roll_dice(1, 5) {
+            1 => DLABuilder::new_random(rng),
             2 => DLABuilder::new_walk_inwards(),
             3 => DLABuilder::new_walk_outwards(),
             4 => DLABuilder::new_central_attractor(),
@@ -43,11 +42,12 @@ impl DLABuilder {
         }
     }
 
-    pub fn new_random() -> Box<DLABuilder> {
-        let mut rng = rltk::RandomNumberGenerator::new();
+    pub fn new_random(rng: &mut RandomNumberGenerator) -> Box<DLABuilder> {
+        // Draw the enum picks from a generator seeded off `rng` to keep maps reproducible.
+        let mut picker = StdRng::seed_from_u64(rng.next_u64());
         Box::new(DLABuilder {
-            algorithm: rand::random(),
-            symmetry: rand::random(),
+            algorithm: picker.gen(),
+            symmetry: picker.gen(),
             brush_size: rng.roll_dice(1, 3),
             floor_percent: 0.25,
         })
@@ -90,10 +90,10 @@ impl DLABuilder {
     }
 
     fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
-        let start = Position::from(build_data.map.center());
-        let start_idx = build_data.map.xy_idx(start.x, start.y);
+        build_data.map.smooth_floors = true;
+        let start = build_data.map.center();
         build_data.take_snapshot();
-        DLABuilder::seed_start(&mut build_data.map, start_idx);
+        DLABuilder::seed_start(&mut build_data.map, start.x, start.y);
 
         // let total_tiles = self.map.tiles.len() as i32;
         let desired_floor_tiles = (self.floor_percent * build_data.map.tiles.len() as f32) as usize;
@@ -107,12 +107,15 @@ impl DLABuilder {
         }
     }
 
-    fn seed_start(map: &mut Map, start_idx: usize) {
-        map.tiles[start_idx] = TileType::Floor;
-        map.tiles[start_idx - 1] = TileType::Floor;
-        map.tiles[start_idx + 1] = TileType::Floor;
-        map.tiles[start_idx - map.width as usize] = TileType::Floor;
-        map.tiles[start_idx + map.width as usize] = TileType::Floor;
+    /// Digs a small plus shape around the start for the walkers to grow from.
+    ///
+    /// Arms that would fall off the edge of the map are skipped.
+    fn seed_start(map: &mut Map, x: i32, y: i32) {
+        for (dx, dy) in [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+            if let Some(idx) = map.try_xy_idx(x + dx, y + dy) {
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
     }
 
     fn walk_inwards(
@@ -185,8 +188,8 @@ impl DLABuilder {
 
             let mut path = rltk::line2d(
                 rltk::LineAlg::Bresenham,
-                rltk::Point::new(digger.x, digger.y),
-                rltk::Point::new(build_data.start.unwrap().x, build_data.start.unwrap().y),
+                digger.into(),
+                build_data.start.unwrap().into(),
             );
 
             while build_data.map.tiles[digger_idx] == TileType::Wall && !path.is_empty() {
@@ -278,3 +281,19 @@ impl Digger for TileDigger {
         prev_pos
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeding_in_a_corner_stays_on_the_map() {
+        let mut map = Map::new(1, 10, 10);
+        DLABuilder::seed_start(&mut map, 0, 0);
+        assert_eq!(map.count_floor_tiles(), 3);
+
+        let mut map = Map::new(1, 10, 10);
+        DLABuilder::seed_start(&mut map, 9, 9);
+        assert_eq!(map.count_floor_tiles(), 3);
+    }
+}
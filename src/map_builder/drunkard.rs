@@ -4,8 +4,8 @@ use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
 use super::common::{paint, Digger, Symmetry};
-use super::{Map, Position, TileType};
-use crate::{spawner, BuildData, InitialMapBuilder, SHOW_MAPGEN_VISUALIZER};
+use super::{Map, TileType};
+use crate::{spawner, BuildData, InitialMapBuilder};
 
 /// Sets where drunkards will start when generating a drunkards' walk map.
 #[derive(PartialEq, Copy, Clone)]
@@ -41,8 +41,8 @@ impl DrunkardsWalkBuilder {
         Box::new(DrunkardsWalkBuilder { settings })
     }
 
-    pub fn random() -> Box<DrunkardsWalkBuilder> {
-        match RandomNumberGenerator::new().roll_dice(1, 5) {
+    pub fn random(rng: &mut RandomNumberGenerator) -> Box<DrunkardsWalkBuilder> {
+        match rng.roll_dice(1, 5) {
             1 => DrunkardsWalkBuilder::open_area(),
             2 => DrunkardsWalkBuilder::open_halls(),
             3 => DrunkardsWalkBuilder::winding_passages(),
@@ -109,7 +109,8 @@ impl DrunkardsWalkBuilder {
     /// Builds the drunkards' walk map, using settings from one of the above constructors
     #[allow(clippy::map_entry)]
     fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
-        let start = Position::from(build_data.map.center());
+        build_data.map.smooth_floors = true;
+        let start = build_data.map.center();
         let start_idx = build_data.map.xy_idx(start.x, start.y);
         build_data.map.tiles[start_idx] = TileType::Floor;
 
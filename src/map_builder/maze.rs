@@ -3,16 +3,20 @@ use std::collections::HashMap;
 use rltk::RandomNumberGenerator;
 use specs::World;
 
-use crate::{
-    spawner, BuildData, InitialMapBuilder, Map, Position, TileType, SHOW_MAPGEN_VISUALIZER,
-};
+use crate::{spawner, BuildData, InitialMapBuilder, Map, Position, TileType};
 
 const TOP: usize = 0;
 const RIGHT: usize = 1;
 const BOTTOM: usize = 2;
 const LEFT: usize = 3;
 
-pub struct MazeBuilder {}
+/// Recursive-backtracker maze, optionally opened up afterwards.
+pub struct MazeBuilder {
+    /// Fraction of dead ends knocked through into a neighbouring passage, making loops.
+    braid: f32,
+    /// Fraction of cells filled back in, by repeatedly removing dead ends.
+    sparseness: f32,
+}
 
 impl InitialMapBuilder for MazeBuilder {
     fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut crate::BuildData) {
@@ -21,8 +25,8 @@ impl InitialMapBuilder for MazeBuilder {
 }
 
 impl MazeBuilder {
-    pub fn new() -> Box<MazeBuilder> {
-        Box::new(MazeBuilder {})
+    pub fn new(braid: f32, sparseness: f32) -> Box<MazeBuilder> {
+        Box::new(MazeBuilder { braid, sparseness })
     }
 
     #[allow(clippy::map_entry)]
@@ -33,6 +37,10 @@ impl MazeBuilder {
             rng,
         );
         maze.generate_maze(build_data);
+        maze.sparsify(self.sparseness);
+        maze.braid(self.braid);
+        maze.copy_to_map(&mut build_data.map);
+        build_data.take_snapshot();
     }
 }
 
@@ -43,6 +51,8 @@ struct Cell {
     col: i32,
     walls: [bool; 4],
     visited: bool,
+    /// Filled back in by [`Grid::sparsify`]; drawn as solid wall.
+    removed: bool,
 }
 
 impl Cell {
@@ -53,6 +63,7 @@ impl Cell {
             col,
             walls: [true, true, true, true],
             visited: false,
+            removed: false,
         }
     }
 
@@ -113,6 +124,102 @@ impl<'a> Grid<'a> {
         }
     }
 
+    /// Index of the cell on the other side of `cell`'s wall in direction `dir`.
+    fn neighbor(&self, cell: usize, dir: usize) -> Option<usize> {
+        let (row, col) = (self.cells[cell].row, self.cells[cell].col);
+        let idx = match dir {
+            TOP => self.calculate_index(row - 1, col),
+            RIGHT => self.calculate_index(row, col + 1),
+            BOTTOM => self.calculate_index(row + 1, col),
+            _ => self.calculate_index(row, col - 1),
+        };
+        if idx == -1 {
+            None
+        } else {
+            Some(idx as usize)
+        }
+    }
+
+    /// Knocks down the wall between `cell` and its neighbor in direction `dir`.
+    fn open_wall(&mut self, cell: usize, dir: usize) {
+        if let Some(next) = self.neighbor(cell, dir) {
+            self.cells[cell].walls[dir] = false;
+            self.cells[next].walls[(dir + 2) % 4] = false;
+        }
+    }
+
+    fn is_dead_end(&self, cell: usize) -> bool {
+        !self.cells[cell].removed && self.cells[cell].walls.iter().filter(|w| !**w).count() == 1
+    }
+
+    fn dead_ends(&self) -> Vec<usize> {
+        (0..self.cells.len())
+            .filter(|cell| self.is_dead_end(*cell))
+            .collect()
+    }
+
+    /// Fills in dead ends one at a time until `fraction` of the cells are gone.
+    ///
+    /// Only ever removing dead ends keeps the rest of the maze connected.
+    fn sparsify(&mut self, fraction: f32) {
+        let mut to_remove = (self.cells.len() as f32 * fraction) as usize;
+        while to_remove > 0 {
+            let dead_ends = self.dead_ends();
+            if dead_ends.is_empty() {
+                break;
+            }
+            let cell = dead_ends[(self.rng.roll_dice(1, dead_ends.len() as i32) - 1) as usize];
+            let dir = self.cells[cell].walls.iter().position(|w| !*w).unwrap();
+            if let Some(next) = self.neighbor(cell, dir) {
+                self.cells[next].walls[(dir + 2) % 4] = true;
+            }
+            self.cells[cell].walls = [true; 4];
+            self.cells[cell].removed = true;
+            to_remove -= 1;
+        }
+    }
+
+    /// Opens up `fraction` of the dead ends into a neighbouring passage, turning them into loops.
+    ///
+    /// Where possible, the wall knocked out leads into another dead end, fixing both at once.
+    fn braid(&mut self, fraction: f32) {
+        let mut dead_ends = self.dead_ends();
+        let to_braid = (dead_ends.len() as f32 * fraction).round() as usize;
+        for _ in 0..to_braid {
+            if dead_ends.is_empty() {
+                break;
+            }
+            let pick = (self.rng.roll_dice(1, dead_ends.len() as i32) - 1) as usize;
+            let cell = dead_ends.swap_remove(pick);
+            // An earlier pass may already have opened this one up.
+            if !self.is_dead_end(cell) {
+                continue;
+            }
+
+            let candidates: Vec<usize> = (0..4)
+                .filter(|dir| self.cells[cell].walls[*dir])
+                .filter(|dir| match self.neighbor(cell, *dir) {
+                    Some(next) => !self.cells[next].removed,
+                    None => false,
+                })
+                .collect();
+            let preferred: Vec<usize> = candidates
+                .iter()
+                .copied()
+                .filter(|dir| self.is_dead_end(self.neighbor(cell, *dir).unwrap()))
+                .collect();
+            let options = if preferred.is_empty() {
+                candidates
+            } else {
+                preferred
+            };
+            if !options.is_empty() {
+                let dir = options[(self.rng.roll_dice(1, options.len() as i32) - 1) as usize];
+                self.open_wall(cell, dir);
+            }
+        }
+    }
+
     fn get_available_neighbors(&self) -> Vec<usize> {
         let mut neighbors: Vec<usize> = Vec::new();
         let current_row = self.cells[self.current].row;
@@ -189,24 +296,63 @@ impl<'a> Grid<'a> {
     fn copy_to_map(&self, map: &mut Map) {
         map.tiles.iter_mut().for_each(|i| *i = TileType::Wall);
 
-        for cell in self.cells.iter() {
+        for cell in self.cells.iter().filter(|cell| !cell.removed) {
             let x = cell.col + 1;
             let y = cell.row + 1;
-            let idx = map.xy_idx(x * 2, y * 2);
+            let (x, y) = (x * 2, y * 2);
 
-            map.tiles[idx] = TileType::Floor;
-            if !cell.walls[TOP] {
-                map.tiles[idx - map.width as usize] = TileType::Floor
-            }
-            if !cell.walls[RIGHT] {
-                map.tiles[idx + 1] = TileType::Floor
-            }
-            if !cell.walls[BOTTOM] {
-                map.tiles[idx + map.width as usize] = TileType::Floor
-            }
-            if !cell.walls[LEFT] {
-                map.tiles[idx - 1] = TileType::Floor
+            let openings = [
+                (true, 0, 0),
+                (!cell.walls[TOP], 0, -1),
+                (!cell.walls[RIGHT], 1, 0),
+                (!cell.walls[BOTTOM], 0, 1),
+                (!cell.walls[LEFT], -1, 0),
+            ];
+            for (open, dx, dy) in openings.iter() {
+                if let (true, Some(idx)) = (*open, map.try_xy_idx(x + dx, y + dy)) {
+                    map.tiles[idx] = TileType::Floor;
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuilderChain;
+
+    fn generated_grid(rng: &mut RandomNumberGenerator) -> Grid {
+        let mut build_data = BuilderChain::new(1, 40, 30).build_data;
+        build_data.record_history = false;
+        let mut maze = Grid::new(18, 13, rng);
+        maze.generate_maze(&mut build_data);
+        maze
+    }
+
+    #[test]
+    fn braiding_removes_dead_ends() {
+        let mut rng = RandomNumberGenerator::seeded(5);
+        let mut maze = generated_grid(&mut rng);
+        let before = maze.dead_ends().len();
+        assert!(before > 0);
+
+        maze.braid(0.5);
+        let after = maze.dead_ends().len();
+        assert!(
+            after <= before / 2,
+            "{} dead ends left of {}",
+            after,
+            before
+        );
+    }
+
+    #[test]
+    fn sparseness_removes_cells() {
+        let mut rng = RandomNumberGenerator::seeded(5);
+        let mut maze = generated_grid(&mut rng);
+        maze.sparsify(0.25);
+        let removed = maze.cells.iter().filter(|cell| cell.removed).count();
+        assert_eq!(removed, maze.cells.len() / 4);
+    }
+}
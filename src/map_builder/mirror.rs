@@ -0,0 +1,140 @@
+use rltk::RandomNumberGenerator;
+
+use crate::{BuildData, MetaMapBuilder, Position, TileType};
+
+use super::common::{reflect, DistantExit, Symmetry, FINAL_EXIT_SPAWNS};
+
+/// Makes a level symmetrical by copying its left (and/or top) half over the other half.
+///
+/// `Symmetry::Horizontal` mirrors left to right, `Symmetry::Vertical` top to bottom and
+/// `Symmetry::Both` does both, leaving four matching quarters. Spawns are mirrored along with the
+/// tiles. Best used straight after the initial builder; if a start or exit has already been
+/// placed, the start is moved onto the kept half and the exit is placed again.
+pub struct MirrorMap {
+    axis: Symmetry,
+}
+
+impl MetaMapBuilder for MirrorMap {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        self.build(rng, build_data);
+    }
+}
+
+impl MirrorMap {
+    pub fn new(axis: Symmetry) -> Box<MirrorMap> {
+        Box::new(MirrorMap { axis })
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        let (flip_x, flip_y) = match self.axis {
+            Symmetry::None => return,
+            Symmetry::Horizontal => (true, false),
+            Symmetry::Vertical => (false, true),
+            Symmetry::Both => (true, true),
+        };
+        let (width, height) = (build_data.map.width, build_data.map.height);
+        // Coordinates on the half being kept; the middle column/row (on odd sizes) mirrors onto
+        // itself, so it's kept as-is rather than copied twice.
+        let kept_x = |x: i32| !flip_x || x <= reflect(x, width);
+        let kept_y = |y: i32| !flip_y || y <= reflect(y, height);
+        let mirrors = |x: i32, y: i32| {
+            let mut images = vec![(x, y)];
+            if flip_x && x != reflect(x, width) {
+                images.push((reflect(x, width), y));
+            }
+            if flip_y && y != reflect(y, height) {
+                for i in 0..images.len() {
+                    images.push((images[i].0, reflect(y, height)));
+                }
+            }
+            images
+        };
+
+        let had_exit = build_data.map.tiles.contains(&TileType::DownStairs)
+            || build_data
+                .spawn_list
+                .iter()
+                .any(|(_, name)| FINAL_EXIT_SPAWNS.contains(&name.as_str()));
+
+        for y in 0..height {
+            for x in 0..width {
+                if !(kept_x(x) && kept_y(y)) {
+                    continue;
+                }
+                let tile = build_data.map.tiles[build_data.map.xy_idx(x, y)];
+                for (mx, my) in mirrors(x, y).into_iter().skip(1) {
+                    let idx = build_data.map.xy_idx(mx, my);
+                    build_data.map.tiles[idx] = tile;
+                }
+            }
+        }
+
+        // Spawns on the overwritten side go; the rest are copied across.
+        let spawns = std::mem::take(&mut build_data.spawn_list);
+        for (idx, name) in spawns {
+            let (x, y) = (idx as i32 % width, idx as i32 / width);
+            if !(kept_x(x) && kept_y(y)) || FINAL_EXIT_SPAWNS.contains(&name.as_str()) {
+                continue;
+            }
+            for (mx, my) in mirrors(x, y) {
+                let idx = build_data.map.xy_idx(mx, my);
+                build_data.spawn_list.push((idx, name.clone()));
+            }
+        }
+
+        if let Some(start) = build_data.start.as_mut() {
+            if !kept_x(start.x) {
+                start.x = reflect(start.x, width);
+            }
+            if !kept_y(start.y) {
+                start.y = reflect(start.y, height);
+            }
+        }
+        build_data.take_snapshot();
+
+        // Mirroring copied the exit too, so clear them all out and put one back.
+        if had_exit && build_data.start.is_some() {
+            for tile in build_data.map.tiles.iter_mut() {
+                if *tile == TileType::DownStairs {
+                    *tile = TileType::Floor;
+                }
+            }
+            DistantExit::new().build_map(rng, build_data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuilderChain, Map};
+
+    #[test]
+    fn mirrors_tiles_and_spawns_left_to_right() {
+        let mut build_data = BuilderChain::new(1, 11, 5).build_data;
+        build_data.record_history = false;
+        // The junk on the right-hand side gets overwritten.
+        let rows = [
+            "###########",
+            "########.##",
+            "#.....#####",
+            "###########",
+            "###########",
+        ];
+        build_data.map = Map::from_ascii(&rows.join("\n")).unwrap().0;
+        let map = &build_data.map;
+        build_data.spawn_list = vec![
+            (map.xy_idx(8, 1), "Rat".to_string()),
+            (map.xy_idx(2, 2), "Goblin".to_string()),
+            (map.xy_idx(5, 2), "Orc".to_string()),
+        ];
+
+        let mut rng = RandomNumberGenerator::seeded(1);
+        MirrorMap::new(Symmetry::Horizontal).build(&mut rng, &mut build_data);
+
+        assert_eq!(
+            build_data.map.to_ascii_with(None, &build_data.spawn_list),
+            "###########\n###########\n#.G..O..G.#\n###########\n###########\n"
+        );
+    }
+}
@@ -6,17 +6,21 @@ use area_based_gen::{AreaStartingPosition, VoronoiSpawning, XStart, YStart};
 use bsp_dungeon::BspDungeonBuilder;
 use bsp_interior::BspInteriorBuilder;
 use cellular_automata::CellularAutomataBuilder;
-use common::{CullUnreachable, DistantExit};
+use common::{ConnectRegions, CullUnreachable, DistantExit, Symmetry};
+use decoration::DecorationBuilder;
 use dla::DLABuilder;
 use drunkard::DrunkardsWalkBuilder;
 use maze::MazeBuilder;
+use mirror::MirrorMap;
 use prefab_builder::PrefabBuilder;
 use room_based_gen::{RoomBasedSpawner, RoomBasedStairs, RoomBasedStartingPosition};
+use room_theming::RoomThemer;
 use simple_map::SimpleMapBuilder;
 use voronoi::VoronoiBuilder;
+use water::WaterBuilder;
 use waveform_collapse::WaveformCollapseBuilder;
 
-use crate::{spawner, SHOW_MAPGEN_VISUALIZER};
+use crate::{difficulty::Difficulty, settings::env_flag, spawner};
 
 use super::Rect;
 use super::{components::Position, map::*};
@@ -26,15 +30,34 @@ mod bsp_dungeon;
 mod bsp_interior;
 mod cellular_automata;
 mod common;
+mod decoration;
 mod dla;
 mod drunkard;
 mod maze;
+mod mirror;
 mod prefab_builder;
 mod room_based_gen;
+mod room_theming;
 mod simple_map;
 mod voronoi;
+mod water;
 mod waveform_collapse;
 
+/// Chance for each eligible interior tile to get a decoration.
+const DECORATION_DENSITY: f32 = 0.03;
+
+/// Fraction of maze dead ends opened up into loops.
+const MAZE_BRAID: f32 = 0.5;
+
+/// Fraction of maze cells filled back in, to thin out the passages.
+const MAZE_SPARSENESS: f32 = 0.1;
+
+/// Depth of the shop level, where gold can be spent instead of fighting.
+pub const SHOP_DEPTH: i32 = 5;
+
+/// Depth of the last level, where the amulet (and its guardian) wait instead of stairs down.
+pub const FINAL_DEPTH: i32 = 10;
+
 pub trait InitialMapBuilder {
     fn build_map(&mut self, rng: &mut rltk::RandomNumberGenerator, build_data: &mut BuildData);
 }
@@ -43,8 +66,71 @@ pub trait MetaMapBuilder {
     fn build_map(&mut self, rng: &mut rltk::RandomNumberGenerator, build_data: &mut BuildData);
 }
 
-pub fn random_builder(new_depth: i32, rng: &mut rltk::RandomNumberGenerator) -> BuilderChain {
-    BuilderChains::CellularAutomata.match_builder(new_depth)
+/// One in this many levels is dug out as rooms and corridors instead of caves.
+const ROOMS_CHANCE: i32 = 3;
+
+/// Picks a builder chain for a `width` by `height` level at `new_depth`.
+///
+/// Most levels are caves; the rest are rooms, which is where themed rooms turn up.
+pub fn random_builder(
+    new_depth: i32,
+    width: i32,
+    height: i32,
+    rng: &mut rltk::RandomNumberGenerator,
+) -> BuilderChain {
+    if new_depth == SHOP_DEPTH {
+        return BuilderChain::new(new_depth, width, height)
+            .start_with(PrefabBuilder::constant(prefab_builder::prefab_levels::SHOP));
+    }
+    let chain = if rng.roll_dice(1, ROOMS_CHANCE) == 1 {
+        BuilderChains::SimpleMap
+    } else {
+        BuilderChains::CellularAutomata
+    };
+    chain.match_builder(new_depth, width, height, rng)
+}
+
+/// Builds a full-sized level at `depth` without touching the ECS.
+///
+/// Returns everything the chain left behind; the caller decides what to do with the spawn list.
+pub fn build_level(
+    depth: i32,
+    difficulty: Difficulty,
+    record_history: bool,
+    rng: &mut rltk::RandomNumberGenerator,
+) -> BuildData {
+    let mut builder = random_builder(depth, MAPWIDTH as i32, MAPHEIGHT as i32, rng);
+    builder.build_data.difficulty = difficulty;
+    builder.build_data.record_history = record_history;
+    builder.build_map(rng);
+    builder.build_data
+}
+
+/// Generates the level at `depth` from nothing but a seed, for tests and offline tooling.
+///
+/// Returns the finished map, what to spawn where, and the player's starting position.
+pub fn generate_map(depth: i32, seed: u64) -> (Map, Vec<(usize, String)>, Position) {
+    let mut rng = rltk::RandomNumberGenerator::seeded(seed);
+    let build_data = build_level(depth, Difficulty::default(), false, &mut rng);
+    let start = build_data
+        .start
+        .expect("Builder chain finished without a starting position");
+    (build_data.map, build_data.spawn_list, start)
+}
+
+/// Resource controlling whether map generation is played back on screen.
+///
+/// On unless the `SHOW_MAPGEN_VISUALIZER` environment variable is `0` or `false`.
+pub struct MapgenVisualizer {
+    pub enabled: bool,
+}
+
+impl Default for MapgenVisualizer {
+    fn default() -> Self {
+        MapgenVisualizer {
+            enabled: env_flag("SHOW_MAPGEN_VISUALIZER", true),
+        }
+    }
 }
 
 pub struct BuildData {
@@ -53,11 +139,16 @@ pub struct BuildData {
     pub start: Option<Position>,
     pub rooms: Option<Vec<Rect>>,
     pub history: Vec<Map>,
+    /// Whether [`BuildData::take_snapshot`] records anything; cloning the map at every step is
+    /// wasted work when nobody is going to watch it being built.
+    pub record_history: bool,
+    /// Difficulty of the run the map is being built for, which scales spawn counts.
+    pub difficulty: Difficulty,
 }
 
 impl BuildData {
     fn take_snapshot(&mut self) {
-        if SHOW_MAPGEN_VISUALIZER {
+        if self.record_history {
             let mut snapshot = self.map.clone();
             snapshot.revealed_tiles.iter_mut().for_each(|v| *v = true);
             self.history.push(snapshot);
@@ -72,16 +163,18 @@ pub struct BuilderChain {
 }
 
 impl BuilderChain {
-    pub fn new(new_depth: i32) -> BuilderChain {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> BuilderChain {
         BuilderChain {
             starter: None,
             builders: Vec::new(),
             build_data: BuildData {
                 spawn_list: Vec::new(),
-                map: Map::new(new_depth),
+                map: Map::new(new_depth, width, height),
                 start: None,
                 rooms: None,
                 history: Vec::new(),
+                record_history: true,
+                difficulty: Difficulty::default(),
             },
         }
     }
@@ -128,57 +221,80 @@ pub enum BuilderChains {
     Maze,
     Prefab,
     Voronoi,
+    /// Symmetrical caves, for set-piece fights.
+    Arena,
 }
 
 impl BuilderChains {
-    pub fn match_builder(&self, new_depth: i32) -> BuilderChain {
+    pub fn match_builder(
+        &self,
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        rng: &mut rltk::RandomNumberGenerator,
+    ) -> BuilderChain {
         match *self {
-            BuilderChains::SimpleMap => BuilderChain::new(new_depth)
+            BuilderChains::SimpleMap => BuilderChain::new(new_depth, width, height)
                 .start_with(SimpleMapBuilder::new())
                 .with(RoomBasedSpawner::new())
+                .with(RoomThemer::new())
                 .with(RoomBasedStartingPosition::new())
-                .with(RoomBasedStairs::new()),
-            BuilderChains::BspDungeon => BuilderChain::new(new_depth)
+                .with(RoomBasedStairs::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
+            BuilderChains::BspDungeon => BuilderChain::new(new_depth, width, height)
                 .start_with(BspDungeonBuilder::new())
                 .with(RoomBasedSpawner::new())
+                .with(RoomThemer::new())
                 .with(RoomBasedStartingPosition::new())
-                .with(RoomBasedStairs::new()),
-            BuilderChains::BspInterior => BuilderChain::new(new_depth)
+                .with(RoomBasedStairs::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
+            BuilderChains::BspInterior => BuilderChain::new(new_depth, width, height)
                 .start_with(BspInteriorBuilder::new())
                 .with(RoomBasedSpawner::new())
+                .with(RoomThemer::new())
                 .with(RoomBasedStartingPosition::new())
-                .with(RoomBasedStairs::new()),
-            BuilderChains::CellularAutomata => BuilderChain::new(new_depth)
+                .with(RoomBasedStairs::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
+            BuilderChains::CellularAutomata => BuilderChain::new(new_depth, width, height)
                 .start_with(CellularAutomataBuilder::new())
                 .with(AreaStartingPosition::new(XStart::CENTER, YStart::CENTER))
-                .with(CullUnreachable::new())
+                .with(ConnectRegions::new())
                 .with(VoronoiSpawning::new())
-                .with(DistantExit::new()),
-            BuilderChains::DrunkardsWalk => BuilderChain::new(new_depth)
-                .start_with(DrunkardsWalkBuilder::random())
+                .with(DistantExit::new())
+                .with(WaterBuilder::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
+            BuilderChains::DrunkardsWalk => BuilderChain::new(new_depth, width, height)
+                .start_with(DrunkardsWalkBuilder::random(rng))
                 .with(AreaStartingPosition::new(XStart::CENTER, YStart::CENTER))
                 .with(CullUnreachable::new())
                 .with(VoronoiSpawning::new())
-                .with(DistantExit::new()),
-            BuilderChains::DiffusionLimitedAggregation => BuilderChain::new(new_depth)
-                .start_with(DLABuilder::new())
+                .with(DistantExit::new())
+                .with(WaterBuilder::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
+            BuilderChains::DiffusionLimitedAggregation => {
+                BuilderChain::new(new_depth, width, height)
+                    .start_with(DLABuilder::new(rng))
+                    .with(AreaStartingPosition::new(XStart::CENTER, YStart::CENTER))
+                    .with(ConnectRegions::new())
+                    .with(VoronoiSpawning::new())
+                    .with(DistantExit::new())
+                    .with(DecorationBuilder::new(DECORATION_DENSITY))
+            }
+            BuilderChains::Maze => BuilderChain::new(new_depth, width, height)
+                .start_with(MazeBuilder::new(MAZE_BRAID, MAZE_SPARSENESS))
                 .with(AreaStartingPosition::new(XStart::CENTER, YStart::CENTER))
                 .with(CullUnreachable::new())
                 .with(VoronoiSpawning::new())
-                .with(DistantExit::new()),
-            BuilderChains::Maze => BuilderChain::new(new_depth)
-                .start_with(MazeBuilder::new())
+                .with(DistantExit::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
+            BuilderChains::Voronoi => BuilderChain::new(new_depth, width, height)
+                .start_with(VoronoiBuilder::new(rng))
                 .with(AreaStartingPosition::new(XStart::CENTER, YStart::CENTER))
                 .with(CullUnreachable::new())
                 .with(VoronoiSpawning::new())
-                .with(DistantExit::new()),
-            BuilderChains::Voronoi => BuilderChain::new(new_depth)
-                .start_with(VoronoiBuilder::new())
-                .with(AreaStartingPosition::new(XStart::CENTER, YStart::CENTER))
-                .with(CullUnreachable::new())
-                .with(VoronoiSpawning::new())
-                .with(DistantExit::new()),
-            BuilderChains::Prefab => BuilderChain::new(new_depth)
+                .with(DistantExit::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
+            BuilderChains::Prefab => BuilderChain::new(new_depth, width, height)
                 .start_with(VoronoiBuilder::pythagoras(64))
                 .with(WaveformCollapseBuilder::new())
                 .with(PrefabBuilder::room_vaults())
@@ -188,8 +304,75 @@ impl BuilderChains {
                 .with(PrefabBuilder::sectional(
                     prefab_builder::prefab_sections::UNDERGROUND_FORT,
                 ))
-                .with(DistantExit::new()),
+                .with(DistantExit::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
+            BuilderChains::Arena => BuilderChain::new(new_depth, width, height)
+                .start_with(CellularAutomataBuilder::new())
+                .with(MirrorMap::new(Symmetry::Horizontal))
+                .with(AreaStartingPosition::new(XStart::CENTER, YStart::CENTER))
+                .with(ConnectRegions::new())
+                .with(VoronoiSpawning::new())
+                .with(DistantExit::new())
+                .with(DecorationBuilder::new(DECORATION_DENSITY)),
             _ => panic!("BuilderChain yet implemented for specified builder!"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(record_history: bool) -> BuildData {
+        let mut rng = rltk::RandomNumberGenerator::seeded(7);
+        let mut builder = BuilderChains::SimpleMap.match_builder(1, 80, 50, &mut rng);
+        builder.build_data.record_history = record_history;
+        builder.build_map(&mut rng);
+        builder.build_data
+    }
+
+    #[test]
+    fn history_is_only_recorded_when_asked_for() {
+        assert!(!build(true).history.is_empty());
+        assert!(build(false).history.is_empty());
+    }
+
+    #[test]
+    fn recording_history_does_not_change_the_map() {
+        assert!(build(true).map.tiles == build(false).map.tiles);
+    }
+
+    #[test]
+    fn seeded_level_is_connected_with_reachable_stairs() {
+        let (mut map, _, start) = generate_map(1, 1234);
+        assert_eq!(common::connected_regions(&map).len(), 1);
+
+        map.populate_blocked();
+        let start_idx = map.xy_idx(start.x, start.y);
+        let dijkstra = rltk::DijkstraMap::new(
+            map.width as usize,
+            map.height as usize,
+            &[start_idx],
+            &map,
+            1000.0,
+        );
+        let stairs = map
+            .tiles
+            .iter()
+            .position(|tile| *tile == TileType::DownStairs)
+            .expect("No stairs down");
+        assert!(dijkstra.map[stairs] < std::f32::MAX);
+    }
+
+    #[test]
+    fn same_seed_same_level() {
+        let (first, first_spawns, first_start) = generate_map(3, 99);
+        let (second, second_spawns, second_start) = generate_map(3, 99);
+        assert!(first.tiles == second.tiles);
+        assert_eq!(first_spawns, second_spawns);
+        assert_eq!(
+            (first_start.x, first_start.y),
+            (second_start.x, second_start.y)
+        );
+    }
+}
@@ -1,9 +1,6 @@
 use std::collections::HashSet;
 
-use crate::{
-    spawner, BuildData, InitialMapBuilder, Map, MetaMapBuilder, Position, TileType,
-    SHOW_MAPGEN_VISUALIZER,
-};
+use crate::{spawner, BuildData, InitialMapBuilder, Map, MetaMapBuilder, Position, TileType};
 
 use prefab_rooms::PrefabRoom;
 use prefab_sections::{HorizontalPlacement, VerticalPlacement};
@@ -91,29 +88,42 @@ impl PrefabBuilder {
                 });
             }
             '>' => build_data.map.tiles[idx] = TileType::DownStairs,
-            'g' => {
-                build_data.map.tiles[idx] = TileType::Floor;
-                build_data.spawn_list.push((idx, "Goblin".to_string()));
-            }
-            'o' => {
-                build_data.map.tiles[idx] = TileType::Floor;
-                build_data.spawn_list.push((idx, "Orc".to_string()));
-            }
-            '^' => {
-                build_data.map.tiles[idx] = TileType::Floor;
-                build_data.spawn_list.push((idx, "Bear Trap".to_string()));
-            }
-            '%' => {
-                build_data.map.tiles[idx] = TileType::Floor;
-                build_data.spawn_list.push((idx, "Rations".to_string()));
-            }
-            '!' => {
-                build_data.map.tiles[idx] = TileType::Floor;
-                build_data
-                    .spawn_list
-                    .push((idx, "Health Potion".to_string()));
-            }
-            _ => rltk::console::log(format!("Unknown glyph when loading map: {}", ch)),
+            _ => match Self::glyph_to_spawn(ch) {
+                Some(name) => {
+                    build_data.map.tiles[idx] = TileType::Floor;
+                    build_data.spawn_list.push((idx, name.to_string()));
+                }
+                None => rltk::console::log(format!("Unknown glyph when loading map: {}", ch)),
+            },
+        }
+    }
+
+    /// Name of the entity a prefab glyph places on a floor tile, if it places one.
+    fn glyph_to_spawn(ch: char) -> Option<&'static str> {
+        match ch {
+            // Monsters
+            'g' => Some("Goblin"),
+            'o' => Some("Orc"),
+            'O' => Some("Orc Captain"),
+            'G' => Some("Ogre"),
+            'b' => Some("Vampire Bat"),
+            'W' => Some("Wraith"),
+            'j' => Some("Slime"),
+            // Items
+            '%' => Some("Rations"),
+            '!' => Some("Health Potion"),
+            '?' => Some("Magic Missile Scroll"),
+            '/' => Some("Longsword"),
+            '(' => Some("Shield"),
+            '[' => Some("Leather Armor"),
+            // Traps and scenery
+            '^' => Some("Bear Trap"),
+            '&' => Some("Statue"),
+            // Shops
+            '$' => Some("Gold"),
+            'S' => Some("Shopkeeper"),
+            '*' => Some("Shop Stock"),
+            _ => None,
         }
     }
 
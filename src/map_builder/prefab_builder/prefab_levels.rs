@@ -11,6 +11,13 @@ pub const WFC_POPULATED: PrefabLevel = PrefabLevel {
     height: 43,
 };
 
+/// A quiet level with a shopkeeper and some wares on display.
+pub const SHOP: PrefabLevel = PrefabLevel {
+    template: SHOP_MAP,
+    width: 80,
+    height: 43,
+};
+
 const LEVEL_MAP: &str = "
 ###############################################################################
 #### ######### #    # #######       #########  ####    #####                ###
@@ -44,3 +51,49 @@ const LEVEL_MAP: &str = "
 #!%^   ###  ###     ############### ########      ##### g     ####      # g#   #
 ###############################################################################
 ";
+
+const SHOP_MAP: &str = "
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+##########################&                          &##########################
+##########################              S             ##########################
+##########################                            ##########################
+##########################    *   *   *   *   *       ##########################
+##########################                            ##########################
+##########################                            ##########################
+##########################      *   *   *   *   *     ##########################
+##########################                            ##########################
+##########################                            ##########################
+##########################                            ##########################
+##########################                            ##########################
+##########################                            ##########################
+##########################&                          &##########################
+######################################    ######################################
+######################################    ######################################
+######################################    ######################################
+######################################    ######################################
+######################################    ######################################
+######################################    ######################################
+##############################                    ##############################
+##############################                    ##############################
+##############################                >   ##############################
+##############################                    ##############################
+##############################    @               ##############################
+##############################                    ##############################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+################################################################################
+";
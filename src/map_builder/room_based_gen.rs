@@ -1,4 +1,5 @@
-use crate::{spawner, BuildData, MetaMapBuilder, Position, TileType};
+use super::common::place_exit;
+use crate::{spawner, BuildData, MetaMapBuilder};
 
 pub struct RoomBasedSpawner {}
 
@@ -25,6 +26,7 @@ impl RoomBasedSpawner {
                     rng,
                     room,
                     build_data.map.depth,
+                    build_data.difficulty,
                     &mut build_data.spawn_list,
                 );
             }
@@ -49,8 +51,7 @@ impl RoomBasedStartingPosition {
 
     fn build(&mut self, rng: &mut rltk::RandomNumberGenerator, build_data: &mut BuildData) {
         if let Some(rooms) = &build_data.rooms {
-            let start = rooms[0].center();
-            build_data.start = Some(Position::from(start));
+            build_data.start = Some(rooms[0].center());
         } else {
             panic!("Room-based start only works after rooms have been created");
         }
@@ -73,8 +74,8 @@ impl RoomBasedStairs {
     fn build(&mut self, rng: &mut rltk::RandomNumberGenerator, build_data: &mut BuildData) {
         if let Some(rooms) = &build_data.rooms {
             let stairs = rooms.last().unwrap().center();
-            let idx = build_data.map.xy_idx(stairs.0, stairs.1);
-            build_data.map.tiles[idx] = TileType::DownStairs;
+            let idx = build_data.map.xy_idx(stairs.x, stairs.y);
+            place_exit(build_data, idx);
             build_data.take_snapshot();
         } else {
             panic!("Room-based stairs only works after rooms have been created")
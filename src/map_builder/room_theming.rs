@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+
+use rltk::RandomNumberGenerator;
+
+use crate::{random_table::RandomTable, spawner, BuildData, MetaMapBuilder, Rect, TileType};
+
+/// One in this many eligible rooms gets a theme.
+const THEME_CHANCE: i32 = 4;
+
+/// What a themed room is stocked with.
+#[derive(PartialEq, Copy, Clone, Debug)]
+enum RoomTheme {
+    Armory,
+    Library,
+    TreasureVault,
+}
+
+impl RoomTheme {
+    /// What the room's extra items are rolled from.
+    fn loot_table(self) -> RandomTable {
+        match self {
+            RoomTheme::Armory => RandomTable::new()
+                .add("Dagger", 4)
+                .add("Shield", 4)
+                .add("Longsword", 2)
+                .add("Tower Shield", 2)
+                .add("Battle Axe", 1)
+                .add("Leather Cap", 3)
+                .add("Leather Armor", 3)
+                .add("Chain Mail", 1)
+                .add("Leather Gloves", 3)
+                .add("Leather Boots", 3),
+            RoomTheme::Library => RandomTable::new()
+                .add("Magic Missile Scroll", 4)
+                .add("Confusion Scroll", 3)
+                .add("Fireball Scroll", 2)
+                .add("Magic Mapping Scroll", 2)
+                .add("Clairvoyance Scroll", 1),
+            RoomTheme::TreasureVault => RandomTable::new()
+                .add("Health Potion", 4)
+                .add("Telepathy Potion", 2)
+                .add("Gold", 4),
+        }
+    }
+
+    /// How many extra items the room gets.
+    fn item_count(self, rng: &mut RandomNumberGenerator) -> i32 {
+        match self {
+            RoomTheme::Armory => rng.roll_dice(1, 3) + 1,
+            RoomTheme::Library => rng.roll_dice(1, 3) + 1,
+            RoomTheme::TreasureVault => rng.roll_dice(1, 3) + 2,
+        }
+    }
+
+    /// How many monsters are guaranteed to guard the room.
+    fn guard_count(self, rng: &mut RandomNumberGenerator) -> i32 {
+        match self {
+            RoomTheme::TreasureVault => rng.roll_dice(1, 2) + 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Gives some rooms a theme, adding spawns to match: armories get weapons and armor,
+/// libraries get scrolls, and treasure vaults get potions and gold along with guards.
+///
+/// The first room (where the player starts) and the last (with the exit) are never themed.
+pub struct RoomThemer {}
+
+impl MetaMapBuilder for RoomThemer {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        self.build(rng, build_data);
+    }
+}
+
+impl RoomThemer {
+    pub fn new() -> Box<RoomThemer> {
+        Box::new(RoomThemer {})
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        let rooms: Vec<Rect> = match &build_data.rooms {
+            Some(rooms) if rooms.len() > 2 => rooms[1..rooms.len() - 1].to_vec(),
+            _ => return,
+        };
+
+        let mut taken: HashSet<usize> = build_data.spawn_list.iter().map(|(idx, _)| *idx).collect();
+        for room in rooms.iter() {
+            if rng.roll_dice(1, THEME_CHANCE) != 1 {
+                continue;
+            }
+            let theme = match rng.roll_dice(1, 3) {
+                1 => RoomTheme::Armory,
+                2 => RoomTheme::Library,
+                _ => RoomTheme::TreasureVault,
+            };
+
+            let mut open_tiles: Vec<usize> = room
+                .iter()
+                .map(|(x, y)| build_data.map.xy_idx(x, y))
+                .filter(|idx| build_data.map.tiles[*idx] == TileType::Floor && !taken.contains(idx))
+                .collect();
+
+            let loot = theme.loot_table();
+            let items = theme.item_count(rng);
+            let guards = theme.guard_count(rng);
+            for i in 0..items + guards {
+                if open_tiles.is_empty() {
+                    break;
+                }
+                let idx =
+                    open_tiles.remove((rng.roll_dice(1, open_tiles.len() as i32) - 1) as usize);
+                // Guards go down first, so a cramped room still gets them.
+                let name = if i < guards {
+                    spawner::wandering_monster(rng, build_data.map.depth)
+                } else {
+                    loot.roll(rng)
+                };
+                taken.insert(idx);
+                build_data.spawn_list.push((idx, name));
+            }
+        }
+        build_data.take_snapshot();
+    }
+}
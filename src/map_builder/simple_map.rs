@@ -1,5 +1,5 @@
 use super::{common::*, Map, Rect, TileType};
-use crate::{spawner, BuildData, InitialMapBuilder, Position, SHOW_MAPGEN_VISUALIZER};
+use crate::{spawner, BuildData, InitialMapBuilder, Position};
 use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
@@ -40,14 +40,14 @@ impl SimpleMapBuilder {
                 build_data.take_snapshot();
 
                 if !rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = rooms.last().unwrap().center();
+                    let new = new_room.center();
+                    let prev = rooms.last().unwrap().center();
                     if rng.range(0, 2) == 1 {
-                        apply_horizontal_tunnel(&mut build_data.map, prev_x, new_x, prev_y);
-                        apply_vertical_tunnel(&mut build_data.map, prev_y, new_y, new_x);
+                        apply_horizontal_tunnel(&mut build_data.map, prev.x, new.x, prev.y);
+                        apply_vertical_tunnel(&mut build_data.map, prev.y, new.y, new.x);
                     } else {
-                        apply_vertical_tunnel(&mut build_data.map, prev_y, new_y, prev_x);
-                        apply_horizontal_tunnel(&mut build_data.map, prev_x, new_x, new_y);
+                        apply_vertical_tunnel(&mut build_data.map, prev.y, new.y, prev.x);
+                        apply_horizontal_tunnel(&mut build_data.map, prev.x, new.x, new.y);
                     }
                 }
 
@@ -2,10 +2,7 @@ use std::collections::HashMap;
 
 use rltk::RandomNumberGenerator;
 
-use crate::{
-    spawner, BuildData, InitialMapBuilder, Map, Position, TileType, MAPHEIGHT, MAPWIDTH,
-    SHOW_MAPGEN_VISUALIZER,
-};
+use crate::{spawner, BuildData, InitialMapBuilder, Map, Position, TileType, MAPHEIGHT, MAPWIDTH};
 
 use super::common::DistanceAlgorithm;
 
@@ -22,8 +19,8 @@ impl InitialMapBuilder for VoronoiBuilder {
 }
 
 impl VoronoiBuilder {
-    pub fn new() -> Box<VoronoiBuilder> {
-        match RandomNumberGenerator::new().roll_dice(1, 3) {
+    pub fn new(rng: &mut RandomNumberGenerator) -> Box<VoronoiBuilder> {
+        match rng.roll_dice(1, 3) {
             1 => Self::pythagoras(64),
             2 => Self::manhattan(64),
             _ => Self::chebyshev(64),
@@ -70,6 +67,9 @@ impl VoronoiBuilder {
     }
 
     pub fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        self.diagram
+            .resize(build_data.map.width, build_data.map.height);
+        self.diagram.generate(rng);
         for y in 1..build_data.map.height - 1 {
             for x in 1..build_data.map.width - 1 {
                 let idx = build_data.map.xy_idx(x, y);
@@ -88,7 +88,6 @@ impl VoronoiBuilder {
 /// Handles seeding, membership, and neighboring.
 struct VoronoiDiagram {
     pub membership: Vec<i32>,
-    rng: rltk::RandomNumberGenerator,
     seeds: Vec<(usize, rltk::Point)>,
     width: i32,
     height: i32,
@@ -96,27 +95,38 @@ struct VoronoiDiagram {
 }
 
 impl VoronoiDiagram {
-    /// Constructs a new seeded VoronoiDiagram with distance and
-    /// membership calculated.
+    /// Constructs a new, unseeded VoronoiDiagram; call [`VoronoiDiagram::generate`]
+    /// before reading membership.
     pub fn new(width: i32, height: i32, distance_algorithm: DistanceAlgorithm) -> VoronoiDiagram {
-        let mut vd = VoronoiDiagram {
+        VoronoiDiagram {
             membership: vec![0; (width * height) as usize],
-            rng: rltk::RandomNumberGenerator::new(),
             seeds: Vec::new(),
             width,
             height,
             distance: DistanceAlgorithm::get_func(&distance_algorithm),
-        };
-        vd.populate_seeds(64);
-        vd.determine_membership(64);
-        vd
+        }
+    }
+
+    /// Changes the dimensions the diagram covers, discarding any membership.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.width = width;
+        self.height = height;
+        self.membership = vec![0; (width * height) as usize];
+    }
+
+    /// Seeds the diagram from `rng` and calculates membership, so the same
+    /// RNG state always produces the same diagram.
+    pub fn generate(&mut self, rng: &mut RandomNumberGenerator) {
+        self.seeds.clear();
+        self.populate_seeds(rng, 64);
+        self.determine_membership(64);
     }
 
     /// Generates `n_seeds` random seeds within the specified dimensions.
-    fn populate_seeds(&mut self, n_seeds: usize) {
+    fn populate_seeds(&mut self, rng: &mut RandomNumberGenerator, n_seeds: usize) {
         while self.seeds.len() < n_seeds {
-            let vx = self.rng.roll_dice(1, self.width - 1);
-            let vy = self.rng.roll_dice(1, self.height - 1);
+            let vx = rng.roll_dice(1, self.width - 1);
+            let vy = rng.roll_dice(1, self.height - 1);
             let vidx = self.xy_idx(vx, vy);
 
             let candidate = (vidx, rltk::Point::new(vx, vy));
@@ -0,0 +1,51 @@
+use rltk::RandomNumberGenerator;
+
+use crate::{BuildData, MetaMapBuilder, TileType};
+
+/// Noise below this floods a floor tile with shallow water.
+const WATER_LEVEL: f32 = -0.45;
+/// Noise below this (but above [`WATER_LEVEL`]) turns a floor tile to mud.
+const MUD_LEVEL: f32 = -0.3;
+
+/// Floods the low-lying parts of a level with shallow water, ringed by mud.
+///
+/// "Low-lying" comes from a simplex noise height map, so pools form in patches rather than
+/// being scattered tile by tile. The starting tile is always left dry.
+pub struct WaterBuilder {}
+
+impl MetaMapBuilder for WaterBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        self.build(rng, build_data);
+    }
+}
+
+impl WaterBuilder {
+    pub fn new() -> Box<WaterBuilder> {
+        Box::new(WaterBuilder {})
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuildData) {
+        let mut noise = rltk::FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
+        noise.set_noise_type(rltk::NoiseType::Simplex);
+        noise.set_frequency(0.08);
+
+        let start_idx = build_data
+            .start
+            .as_ref()
+            .map(|start| build_data.map.xy_idx(start.x, start.y));
+
+        for (x, y) in build_data.map.iter_xy() {
+            let idx = build_data.map.xy_idx(x, y);
+            if build_data.map.tiles[idx] != TileType::Floor || Some(idx) == start_idx {
+                continue;
+            }
+            let height = noise.get_noise(x as f32, y as f32);
+            if height < WATER_LEVEL {
+                build_data.map.tiles[idx] = TileType::ShallowWater;
+            } else if height < MUD_LEVEL {
+                build_data.map.tiles[idx] = TileType::Mud;
+            }
+        }
+        build_data.take_snapshot();
+    }
+}
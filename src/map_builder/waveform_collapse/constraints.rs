@@ -3,6 +3,7 @@ use std::collections::HashSet;
 use crate::{Map, Position, TileType};
 
 use super::common::{Direction, MapChunk};
+use crate::map_builder::common::reflect;
 
 pub fn build_patterns(
     map: &Map,
@@ -30,8 +31,10 @@ pub fn build_patterns(
 
     if dedupe {
         rltk::console::log(format!("Pre-dedupe: {} patterns", patterns.len()));
-        let set: HashSet<Vec<TileType>> = patterns.drain(..).collect();
-        patterns.extend(set.into_iter());
+        // Keep the first copy of each pattern, in order, so the same map always gives the same
+        // constraints.
+        let mut seen: HashSet<Vec<TileType>> = HashSet::new();
+        patterns.retain(|pattern| seen.insert(pattern.clone()));
         rltk::console::log(format!("Post-dedupe: {} patterns", patterns.len()))
     }
 
@@ -208,7 +211,7 @@ impl BuildPattern {
     fn flip_horizontal(&self, map: &Map) -> BuildPattern {
         let mut flipped = BuildPattern::new(self.chunk);
         for pos in flipped.chunk.into_iter() {
-            let idx = map.xy_idx(self.chunk.end.x - (pos.x + 1), pos.y);
+            let idx = map.xy_idx(reflect(pos.x, self.chunk.end.x), pos.y);
             flipped.add_tile(map.tiles[idx]);
         }
         flipped
@@ -217,7 +220,7 @@ impl BuildPattern {
     fn flip_vertical(&self, map: &Map) -> BuildPattern {
         let mut flipped = BuildPattern::new(self.chunk);
         for pos in flipped.chunk.into_iter() {
-            let idx = map.xy_idx(pos.x, self.chunk.end.y - (pos.y + 1));
+            let idx = map.xy_idx(pos.x, reflect(pos.y, self.chunk.end.y));
             flipped.add_tile(map.tiles[idx]);
         }
         flipped
@@ -227,8 +230,8 @@ impl BuildPattern {
         let mut flipped = BuildPattern::new(self.chunk);
         for pos in flipped.chunk.into_iter() {
             let idx = map.xy_idx(
-                self.chunk.end.x - (pos.x + 1),
-                self.chunk.end.y - (pos.y + 1),
+                reflect(pos.x, self.chunk.end.x),
+                reflect(pos.y, self.chunk.end.y),
             );
             flipped.add_tile(map.tiles[idx]);
         }
@@ -1,7 +1,12 @@
 use crate::{Map, TileType};
 
+/// Loads a map the size of the first layer of `xp_file`.
 pub fn load_rex_map(new_depth: i32, xp_file: &rltk::XpFile) -> Map {
-    let mut map: Map = Map::new(new_depth);
+    let (width, height) = xp_file
+        .layers
+        .first()
+        .map_or((0, 0), |layer| (layer.width as i32, layer.height as i32));
+    let mut map: Map = Map::new(new_depth, width, height);
 
     for layer in &xp_file.layers {
         for y in 0..layer.height {
@@ -6,13 +6,18 @@ use image_loader::load_rex_map;
 use rltk::RandomNumberGenerator;
 use solver::Solver;
 
-use crate::{spawner, BuildData, Map, MetaMapBuilder, Position, TileType, SHOW_MAPGEN_VISUALIZER};
+use crate::{spawner, BuildData, InitialMapBuilder, Map, MetaMapBuilder, Position, TileType};
+
+use super::cellular_automata::CellularAutomataBuilder;
 
 mod common;
 mod constraints;
 mod image_loader;
 mod solver;
 
+/// How many times the solver may paint itself into a corner before we give up on it.
+const MAX_SOLVE_ATTEMPTS: usize = 10;
+
 #[derive(PartialEq, Clone, Copy)]
 pub enum WaveformMode {
     TestMap,
@@ -39,18 +44,61 @@ impl WaveformCollapseBuilder {
         let constraints = patterns_to_constraints(patterns, CHUNK_SIZE);
         self.render_tile_gallery(&constraints, CHUNK_SIZE, build_data);
 
-        build_data.map = Map::new(build_data.map.depth);
-        loop {
-            let mut solver = Solver::new(constraints.clone(), CHUNK_SIZE, &build_data.map);
+        Self::collapse(&constraints, CHUNK_SIZE, rng, build_data);
+    }
+
+    /// Fills a blank map from `constraints`, or with caves if they can't be made to fit.
+    fn collapse(
+        constraints: &[MapChunk],
+        chunk_size: i32,
+        rng: &mut RandomNumberGenerator,
+        build_data: &mut BuildData,
+    ) {
+        let (width, height) = (build_data.map.width, build_data.map.height);
+        build_data.map = Map::new(build_data.map.depth, width, height);
+        if !Self::solve(constraints, chunk_size, rng, build_data) {
+            // Some source maps just don't produce patterns that fit together; fall back to caves
+            // rather than retrying forever.
+            build_data.map = Map::new(build_data.map.depth, width, height);
+            build_data.spawn_list.clear();
+            <CellularAutomataBuilder as InitialMapBuilder>::build_map(
+                &mut CellularAutomataBuilder {},
+                rng,
+                build_data,
+            );
+        }
+    }
+
+    /// Runs the solver until it fills the map, returning `false` if every attempt hit a chunk with
+    /// no compatible pattern.
+    fn solve(
+        constraints: &[MapChunk],
+        chunk_size: i32,
+        rng: &mut RandomNumberGenerator,
+        build_data: &mut BuildData,
+    ) -> bool {
+        for attempt in 1..=MAX_SOLVE_ATTEMPTS {
+            let mut solver = Solver::new(constraints.to_vec(), chunk_size, &build_data.map);
             while !solver.iteration(&mut build_data.map, rng) {
                 build_data.take_snapshot();
             }
             build_data.take_snapshot();
             if solver.possible {
-                break;
+                if attempt > 1 {
+                    rltk::console::log(format!(
+                        "Waveform collapse solved after {} retries",
+                        attempt - 1
+                    ));
+                }
+                return true;
             }
             build_data.spawn_list.clear();
         }
+        rltk::console::log(format!(
+            "Waveform collapse failed after {} retries; falling back to cellular automata",
+            MAX_SOLVE_ATTEMPTS - 1
+        ));
+        false
     }
 
     fn render_tile_gallery(
@@ -59,7 +107,8 @@ impl WaveformCollapseBuilder {
         chunk_size: i32,
         build_data: &mut BuildData,
     ) {
-        build_data.map = Map::new(0);
+        let (width, height) = (build_data.map.width, build_data.map.height);
+        build_data.map = Map::new(0, width, height);
         let mut ctr = 0;
         let mut x = 1;
         let mut y = 1;
@@ -78,7 +127,7 @@ impl WaveformCollapseBuilder {
                 if y + chunk_size > build_data.map.height {
                     // Move to the next page
                     build_data.take_snapshot();
-                    build_data.map = Map::new(0);
+                    build_data.map = Map::new(0, width, height);
 
                     x = 1;
                     y = 1;
@@ -89,3 +138,45 @@ impl WaveformCollapseBuilder {
         build_data.take_snapshot();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuilderChain;
+
+    /// Patterns that can't sit next to anything, so no map with more than one chunk is solvable.
+    fn incompatible_chunks(chunk_size: i32) -> Vec<MapChunk> {
+        [TileType::Floor, TileType::Wall]
+            .iter()
+            .map(|tile| MapChunk {
+                pattern: vec![*tile; (chunk_size * chunk_size) as usize],
+                exits: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
+                has_exits: false,
+                compatible_with: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn impossible_constraints_give_up() {
+        let mut rng = RandomNumberGenerator::seeded(3);
+        let mut build_data = BuilderChain::new(1, 80, 50).build_data;
+        build_data.record_history = false;
+        assert!(!WaveformCollapseBuilder::solve(
+            &incompatible_chunks(7),
+            7,
+            &mut rng,
+            &mut build_data
+        ));
+    }
+
+    #[test]
+    fn impossible_constraints_fall_back_to_caves() {
+        let mut rng = RandomNumberGenerator::seeded(3);
+        let mut build_data = BuilderChain::new(1, 80, 50).build_data;
+        build_data.record_history = false;
+        WaveformCollapseBuilder::collapse(&incompatible_chunks(7), 7, &mut rng, &mut build_data);
+        assert!(build_data.map.smooth_floors);
+        assert!(build_data.map.tiles.contains(&TileType::Floor));
+    }
+}
@@ -90,6 +90,10 @@ impl Solver {
                 }
             }
 
+            // `to_check` iterates in no particular order; sort so the roll picks the same option
+            // every time for a given seed.
+            possible_options.sort_unstable();
+
             if possible_options.is_empty() {
                 rltk::console::log("Impossible!");
                 self.possible = false;
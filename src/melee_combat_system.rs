@@ -1,104 +1,540 @@
-use super::{components::*, gamelog::GameLog, particle_system::ParticleBuilder, Position};
-use rltk::{BLACK, ORANGE, RGB};
+use super::{
+    components::*,
+    gamelog::{GameLog, TurnCounter},
+    hunger_system::hunger_effects,
+    particle_system::ParticleBuilder,
+    spawner::SpawnRequests,
+    Map, Position, TileType,
+};
+use rltk::{Point, BLACK, ORANGE, RGB};
 use specs::prelude::*;
 
+/// Melee power bonus (or penalty) `ent` gets from its hunger state.
+pub fn hunger_bonus(ent: Entity, hunger_clock: &ReadStorage<HungerClock>) -> i32 {
+    hunger_clock
+        .get(ent)
+        .map_or(0, |hc| hunger_effects(hc.state).melee_bonus)
+}
+
+/// Total melee power bonus `ent` gets from its equipped items and its hunger state.
+///
+/// Ranged weapons don't help with bump attacks, so only melee [`Weapon`]s count.
+pub fn offense_bonus(
+    ent: Entity,
+    melee_power_bonuses: &ReadStorage<MeleePowerBonus>,
+    weapons: &ReadStorage<Weapon>,
+    equipped: &ReadStorage<Equipped>,
+    hunger_clock: &ReadStorage<HungerClock>,
+) -> i32 {
+    // Get the offensive bonus offered by equipped items.
+    let mut bonus: i32 = (melee_power_bonuses, equipped)
+        .join()
+        .filter(|(_, equipped_by)| equipped_by.owner == ent)
+        .map(|(p, _)| p)
+        .fold(0, |acc, item| acc + item.power);
+    bonus += (weapons, equipped)
+        .join()
+        .filter(|(weapon, equipped_by)| {
+            equipped_by.owner == ent && weapon.attack_kind == AttackKind::Melee
+        })
+        .map(|(weapon, _)| weapon.damage)
+        .sum::<i32>();
+
+    // Being well fed (or starving) changes how hard you hit.
+    bonus += hunger_bonus(ent, hunger_clock);
+
+    bonus
+}
+
+/// Total defense bonus `ent` gets from its equipped items.
+pub fn defense_bonus(
+    ent: Entity,
+    defense_bonuses: &ReadStorage<DefenseBonus>,
+    equipped: &ReadStorage<Equipped>,
+) -> i32 {
+    (defense_bonuses, equipped)
+        .join()
+        .filter(|(_, equipped_by)| equipped_by.owner == ent)
+        .map(|(d, _)| d)
+        .fold(0, |acc, item| acc + item.defense)
+}
+
+/// Equipped gear of `owner`'s, among `items`, that wears down as it's used.
+fn wearing_gear<T: Component>(
+    entities: &Entities,
+    owner: Entity,
+    items: &ReadStorage<T>,
+    equipped: &ReadStorage<Equipped>,
+    durability: &WriteStorage<Durability>,
+) -> Vec<Entity> {
+    (entities, items, equipped, durability)
+        .join()
+        .filter(|(_, _, equipped_by, _)| equipped_by.owner == owner)
+        .map(|(item, _, _, _)| item)
+        .collect()
+}
+
+/// Finds an open floor tile next to `pos` for a split-off monster, if there is one.
+fn split_destination(map: &Map, pos: &Position) -> Option<(i32, i32)> {
+    for (dx, dy) in [
+        (-1, 0),
+        (1, 0),
+        (0, -1),
+        (0, 1),
+        (-1, -1),
+        (1, -1),
+        (-1, 1),
+        (1, 1),
+    ]
+    .iter()
+    {
+        let (x, y) = (pos.x + dx, pos.y + dy);
+        if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+            continue;
+        }
+        let idx = map.xy_idx(x, y);
+        if map.tiles[idx].is_walkable() && !map.blocked[idx] {
+            return Some((x, y));
+        }
+    }
+    None
+}
+
+/// Extra damage taken by a knocked-back target that hits something on the way.
+const KNOCKBACK_IMPACT_DAMAGE: i32 = 2;
+
+/// How far `ent`'s blows knock their target back: the furthest of its own [`Knockback`] and
+/// that of anything it has equipped.
+fn knockback_distance(
+    ent: Entity,
+    knockbacks: &ReadStorage<Knockback>,
+    equipped: &ReadStorage<Equipped>,
+) -> i32 {
+    (knockbacks, equipped)
+        .join()
+        .filter(|(_, equipped_by)| equipped_by.owner == ent)
+        .map(|(k, _)| k.distance)
+        .chain(knockbacks.get(ent).map(|k| k.distance))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Where a target at `to`, hit from `from`, ends up after being knocked back up to
+/// `distance` tiles, and the tile it slammed into if something stopped it early.
+fn knockback_path(map: &Map, from: Point, to: Point, distance: i32) -> (Point, Option<usize>) {
+    let (dx, dy) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+    let mut landing = to;
+    for _ in 0..distance {
+        let (x, y) = (landing.x + dx, landing.y + dy);
+        let next = match map.try_xy_idx(x, y) {
+            Some(next) => next,
+            None => break,
+        };
+        let diagonal = dx != 0 && dy != 0;
+        if map.blocked[next] || (diagonal && !map.is_diagonal_open(landing.x, landing.y, dx, dy)) {
+            return (landing, Some(next));
+        }
+        landing = Point::new(x, y);
+    }
+    (landing, None)
+}
+
 /// Handle for our melee combat system.
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         Entities<'a>,
         WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
-        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
         ReadStorage<'a, MeleePowerBonus>,
         ReadStorage<'a, DefenseBonus>,
         ReadStorage<'a, Equipped>,
         WriteExpect<'a, ParticleBuilder>,
-        ReadStorage<'a, Position>,
+        WriteStorage<'a, Position>,
         ReadStorage<'a, HungerClock>,
+        ReadStorage<'a, Monster>,
+        ReadStorage<'a, LifeDrain>,
+        ReadStorage<'a, StatDrain>,
+        ReadStorage<'a, SplitOnHit>,
+        WriteExpect<'a, SpawnRequests>,
+        WriteExpect<'a, Map>,
+        WriteStorage<'a, Durability>,
+        ReadExpect<'a, Entity>,
+        (
+            ReadStorage<'a, Knockback>,
+            ReadStorage<'a, Weapon>,
+            WriteStorage<'a, EntityMoved>,
+            WriteStorage<'a, Viewshed>,
+            WriteExpect<'a, Point>,
+        ),
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        #[allow(clippy::type_complexity)]
         let (
             entities,
             mut log,
+            turn_counter,
             mut melee,
             names,
-            combat_stats,
+            mut combat_stats,
             mut inflict_damage,
             melee_power_bonuses,
             defense_bonuses,
             equipped,
             mut particle_builder,
-            positions,
+            mut positions,
             hunger_clock,
+            monsters,
+            life_drains,
+            stat_drains,
+            splits,
+            mut spawn_requests,
+            mut map,
+            mut durability,
+            player_ent,
+            (knockbacks, weapons, mut entity_moved, mut viewsheds, mut player_pos),
         ) = data;
 
+        // Special attack effects, applied once every attack has been worked out.
+        let mut heals: Vec<(Entity, i32)> = Vec::new();
+        let mut max_hp_drains: Vec<(Entity, i32)> = Vec::new();
+        // Gear that took a knock, once per blow it was involved in.
+        let mut worn: Vec<(Entity, Entity)> = Vec::new();
+        // Targets to shove: who hit them, who they are and how far they go.
+        let mut knocked: Vec<(Entity, Entity, i32)> = Vec::new();
+
         for (ent, wants_melee, name, stats) in (&entities, &melee, &names, &combat_stats).join() {
             // If no HP, combat doesn't make much sense does it
             if stats.hp > 0 {
-                // Get the offensive bonus offered by equipped items.
-                let mut offense_bonus: i32 = (&melee_power_bonuses, &equipped)
-                    .join()
-                    .filter(|(_, equipped_by)| equipped_by.owner == ent)
-                    .map(|(p, _)| p)
-                    .fold(0, |acc, item| acc + item.power);
-
-                // Give a power bonus for being well fed.
-                if let Some(hc) = hunger_clock.get(ent) {
-                    if hc.state == HungerState::WellFed {
-                        offense_bonus += 1;
-                    }
-                }
+                let offense_bonus = offense_bonus(
+                    ent,
+                    &melee_power_bonuses,
+                    &weapons,
+                    &equipped,
+                    &hunger_clock,
+                );
 
-                let target_stats = combat_stats.get(wants_melee.target).unwrap();
-                if target_stats.hp > 0 {
-                    // Get defense bonus offered by equipped items.
-                    let defense_bonus: i32 = (&defense_bonuses, &equipped)
-                        .join()
-                        .filter(|(_, equipped_by)| equipped_by.owner == wants_melee.target)
-                        .map(|(d, _)| d)
-                        .fold(0, |acc, item| acc + item.defense);
-
-                    // Render some particles to denote combat is ongoing.
-                    if let Some(pos) = positions.get(wants_melee.target) {
-                        particle_builder.request(
-                            pos.x,
-                            pos.y,
-                            RGB::named(ORANGE),
-                            RGB::named(BLACK),
-                            rltk::to_cp437('‼'),
-                            200.0,
+                for target in wants_melee.targets.iter() {
+                    let target_stats = match combat_stats.get(*target) {
+                        Some(target_stats) => target_stats,
+                        None => continue,
+                    };
+                    if target_stats.hp > 0 {
+                        let defense_bonus = defense_bonus(*target, &defense_bonuses, &equipped);
+
+                        // Every blow wears on the weapon swung and the armor it lands on.
+                        for item in wearing_gear(&entities, ent, &weapons, &equipped, &durability)
+                            .into_iter()
+                            .filter(|item| {
+                                weapons.get(*item).unwrap().attack_kind == AttackKind::Melee
+                            })
+                        {
+                            worn.push((ent, item));
+                        }
+                        for item in wearing_gear(
+                            &entities,
+                            *target,
+                            &defense_bonuses,
+                            &equipped,
+                            &durability,
+                        ) {
+                            worn.push((*target, item));
+                        }
+
+                        // Render some particles to denote combat is ongoing.
+                        if let Some(pos) = positions.get(*target) {
+                            particle_builder.request(
+                                pos.x,
+                                pos.y,
+                                RGB::named(ORANGE),
+                                RGB::named(BLACK),
+                                rltk::to_cp437('‼'),
+                                200.0,
+                            );
+                        }
+
+                        // Calculate damage, accounting for equipment bonuses.
+                        let damage = i32::max(
+                            0,
+                            (stats.power + offense_bonus) - (target_stats.defense + defense_bonus),
                         );
-                    }
 
-                    // Calculate damage, accounting for equipment bonuses.
-                    let damage = i32::max(
-                        0,
-                        (stats.power + offense_bonus) - (target_stats.defense + defense_bonus),
-                    );
-
-                    // Deal the damage and write it to the log.
-                    let target_name = names.get(wants_melee.target).unwrap();
-                    if damage == 0 {
-                        log.entries.push(format!(
-                            "{} is left unscathed from {}'s attack!",
-                            &target_name.name, &name.name
-                        ));
-                    } else {
-                        log.entries.push(format!(
-                            "{} hits {} for {} hp.",
-                            &name.name, &target_name.name, damage
-                        ));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                        // Let the player know when hunger made the difference.
+                        let hunger = hunger_bonus(ent, &hunger_clock);
+                        let unfed_damage = i32::max(
+                            0,
+                            (stats.power + offense_bonus - hunger)
+                                - (target_stats.defense + defense_bonus),
+                        );
+                        if damage > unfed_damage {
+                            log.push(
+                                turn_counter.turn,
+                                format!("Well fed, {} strikes with extra force.", &name.name),
+                            );
+                        } else if damage < unfed_damage {
+                            log.push(
+                                turn_counter.turn,
+                                format!("Weak from hunger, {}'s blow falters.", &name.name),
+                            );
+                        }
+
+                        // Deal the damage and write it to the log.
+                        let target_name = names.get(*target).unwrap();
+                        if damage == 0 {
+                            log.push(
+                                turn_counter.turn,
+                                format!(
+                                    "{} is left unscathed from {}'s attack!",
+                                    &target_name.name, &name.name
+                                ),
+                            );
+                        } else {
+                            log.push(
+                                turn_counter.turn,
+                                format!(
+                                    "{} hits {} for {} {} damage.",
+                                    &name.name,
+                                    &target_name.name,
+                                    damage,
+                                    DamageType::Physical.name()
+                                ),
+                            );
+                            SufferDamage::new_damage(
+                                &mut inflict_damage,
+                                *target,
+                                damage,
+                                DamageType::Physical,
+                            );
+
+                            // Special attacks only come from monsters.
+                            if monsters.contains(ent) {
+                                if let Some(drain) = life_drains.get(ent) {
+                                    let healed = (damage as f32 * drain.fraction) as i32;
+                                    if healed > 0 {
+                                        heals.push((ent, healed));
+                                        log.push(
+                                            turn_counter.turn,
+                                            format!(
+                                                "{} drains {} life from {}!",
+                                                &name.name, healed, &target_name.name
+                                            ),
+                                        );
+                                    }
+                                }
+                                if let Some(drain) = stat_drains.get(ent) {
+                                    max_hp_drains.push((*target, drain.amount));
+                                    log.push(
+                                        turn_counter.turn,
+                                        format!(
+                                            "{}'s touch saps {}'s vitality!",
+                                            &name.name, &target_name.name
+                                        ),
+                                    );
+                                }
+                            }
+
+                            // Heavy blows knock survivors back.
+                            let distance = knockback_distance(ent, &knockbacks, &equipped);
+                            if distance > 0 && target_stats.hp > damage {
+                                knocked.push((ent, *target, distance));
+                            }
+
+                            // Splitters bud off a smaller copy when they survive a hit.
+                            if let (Some(split), Some(pos)) =
+                                (splits.get(*target), positions.get(*target))
+                            {
+                                if monsters.contains(*target) && target_stats.hp > damage {
+                                    if let Some((x, y)) = split_destination(&map, pos) {
+                                        let idx = map.xy_idx(x, y);
+                                        map.blocked[idx] = true;
+                                        spawn_requests.request(x, y, &split.spawn);
+                                        log.push(
+                                            turn_counter.turn,
+                                            format!("The {} splits in two!", &target_name.name),
+                                        );
+                                    }
+                                }
+                            }
+                        }
                     }
                 }
             }
         }
         melee.clear();
+
+        for (attacker, target, distance) in knocked.iter() {
+            let (from, to) = match (positions.get(*attacker), positions.get(*target)) {
+                (Some(from), Some(to)) => (Point::new(from.x, from.y), Point::new(to.x, to.y)),
+                _ => continue,
+            };
+            let (landing, impact) = knockback_path(&map, from, to, *distance);
+            let target_name = &names.get(*target).unwrap().name;
+
+            if landing != to {
+                let (old_idx, new_idx) = (map.xy_idx(to.x, to.y), map.xy_idx(landing.x, landing.y));
+                map.blocked[old_idx] = false;
+                map.blocked[new_idx] = true;
+                map.tile_content[old_idx].retain(|ent| ent != target);
+                map.tile_content[new_idx].push(*target);
+                if let Some(pos) = positions.get_mut(*target) {
+                    pos.x = landing.x;
+                    pos.y = landing.y;
+                }
+                if let Some(viewshed) = viewsheds.get_mut(*target) {
+                    viewshed.dirty = true;
+                }
+                if *target == *player_ent {
+                    *player_pos = landing;
+                }
+                // Counts as moving, so any trap it lands on goes off.
+                entity_moved
+                    .insert(*target, EntityMoved {})
+                    .expect("Unable to insert marker");
+                log.push(
+                    turn_counter.turn,
+                    format!("{} is knocked back.", target_name),
+                );
+            }
+
+            if let Some(impact) = impact {
+                let obstacle = if map.tiles[impact] == TileType::Wall {
+                    "the wall".to_string()
+                } else {
+                    map.tile_content[impact]
+                        .iter()
+                        .find_map(|ent| names.get(*ent))
+                        .map_or("something".to_string(), |name| name.name.clone())
+                };
+                log.push(
+                    turn_counter.turn,
+                    format!(
+                        "{} slams into {}, taking {} damage.",
+                        target_name, obstacle, KNOCKBACK_IMPACT_DAMAGE
+                    ),
+                );
+                SufferDamage::new_damage(
+                    &mut inflict_damage,
+                    *target,
+                    KNOCKBACK_IMPACT_DAMAGE,
+                    DamageType::Physical,
+                );
+            }
+        }
+
+        for (ent, amount) in heals.iter() {
+            if let Some(stats) = combat_stats.get_mut(*ent) {
+                stats.hp = i32::min(stats.max_hp, stats.hp + amount);
+            }
+        }
+        for (ent, amount) in max_hp_drains.iter() {
+            if let Some(stats) = combat_stats.get_mut(*ent) {
+                stats.max_hp = i32::max(1, stats.max_hp - amount);
+                stats.hp = i32::min(stats.hp, stats.max_hp);
+            }
+        }
+
+        for (owner, item) in worn.iter() {
+            let broken = match durability.get_mut(*item) {
+                Some(item_durability) if item_durability.current > 0 => {
+                    item_durability.current -= 1;
+                    item_durability.current == 0
+                }
+                _ => false,
+            };
+            if !broken {
+                continue;
+            }
+            // Deleting the item takes it out of its owner's equipment too.
+            let item_name = &names.get(*item).unwrap().name;
+            if *owner == *player_ent {
+                log.push(turn_counter.turn, format!("Your {} breaks!", item_name));
+            } else if let Some(owner_name) = names.get(*owner) {
+                log.push(
+                    turn_counter.turn,
+                    format!("{}'s {} breaks!", &owner_name.name, item_name),
+                );
+            }
+            entities
+                .delete(*item)
+                .expect("Unable to delete broken item");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fighter(ecs: &mut World, name: &str, x: i32, y: i32) -> Entity {
+        ecs.create_entity()
+            .with(Name {
+                name: name.to_string(),
+            })
+            .with(Position { x, y })
+            .with(CombatStats {
+                max_hp: 30,
+                hp: 30,
+                defense: 0,
+                power: 5,
+            })
+            .build()
+    }
+
+    #[test]
+    fn knockback_stops_at_walls() {
+        let (map, _) = Map::from_ascii("######\n#....#\n######").unwrap();
+        let from = Point::new(1, 1);
+
+        assert!(knockback_path(&map, from, Point::new(2, 1), 2) == (Point::new(4, 1), None));
+        assert!(
+            knockback_path(&map, from, Point::new(3, 1), 3)
+                == (Point::new(4, 1), Some(map.xy_idx(5, 1)))
+        );
+    }
+
+    #[test]
+    fn heavy_blows_shove_the_target_into_the_wall() {
+        let mut ecs = World::new();
+        crate::register_components(&mut ecs);
+        let (mut map, _) = Map::from_ascii("######\n#....#\n######").unwrap();
+        map.populate_blocked();
+        ecs.insert(map);
+        ecs.insert(GameLog::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(ParticleBuilder::new());
+        ecs.insert(SpawnRequests::default());
+        ecs.insert(Point::new(1, 1));
+
+        let ogre = fighter(&mut ecs, "Ogre", 1, 1);
+        ecs.write_storage::<Knockback>()
+            .insert(ogre, Knockback { distance: 5 })
+            .unwrap();
+        let victim = fighter(&mut ecs, "Player", 2, 1);
+        ecs.insert(victim);
+        ecs.write_storage::<WantsToMelee>()
+            .insert(
+                ogre,
+                WantsToMelee {
+                    targets: vec![victim],
+                },
+            )
+            .unwrap();
+
+        MeleeCombatSystem {}.run_now(&ecs);
+
+        let positions = ecs.read_storage::<Position>();
+        let landed = positions.get(victim).unwrap();
+        assert_eq!((landed.x, landed.y), (4, 1));
+        assert_eq!(*ecs.fetch::<Point>(), Point::new(4, 1));
+        assert!(ecs.read_storage::<EntityMoved>().contains(victim));
+        // The blow itself plus slamming into the wall.
+        let damage = ecs.read_storage::<SufferDamage>();
+        assert_eq!(damage.get(victim).unwrap().amount.len(), 2);
     }
 }
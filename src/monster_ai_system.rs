@@ -1,7 +1,223 @@
-use super::{components::*, particle_system::ParticleBuilder, Map, RunState};
-use rltk::{Point, BLACK, MAGENTA, RGB};
+use super::{
+    components::*,
+    gamelog::{GameLog, TurnCounter},
+    particle_system::ParticleBuilder,
+    player::PlayerStepped,
+    scent_system::ScentMap,
+    spawner::SpawnRequests,
+    Map, RunState, TileType,
+};
+use rltk::{BaseMap, DijkstraMap, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
+/// How far out flee maps are calculated.
+const FLEE_DEPTH: f32 = 100.0;
+/// How many turns a monster keeps hunting after losing sight of the player.
+const HUNT_MEMORY_TURNS: i32 = 8;
+/// How many turns a monster searches around where it lost the player before giving up.
+const SEARCH_TURNS: i32 = 4;
+/// Furthest (in each axis) from where the player was lost that a monster will search.
+const SEARCH_RADIUS: i32 = 2;
+/// How long the `!`/`?` shown over a monster spotting or losing the player lasts, in ms.
+const NOTICE_PARTICLE_LIFETIME: f32 = 400.0;
+/// Idle monsters have a 1 in `WANDER_CHANCE` chance each turn to start wandering.
+const WANDER_CHANCE: i32 = 6;
+/// Furthest (in each axis) a monster will pick a wander destination.
+const WANDER_RADIUS: i32 = 6;
+/// Most minions a summoner calls in at once.
+const MINIONS_PER_SUMMON: usize = 2;
+/// Energy gained per turn at the player's speed, and the energy each action costs.
+pub const NORMAL_SPEED: i32 = 100;
+
+/// Gives every monster its turn's worth of energy, once the player has acted.
+///
+/// A player wading through water or mud takes longer about it, so monsters get more energy.
+/// Monsters from before speeds existed are given the normal speed.
+pub fn grant_energy(ecs: &mut World) {
+    let entities = ecs.entities();
+    let monsters = ecs.read_storage::<Monster>();
+    let mut speeds = ecs.write_storage::<Speed>();
+    // Only a step onto slow ground takes longer; standing in it to fight or wait doesn't.
+    let stepped = std::mem::take(&mut ecs.write_resource::<PlayerStepped>().stepped);
+    let turn_length = if stepped {
+        let map = ecs.fetch::<Map>();
+        let player_pos = ecs.fetch::<Point>();
+        map.tiles[map.xy_idx(player_pos.x, player_pos.y)].move_cost()
+    } else {
+        1.0
+    };
+
+    for (ent, _) in (&entities, &monsters).join() {
+        match speeds.get_mut(ent) {
+            Some(speed) => speed.energy += (speed.speed as f32 * turn_length) as i32,
+            None => {
+                speeds
+                    .insert(
+                        ent,
+                        Speed {
+                            speed: NORMAL_SPEED,
+                            energy: NORMAL_SPEED,
+                        },
+                    )
+                    .expect("Unable to insert speed");
+            }
+        }
+    }
+}
+
+/// True if any monster still has enough energy to act again this turn.
+pub fn monsters_ready(ecs: &World) -> bool {
+    let monsters = ecs.read_storage::<Monster>();
+    let speeds = ecs.read_storage::<Speed>();
+    (&monsters, &speeds)
+        .join()
+        .any(|(_, speed)| speed.energy >= NORMAL_SPEED)
+}
+
+/// Builds a map for running away from `threat_idx`.
+///
+/// The distance map to the threat is scaled by -1.2 and re-propagated, so rolling downhill
+/// leads away from the threat while still preferring open space over dead ends.
+fn flee_map(map: &Map, threat_idx: usize) -> DijkstraMap {
+    let mut flee = DijkstraMap::new(
+        map.width as usize,
+        map.height as usize,
+        &[threat_idx],
+        map,
+        FLEE_DEPTH,
+    );
+    flee.map
+        .iter_mut()
+        .filter(|v| **v != std::f32::MAX)
+        .for_each(|v| *v *= -1.2);
+
+    // Relax until stable so the gradient flows smoothly around corners.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in 0..flee.map.len() {
+            if flee.map[idx] == std::f32::MAX {
+                continue;
+            }
+            for (exit, cost) in map.get_available_exits(idx).iter() {
+                let candidate = flee.map[*exit] + cost;
+                if candidate < flee.map[idx] {
+                    flee.map[idx] = candidate;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    flee
+}
+
+/// Finds the next step on an A* path from `start_idx` to `end_idx`, if there is one.
+fn path_step(map: &mut Map, start_idx: usize, end_idx: usize) -> Option<usize> {
+    let path = rltk::a_star_search(start_idx as i32, end_idx as i32, &mut *map);
+    // `steps[0]` is the current position, so take the next step.
+    if path.success && path.steps.len() > 1 {
+        Some(path.steps[1])
+    } else {
+        None
+    }
+}
+
+/// Picks a random open floor tile within `radius` of `pos` to wander to.
+fn wander_destination(
+    map: &Map,
+    pos: &Position,
+    radius: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Option<usize> {
+    for _ in 0..10 {
+        let x = pos.x + rng.range(-radius, radius + 1);
+        let y = pos.y + rng.range(-radius, radius + 1);
+        if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+            continue;
+        }
+        let idx = map.xy_idx(x, y);
+        if map.tiles[idx].is_walkable() && !map.blocked[idx] {
+            return Some(idx);
+        }
+    }
+    None
+}
+
+/// Pops up `glyph` just above a monster, to show it noticing (or losing) the player.
+fn notice(particles: &mut ParticleBuilder, pos: &Position, glyph: char, color: (u8, u8, u8)) {
+    particles.request(
+        pos.x,
+        pos.y - 1,
+        rltk::RGB::named(color),
+        rltk::RGB::named(rltk::BLACK),
+        rltk::to_cp437(glyph),
+        NOTICE_PARTICLE_LIFETIME,
+    );
+}
+
+/// Moves a monster to the tile at `dest_idx`, keeping the map's blocking info up to date.
+fn step_to(
+    map: &mut Map,
+    pos: &mut Position,
+    viewshed: &mut Viewshed,
+    speed: Option<&mut Speed>,
+    dest_idx: usize,
+) {
+    let mut idx = map.xy_idx(pos.x, pos.y);
+    map.blocked[idx] = false;
+    pos.x = dest_idx as i32 % map.width;
+    pos.y = dest_idx as i32 / map.width;
+    idx = map.xy_idx(pos.x, pos.y);
+    map.blocked[idx] = true;
+    viewshed.dirty = true;
+
+    // Slow ground eats into the energy for the monster's next action.
+    if let Some(speed) = speed {
+        speed.energy -= ((map.tiles[idx].move_cost() - 1.0) * NORMAL_SPEED as f32) as i32;
+    }
+}
+
+/// Requests up to [`MINIONS_PER_SUMMON`] `minion`s on open tiles next to `pos`.
+///
+/// Returns how many were summoned.
+fn summon_minions(
+    map: &mut Map,
+    pos: &Position,
+    minion: &str,
+    spawns: &mut SpawnRequests,
+) -> usize {
+    let mut summoned = 0;
+    for (dx, dy) in [
+        (-1, 0),
+        (1, 0),
+        (0, -1),
+        (0, 1),
+        (-1, -1),
+        (1, -1),
+        (-1, 1),
+        (1, 1),
+    ]
+    .iter()
+    {
+        if summoned >= MINIONS_PER_SUMMON {
+            break;
+        }
+        let (x, y) = (pos.x + dx, pos.y + dy);
+        if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+            continue;
+        }
+        let idx = map.xy_idx(x, y);
+        if map.tiles[idx].is_walkable() && !map.blocked[idx] {
+            // Claim the tile so the next minion doesn't land on top of this one.
+            map.blocked[idx] = true;
+            spawns.request(x, y, minion);
+            summoned += 1;
+        }
+    }
+    summoned
+}
+
 pub struct MonsterAI {}
 
 impl<'a> System<'a> for MonsterAI {
@@ -17,11 +233,25 @@ impl<'a> System<'a> for MonsterAI {
         Entities<'a>,
         WriteStorage<'a, Viewshed>,
         ReadStorage<'a, Monster>,
+        ReadStorage<'a, Faction>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
         WriteStorage<'a, Confusion>,
-        WriteExpect<'a, ParticleBuilder>,
         WriteStorage<'a, EntityMoved>,
+        ReadStorage<'a, Alerted>,
+        ReadStorage<'a, CanFlee>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, AiState>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, SummonsMinions>,
+        WriteExpect<'a, SpawnRequests>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
+        WriteStorage<'a, Speed>,
+        WriteExpect<'a, ParticleBuilder>,
+        ReadStorage<'a, TracksByScent>,
+        ReadExpect<'a, ScentMap>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -33,11 +263,25 @@ impl<'a> System<'a> for MonsterAI {
             entities,
             mut viewshed,
             monster,
+            factions,
             mut position,
             mut wants_to_melee,
             mut confused,
-            mut particle_builder,
             mut entity_moved,
+            alerted,
+            can_flee,
+            combat_stats,
+            mut ai_states,
+            mut rng,
+            mut summoners,
+            mut spawn_requests,
+            names,
+            mut log,
+            turn_counter,
+            mut speeds,
+            mut particles,
+            scent_trackers,
+            scent,
         ) = data;
 
         // If it's not the monster's turn, immediately return.
@@ -46,9 +290,31 @@ impl<'a> System<'a> for MonsterAI {
         }
 
         // Else, do the AI.
-        for (ent, mut viewshed, _monster, mut pos) in
-            (&entities, &mut viewshed, &monster, &mut position).join()
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        for (ent, mut viewshed, _monster, mut pos, ai) in (
+            &entities,
+            &mut viewshed,
+            &monster,
+            &mut position,
+            &mut ai_states,
+        )
+            .join()
         {
+            // Acting spends energy; monsters without enough sit this round out.
+            match speeds.get_mut(ent) {
+                Some(speed) if speed.energy >= NORMAL_SPEED => speed.energy -= NORMAL_SPEED,
+                _ => continue,
+            }
+
+            // Only hostile monsters go after the player; those without a
+            // faction are treated as hostile.
+            let hostile = factions
+                .get(ent)
+                .map_or(true, |f| f.kind == FactionKind::Hostile);
+            if !hostile {
+                continue;
+            }
+
             // Check to see if the mob is confused.
             let mut can_act = true;
             if let Some(am_confused) = confused.get_mut(ent) {
@@ -61,55 +327,163 @@ impl<'a> System<'a> for MonsterAI {
                 }
                 // Confused--can't act.
                 can_act = false;
-                // Play the confusion particle effect for each confused turn.
-                particle_builder.request(
-                    pos.x,
-                    pos.y,
-                    RGB::named(MAGENTA),
-                    RGB::named(BLACK),
-                    rltk::to_cp437('?'),
-                    200.0,
-                );
+            }
+
+            // Cowards run from the player once badly hurt, unless they're cornered.
+            if can_act && viewshed.visible_tiles.contains(&*player_pos) {
+                if let (Some(coward), Some(stats)) = (can_flee.get(ent), combat_stats.get(ent)) {
+                    if (stats.hp as f32) < stats.max_hp as f32 * coward.hp_threshold {
+                        let flee = flee_map(&map, player_idx);
+                        let my_idx = map.xy_idx(pos.x, pos.y);
+                        if let Some(dest) = DijkstraMap::find_lowest_exit(&flee, my_idx, &*map) {
+                            if flee.map[dest] < flee.map[my_idx] {
+                                step_to(
+                                    &mut map,
+                                    &mut pos,
+                                    &mut viewshed,
+                                    speeds.get_mut(ent),
+                                    dest,
+                                );
+                                entity_moved
+                                    .insert(ent, EntityMoved {})
+                                    .expect("Unable to insert marker");
+                                can_act = false;
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Seeing the player (or being alerted to them) always starts (or refreshes) a hunt.
+            let sees_player =
+                viewshed.visible_tiles.contains(&*player_pos) || alerted.get(ent).is_some();
+            if sees_player {
+                if !matches!(ai.mode, AiMode::Hunting { .. }) {
+                    notice(&mut particles, pos, '!', rltk::RED);
+                }
+                ai.mode = AiMode::Hunting {
+                    last_seen: player_idx,
+                    turns_left: HUNT_MEMORY_TURNS,
+                };
             }
 
             // If they're not confused, let them act as normal.
-            if can_act {
-                let distance =
-                    rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-
-                // If player is in melee range, initiate combat
-                if distance < 1.5 {
-                    wants_to_melee
-                        .insert(
-                            ent,
-                            WantsToMelee {
-                                target: *player_entity,
-                            },
-                        )
-                        .expect("Unable to insert attack");
-                } else if viewshed.visible_tiles.contains(&*player_pos) {
-                    // If player is visible, get path to them with A*.
-                    let path = rltk::a_star_search(
-                        map.xy_idx(pos.x, pos.y) as i32,
-                        map.xy_idx(player_pos.x, player_pos.y) as i32,
-                        &mut *map,
-                    );
-
-                    // If path is found, take a step and recalculate the viewshed.
-                    // `steps[0]` is the current position, so take the next step.
-                    if path.success && path.steps.len() > 1 {
-                        let mut idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = false;
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
-                        idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = true;
-                        viewshed.dirty = true;
-                        entity_moved
-                            .insert(ent, EntityMoved {})
-                            .expect("Unable to insert marker");
+            if !can_act {
+                continue;
+            }
+
+            // Summoners spend their turn calling for help whenever they can while hunting.
+            if let Some(summoner) = summoners.get_mut(ent) {
+                if summoner.ready_in > 0 {
+                    summoner.ready_in -= 1;
+                } else if sees_player
+                    && summon_minions(&mut map, pos, &summoner.minion, &mut spawn_requests) > 0
+                {
+                    summoner.ready_in = summoner.cooldown;
+                    if let Some(name) = names.get(ent) {
+                        log.push(
+                            turn_counter.turn,
+                            format!("The {} calls for aid!", &name.name),
+                        );
+                    }
+                    continue;
+                }
+            }
+
+            let distance =
+                rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+            let my_idx = map.xy_idx(pos.x, pos.y);
+            let mut dest: Option<usize> = None;
+
+            // Trackers follow the player's trail whenever they can't see them.
+            let scent_step = if sees_player || scent_trackers.get(ent).is_none() {
+                None
+            } else {
+                scent.follow(&map, my_idx)
+            };
+
+            match ai.mode {
+                _ if scent_step.is_some() => {
+                    dest = scent_step;
+                    ai.mode = AiMode::Hunting {
+                        last_seen: my_idx,
+                        turns_left: HUNT_MEMORY_TURNS,
+                    };
+                }
+                AiMode::Idle => {
+                    if rng.roll_dice(1, WANDER_CHANCE) == 1 {
+                        if let Some(wander_to) =
+                            wander_destination(&map, pos, WANDER_RADIUS, &mut rng)
+                        {
+                            ai.mode = AiMode::Wandering { dest: wander_to };
+                        }
+                    }
+                }
+                AiMode::Wandering { dest: wander_to } => {
+                    dest = path_step(&mut map, my_idx, wander_to);
+                    // Arrived, or can't get there--stand around for a while.
+                    if dest.is_none() || dest == Some(wander_to) {
+                        ai.mode = AiMode::Idle;
+                    }
+                }
+                AiMode::Hunting {
+                    last_seen,
+                    turns_left,
+                } => {
+                    if distance < 1.5 && sees_player {
+                        // If player is in melee range, initiate combat
+                        WantsToMelee::new_attack(&mut wants_to_melee, ent, *player_entity);
+                    } else {
+                        // Head for where the player was last seen with A*.
+                        dest = path_step(&mut map, my_idx, last_seen);
+                    }
+
+                    // Give up the hunt once the trail goes cold, but have a look around first if
+                    // it ran out where the player was last seen.
+                    if !sees_player {
+                        if my_idx == last_seen || dest.is_none() {
+                            notice(&mut particles, pos, '?', rltk::YELLOW);
+                            ai.mode = AiMode::Searching {
+                                around: last_seen,
+                                turns_left: SEARCH_TURNS,
+                            };
+                        } else if turns_left <= 1 {
+                            ai.mode = AiMode::Idle;
+                        } else {
+                            ai.mode = AiMode::Hunting {
+                                last_seen,
+                                turns_left: turns_left - 1,
+                            };
+                        }
                     }
                 }
+                AiMode::Searching { around, turns_left } => {
+                    let around_pos = Position {
+                        x: around as i32 % map.width,
+                        y: around as i32 / map.width,
+                    };
+                    if let Some(look_at) =
+                        wander_destination(&map, &around_pos, SEARCH_RADIUS, &mut rng)
+                    {
+                        dest = path_step(&mut map, my_idx, look_at);
+                    }
+                    ai.mode = if turns_left <= 1 {
+                        AiMode::Idle
+                    } else {
+                        AiMode::Searching {
+                            around,
+                            turns_left: turns_left - 1,
+                        }
+                    };
+                }
+            }
+
+            // Take a step and recalculate the viewshed.
+            if let Some(dest) = dest {
+                step_to(&mut map, &mut pos, &mut viewshed, speeds.get_mut(ent), dest);
+                entity_moved
+                    .insert(ent, EntityMoved {})
+                    .expect("Unable to insert marker");
             }
         }
     }
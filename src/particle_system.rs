@@ -1,4 +1,4 @@
-use super::{ParticleLifetime, Position, Renderable, Rltk};
+use super::{AnimatedRenderable, ParticleLifetime, Position, RenderOrder, Renderable, Rltk};
 use rltk::RGB;
 use specs::prelude::*;
 
@@ -81,7 +81,7 @@ impl<'a> System<'a> for ParticleSpawnSystem {
                         fg: new_particle.fg,
                         bg: new_particle.bg,
                         glyph: new_particle.glyph,
-                        render_order: 0,
+                        render_order: RenderOrder::Particle,
                     },
                 )
                 .expect("Unable to insert renderable");
@@ -100,6 +100,14 @@ impl<'a> System<'a> for ParticleSpawnSystem {
     }
 }
 
+/// Moves every [`AnimatedRenderable`] on by however long the last frame took.
+pub fn animate_glyphs(ecs: &mut World, ctx: &Rltk) {
+    let mut animations = ecs.write_storage::<AnimatedRenderable>();
+    for animation in (&mut animations).join() {
+        animation.advance(ctx.frame_time_ms);
+    }
+}
+
 pub fn cull_dead_particles(ecs: &mut World, ctx: &Rltk) {
     let mut dead_particles: Vec<Entity> = Vec::new();
     {
@@ -116,3 +124,25 @@ pub fn cull_dead_particles(ecs: &mut World, ctx: &Rltk) {
         ecs.delete_entity(*dead).expect("Particle will not die");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn animations_cycle_through_their_frames() {
+        let mut torch = AnimatedRenderable::new(vec![
+            (rltk::to_cp437('a'), 100.0),
+            (rltk::to_cp437('b'), 50.0),
+        ]);
+        assert_eq!(torch.glyph(), Some(rltk::to_cp437('a')));
+
+        torch.advance(99.0);
+        assert_eq!(torch.glyph(), Some(rltk::to_cp437('a')));
+        torch.advance(1.0);
+        assert_eq!(torch.glyph(), Some(rltk::to_cp437('b')));
+        // A long frame skips past every frame it covers and wraps around.
+        torch.advance(150.0);
+        assert_eq!(torch.glyph(), Some(rltk::to_cp437('b')));
+    }
+}
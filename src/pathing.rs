@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+
+use rltk::{BaseMap, DijkstraMap, SmallVec};
+use specs::prelude::*;
+
+use super::{EntryTrigger, Faction, FactionKind, Hidden, Map, Monster, Position, Viewshed};
+
+/// Extra cost for stepping onto a tile that should be avoided.
+///
+/// High enough that a detour of a few dozen tiles is preferred, but still finite, so a
+/// dangerous tile in a one-tile corridor doesn't cut off everything behind it.
+pub const AVOID_COST: f32 = 50.0;
+
+/// Wraps the map so that stepping onto any of the `avoid` tiles costs [`AVOID_COST`] more.
+struct AvoidingMap<'a> {
+    map: &'a Map,
+    avoid: &'a HashSet<usize>,
+}
+
+impl<'a> BaseMap for AvoidingMap<'a> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.map.is_opaque(idx)
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        self.map
+            .get_available_exits(idx)
+            .into_iter()
+            .map(|(exit, cost)| {
+                if self.avoid.contains(&exit) {
+                    (exit, cost + AVOID_COST)
+                } else {
+                    (exit, cost)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Finds the tile to step onto from `start` to head for the nearest of `goals`, steering
+/// around the `avoid` tiles where there's another way.
+///
+/// Returns `None` if none of the goals can be reached within `max_depth`.
+pub fn safe_step(
+    map: &Map,
+    start: usize,
+    goals: &[usize],
+    avoid: &HashSet<usize>,
+    max_depth: f32,
+) -> Option<usize> {
+    let avoiding = AvoidingMap { map, avoid };
+    let dijkstra = DijkstraMap::new(map.width, map.height, goals, &avoiding, max_depth);
+    if dijkstra.map[start] == std::f32::MAX {
+        return None;
+    }
+    DijkstraMap::find_lowest_exit(&dijkstra, start, &avoiding)
+}
+
+/// Tiles the player knows to be dangerous: revealed traps, and anywhere next to a hostile
+/// monster they can see.
+pub fn dangerous_tiles(ecs: &World) -> HashSet<usize> {
+    let map = ecs.fetch::<Map>();
+    let player_ent = ecs.fetch::<Entity>();
+    let positions = ecs.read_storage::<Position>();
+    let triggers = ecs.read_storage::<EntryTrigger>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let monsters = ecs.read_storage::<Monster>();
+    let factions = ecs.read_storage::<Faction>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+
+    let mut avoid: HashSet<usize> = (&triggers, &positions, !&hidden)
+        .join()
+        .map(|(_, pos, _)| map.xy_idx(pos.x, pos.y))
+        .collect();
+
+    if let Some(viewshed) = viewsheds.get(*player_ent) {
+        for (_, pos, faction) in (&monsters, &positions, factions.maybe()).join() {
+            let hostile = faction.map_or(true, |f| f.kind == FactionKind::Hostile);
+            if !hostile
+                || !viewshed
+                    .visible_tiles
+                    .contains(&rltk::Point::new(pos.x, pos.y))
+            {
+                continue;
+            }
+            for d_y in -1..=1 {
+                for d_x in -1..=1 {
+                    let (x, y) = (pos.x + d_x, pos.y + d_y);
+                    if x >= 0 && x < map.width && y >= 0 && y < map.height {
+                        avoid.insert(map.xy_idx(x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    avoid
+}
@@ -1,10 +1,76 @@
-use super::{components::*, gamelog::GameLog, Map, RunState, State, TileType};
-use rltk::{Point, Rltk, VirtualKeyCode};
+use super::{
+    camera::Camera,
+    components::*,
+    debug::debug_input,
+    gamelog::{GameLog, TurnCounter},
+    gui,
+    hotbar::{hotbar_item, slot_for_key, Hotbar},
+    hunger_system::hunger_effects,
+    keybindings::{Action, KeyBindings},
+    pathing::{dangerous_tiles, safe_step},
+    ranged_combat_system::{ammo_bundle, ranged_weapon},
+    settings::GameSettings,
+    Map, RunState, State, TileType,
+};
+use rltk::{Point, RandomNumberGenerator, Rltk};
 use specs::prelude::*;
 use std::cmp::{max, min};
 
-/// Tries to move the player by `(delta_x, delta_y)` amount.
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// Out of six, how likely the player is to disarm a trap.
+const DISARM_ODDS: i32 = 4;
+/// A failed disarm sets the trap off 1 in `BOTCHED_DISARM_SPRINGS` times.
+const BOTCHED_DISARM_SPRINGS: i32 = 2;
+
+/// The two directions 45 degrees either side of `(delta_x, delta_y)`.
+fn flanking_deltas(delta_x: i32, delta_y: i32) -> [(i32, i32); 2] {
+    [
+        ((delta_x - delta_y).signum(), (delta_x + delta_y).signum()),
+        ((delta_x + delta_y).signum(), (delta_y - delta_x).signum()),
+    ]
+}
+
+/// Every direction a step can be taken in.
+const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Where a step by `(delta_x, delta_y)` actually goes: somewhere random if the player is
+/// confused. The confusion itself wears off in the status system.
+fn stagger(delta_x: i32, delta_y: i32, ecs: &mut World) -> (i32, i32) {
+    let player_ent = *ecs.fetch::<Entity>();
+    if !ecs.read_storage::<Confusion>().contains(player_ent) {
+        return (delta_x, delta_y);
+    }
+    let roll = ecs
+        .fetch_mut::<RandomNumberGenerator>()
+        .roll_dice(1, DIRECTIONS.len() as i32);
+    DIRECTIONS[roll as usize - 1]
+}
+
+/// Tries to move the player by `(delta_x, delta_y)` amount, picking up whatever's on the
+/// new tile if auto-pickup is on. A confused player lurches off in a random direction instead.
+///
+/// Returns the state to switch to if bumping into something opened another screen.
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> Option<RunState> {
+    let (delta_x, delta_y) = stagger(delta_x, delta_y, ecs);
+    let start = *ecs.fetch::<Point>();
+    let state = move_or_attack(delta_x, delta_y, ecs);
+    let moved = *ecs.fetch::<Point>() != start;
+    ecs.write_resource::<PlayerStepped>().stepped = moved;
+    if moved && ecs.fetch::<GameSettings>().auto_pickup {
+        pick_up_free_items(ecs);
+    }
+    state
+}
+
+fn move_or_attack(delta_x: i32, delta_y: i32, ecs: &mut World) -> Option<RunState> {
     let map = ecs.fetch::<Map>();
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
@@ -12,15 +78,22 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let mut entity_moved = ecs.write_storage::<EntityMoved>();
 
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let factions = ecs.read_storage::<Faction>();
+    let names = ecs.read_storage::<Name>();
     let entities = ecs.entities();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let cleaves = ecs.read_storage::<Cleave>();
+    let vendors = ecs.read_storage::<Vendor>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let mut log = ecs.fetch_mut::<GameLog>();
+    let turn_counter = ecs.fetch::<TurnCounter>();
 
     for (ent, _player, pos, viewshed) in
         (&entities, &mut players, &mut positions, &mut viewsheds).join()
     {
         let (new_x, new_y) = (pos.x + delta_x, pos.y + delta_y);
         if new_x < 1 || new_x > map.width - 1 || new_y < 1 || new_y > map.height - 1 {
-            return;
+            return None;
         }
         let dest_idx = map.xy_idx(new_x, new_y);
 
@@ -29,23 +102,59 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             match target {
                 None => {}
                 Some(_) => {
-                    wants_to_melee
-                        .insert(
-                            ent,
-                            WantsToMelee {
-                                target: *potential_target,
-                            },
-                        )
-                        .expect("Add target failed.");
-                    return; // don't move after an attack
+                    // Shopkeepers trade instead.
+                    if vendors.contains(*potential_target) {
+                        return Some(RunState::ShowShop {
+                            mode: gui::ShopMode::Buy,
+                            page: 0,
+                        });
+                    }
+                    // Neutral entities block the way, but aren't attacked on bump.
+                    if let Some(faction) = factions.get(*potential_target) {
+                        if faction.kind == FactionKind::Neutral {
+                            if let Some(name) = names.get(*potential_target) {
+                                log.push(
+                                    turn_counter.turn,
+                                    format!("The {} is in your way.", &name.name),
+                                );
+                            }
+                            return None;
+                        }
+                    }
+                    WantsToMelee::new_attack(&mut wants_to_melee, ent, *potential_target);
+
+                    // Cleaving weapons also catch whatever stands to either side.
+                    let cleaving = (&cleaves, &equipped)
+                        .join()
+                        .any(|(_, equipped_by)| equipped_by.owner == ent);
+                    if cleaving {
+                        for (d_x, d_y) in flanking_deltas(delta_x, delta_y).iter() {
+                            let (x, y) = (pos.x + d_x, pos.y + d_y);
+                            if x < 1 || x > map.width - 1 || y < 1 || y > map.height - 1 {
+                                continue;
+                            }
+                            for other in map.tile_content[map.xy_idx(x, y)].iter() {
+                                let neutral = factions
+                                    .get(*other)
+                                    .map_or(false, |f| f.kind == FactionKind::Neutral);
+                                if combat_stats.get(*other).is_some() && !neutral {
+                                    WantsToMelee::new_attack(&mut wants_to_melee, ent, *other);
+                                }
+                            }
+                        }
+                    }
+                    return None; // don't move after an attack
                 }
             }
         }
 
-        // Can't move through walls!
-        if !map.blocked[dest_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+        // Can't move through walls, or slip diagonally past their corners.
+        let diagonal = delta_x != 0 && delta_y != 0;
+        if !map.blocked[dest_idx]
+            && (!diagonal || map.is_diagonal_open(pos.x, pos.y, delta_x, delta_y))
+        {
+            pos.x = min(map.width - 1, max(0, pos.x + delta_x));
+            pos.y = min(map.height - 1, max(0, pos.y + delta_y));
             entity_moved
                 .insert(ent, EntityMoved {})
                 .expect("Unable to insert marker");
@@ -59,43 +168,362 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             ppos.y = pos.y;
         }
     }
+    None
 }
 
-/// Handles item pickup.
-fn get_item(ecs: &mut World) {
-    let player_pos = ecs.fetch::<Point>();
+/// Resource noting whether the player stepped onto a new tile since monsters were last
+/// given energy, since only a step makes slow ground cost the player time.
+#[derive(Default)]
+pub struct PlayerStepped {
+    pub stepped: bool,
+}
+
+/// Resource tracking whether the player is currently auto-exploring.
+#[derive(Default)]
+pub struct AutoExplore {
+    pub active: bool,
+}
+
+/// Furthest distance auto-explore will search for unexplored tiles.
+const AUTO_EXPLORE_DEPTH: f32 = 1000.0;
+
+/// Finds the name of a hostile monster in the player's view, if there is one.
+fn visible_hostile(ecs: &World) -> Option<String> {
     let player_ent = ecs.fetch::<Entity>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let monsters = ecs.read_storage::<Monster>();
+    let factions = ecs.read_storage::<Faction>();
+    let names = ecs.read_storage::<Name>();
+    let map = ecs.fetch::<Map>();
+
+    let viewshed = viewsheds.get(*player_ent)?;
+    for tile in viewshed.visible_tiles.iter() {
+        let idx = map.xy_idx(tile.x, tile.y);
+        for ent in map.tile_content[idx].iter() {
+            if monsters.get(*ent).is_none() {
+                continue;
+            }
+            // Monsters without a faction are treated as hostile, same as the AI does.
+            let hostile = match factions.get(*ent) {
+                Some(faction) => faction.kind == FactionKind::Hostile,
+                None => true,
+            };
+            if hostile {
+                return Some(match names.get(*ent) {
+                    Some(name) => name.name.clone(),
+                    None => "monster".to_string(),
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Takes a single auto-explore step toward the nearest unrevealed, reachable tile, keeping
+/// clear of known traps where it can.
+///
+/// Returns `false` (after logging why) if exploring should stop instead.
+fn auto_explore_step(ecs: &mut World) -> bool {
+    if let Some(name) = visible_hostile(ecs) {
+        let turn = ecs.fetch::<TurnCounter>().turn;
+        ecs.fetch_mut::<GameLog>()
+            .push(turn, format!("You stop exploring: a {} is in view.", name));
+        return false;
+    }
+
+    let avoid = dangerous_tiles(ecs);
+    let step = {
+        let map = ecs.fetch::<Map>();
+        let player_pos = ecs.fetch::<Point>();
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+        // Head for anything not yet seen that isn't solid rock.
+        let starts: Vec<usize> = map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(idx, tile)| !map.revealed_tiles[*idx] && **tile != TileType::Wall)
+            .map(|(idx, _)| idx)
+            .collect();
+        safe_step(&map, player_idx, &starts, &avoid, AUTO_EXPLORE_DEPTH).map(|idx| {
+            (
+                idx as i32 % map.width - player_pos.x,
+                idx as i32 / map.width - player_pos.y,
+            )
+        })
+    };
+
+    match step {
+        None => {
+            let turn = ecs.fetch::<TurnCounter>().turn;
+            ecs.fetch_mut::<GameLog>()
+                .push(turn, "There's nothing left to explore here.");
+            false
+        }
+        Some((delta_x, delta_y)) => {
+            try_move_player(delta_x, delta_y, ecs);
+            true
+        }
+    }
+}
+
+/// Resource holding the tile the player is walking to after clicking on it, if any.
+#[derive(Default)]
+pub struct Travel {
+    pub destination: Option<usize>,
+}
+
+/// Starts travelling to the tile under the mouse, if it's somewhere the player can get to.
+///
+/// Returns `false` (after logging why) if the player isn't going anywhere.
+fn start_travel(ecs: &mut World, ctx: &Rltk) -> bool {
+    let hint = {
+        let map = ecs.fetch::<Map>();
+        let camera = ecs.fetch::<Camera>();
+        let player_pos = ecs.fetch::<Point>();
+
+        let (screen_x, screen_y) = ctx.mouse_pos();
+        if !camera.on_screen(screen_x, screen_y) {
+            return false;
+        }
+        let (x, y) = camera.to_world(screen_x, screen_y);
+        if x < 0 || x >= map.width || y < 0 || y >= map.height {
+            return false;
+        }
+        let (start, end) = (map.xy_idx(player_pos.x, player_pos.y), map.xy_idx(x, y));
+        if start == end {
+            return false;
+        }
+
+        if !map.revealed_tiles[end] {
+            "You don't know what's over there yet."
+        } else if !rltk::a_star_search(start, end, &*map).success {
+            "You can't see a way to get there."
+        } else {
+            ecs.write_resource::<Travel>().destination = Some(end);
+            return true;
+        }
+    };
+
+    let turn = ecs.fetch::<TurnCounter>().turn;
+    ecs.fetch_mut::<GameLog>().push(turn, hint);
+    false
+}
+
+/// Takes a single step toward the travel destination.
+///
+/// Returns `false` if the player has arrived, or (after logging why) if they should stop.
+fn travel_step(ecs: &mut World) -> bool {
+    let destination = match ecs.fetch::<Travel>().destination {
+        Some(destination) => destination,
+        None => return false,
+    };
+    if let Some(name) = visible_hostile(ecs) {
+        let turn = ecs.fetch::<TurnCounter>().turn;
+        ecs.fetch_mut::<GameLog>()
+            .push(turn, format!("You stop: a {} is in view.", name));
+        return false;
+    }
+
+    let avoid = dangerous_tiles(ecs);
+    let step = {
+        let map = ecs.fetch::<Map>();
+        let player_pos = ecs.fetch::<Point>();
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        if player_idx == destination {
+            return false;
+        }
+
+        safe_step(&map, player_idx, &[destination], &avoid, AUTO_EXPLORE_DEPTH).map(|idx| {
+            (
+                idx as i32 % map.width - player_pos.x,
+                idx as i32 / map.width - player_pos.y,
+            )
+        })
+    };
+
+    match step {
+        None => {
+            let turn = ecs.fetch::<TurnCounter>().turn;
+            ecs.fetch_mut::<GameLog>()
+                .push(turn, "Something is blocking the way.");
+            false
+        }
+        Some((delta_x, delta_y)) => {
+            try_move_player(delta_x, delta_y, ecs);
+            true
+        }
+    }
+}
+
+/// Items lying on the player's tile, in entity id order.
+///
+/// That isn't the order they were dropped in, since ids get reused, but it's stable from one
+/// turn to the next.
+fn items_underfoot(ecs: &World) -> Vec<Entity> {
+    let player_pos = ecs.fetch::<Point>();
     let entities = ecs.entities();
     let items = ecs.read_storage::<Item>();
     let positions = ecs.read_storage::<Position>();
+
+    // Joins walk storages in entity id order, so the result comes out sorted by id.
+    (&entities, &items, &positions)
+        .join()
+        .filter(|(_, _, pos)| pos.x == player_pos.x && pos.y == player_pos.y)
+        .map(|(item_ent, _, _)| item_ent)
+        .collect()
+}
+
+/// Handles item pickup, grabbing everything on the player's tile.
+///
+/// Each item gets its own pickup intent; whether it actually fits in the pack is
+/// up to the item collection system.
+fn get_item(ecs: &mut World) {
+    let underfoot = items_underfoot(ecs);
+    let player_ent = ecs.fetch::<Entity>();
+    let prices = ecs.read_storage::<Price>();
     let mut log = ecs.fetch_mut::<GameLog>();
+    let turn_counter = ecs.fetch::<TurnCounter>();
 
-    // Check to see if there's an item under the player to pick up.
-    let mut target_item: Option<Entity> = None;
-    for (item_ent, _, position) in (&entities, &items, &positions).join() {
-        if position.x == player_pos.x && position.y == player_pos.y {
-            target_item = Some(item_ent);
-        }
+    if underfoot.is_empty() {
+        log.push(turn_counter.turn, "There is nothing here to pickup.");
+        return;
+    }
+
+    // Shop stock has to be bought from the shopkeeper.
+    let (for_sale, free): (Vec<Entity>, Vec<Entity>) = underfoot
+        .into_iter()
+        .partition(|item| prices.get(*item).is_some());
+    if free.is_empty() {
+        let price = prices.get(for_sale[0]).unwrap();
+        log.push(
+            turn_counter.turn,
+            format!(
+                "That costs {} gold; buy it from the shopkeeper.",
+                price.value
+            ),
+        );
+        return;
+    }
+
+    request_pickup(ecs, *player_ent, &free);
+}
+
+/// Quietly picks up everything underfoot that isn't for sale.
+fn pick_up_free_items(ecs: &mut World) {
+    let player_ent = *ecs.fetch::<Entity>();
+    let free: Vec<Entity> = {
+        let prices = ecs.read_storage::<Price>();
+        items_underfoot(ecs)
+            .into_iter()
+            .filter(|item| prices.get(*item).is_none())
+            .collect()
+    };
+    request_pickup(ecs, player_ent, &free);
+}
+
+fn request_pickup(ecs: &World, collected_by: Entity, items: &[Entity]) {
+    let mut pickup = ecs.write_storage::<WantsToPickupItem>();
+    for item in items.iter() {
+        pickup
+            .insert(
+                *item,
+                WantsToPickupItem {
+                    collected_by,
+                    item: *item,
+                },
+            )
+            .expect("Unable to insert want to pickup");
     }
+}
+
+/// Whether the player is carrying anything to eat.
+fn has_food(ecs: &World) -> bool {
+    let player_ent = ecs.fetch::<Entity>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let food = ecs.read_storage::<ProvidesFood>();
+    (&backpack, &food)
+        .join()
+        .any(|(pack, _)| pack.owner == *player_ent)
+}
+
+/// Starts aiming the player's ranged weapon, if they have one equipped.
+fn fire_weapon(ecs: &mut World) -> RunState {
+    let player_ent = *ecs.fetch::<Entity>();
+    let weapons = ecs.read_storage::<Weapon>();
+    let bow = ranged_weapon(
+        player_ent,
+        &ecs.entities(),
+        &weapons,
+        &ecs.read_storage::<Equipped>(),
+    );
+    let turn = ecs.fetch::<TurnCounter>().turn;
+    let item = match bow {
+        Some(item) => item,
+        None => {
+            ecs.fetch_mut::<GameLog>()
+                .push(turn, "You have no ranged weapon equipped.");
+            return RunState::AwaitingInput;
+        }
+    };
+    let weapon = weapons.get(item).unwrap();
 
-    // Pick up the item, if there is one.
-    match target_item {
-        None => log
-            .entries
-            .push("There is nothing here to pickup.".to_string()),
-        Some(item) => {
-            let mut pickup = ecs.write_storage::<WantsToPickupItem>();
-            pickup
-                .insert(
-                    *player_ent,
-                    WantsToPickupItem {
-                        collected_by: *player_ent,
-                        item,
-                    },
-                )
-                .expect("Unable to insert want to pickup");
+    // Don't bother aiming without anything to shoot.
+    if let Some(kind) = weapon.ammo {
+        let bundle = ammo_bundle(
+            player_ent,
+            kind,
+            &ecs.entities(),
+            &ecs.read_storage::<Ammo>(),
+            &ecs.read_storage::<InBackpack>(),
+        );
+        if bundle.is_none() {
+            ecs.fetch_mut::<GameLog>().push(
+                turn,
+                format!("You have no {} left.", kind.name().to_lowercase()),
+            );
+            return RunState::AwaitingInput;
         }
     }
+    RunState::ShowTargeting {
+        range: weapon.range,
+        item,
+    }
+}
+
+/// Uses an item from the player's backpack, sending ranged items to targeting first.
+pub fn use_item(ecs: &mut World, item: Entity) -> RunState {
+    if let Some(ranged) = ecs.read_storage::<Ranged>().get(item) {
+        return RunState::ShowTargeting {
+            range: ranged.range,
+            item,
+        };
+    }
+    let player_ent = *ecs.fetch::<Entity>();
+    ecs.write_storage::<WantsToUseItem>()
+        .insert(player_ent, WantsToUseItem { item, target: None })
+        .expect("Unable to insert intent");
+    // Counts as player action--run systems in `PlayerTurn` to make effects of the item take place.
+    RunState::PlayerTurn
+}
+
+/// Uses whatever is bound to the hotbar's `slot`.
+fn quick_use(ecs: &mut World, slot: usize) -> RunState {
+    let bound = ecs.fetch::<Hotbar>().name(slot).map(str::to_string);
+    if let Some(item) = hotbar_item(ecs, slot) {
+        return use_item(ecs, item);
+    }
+
+    let turn = ecs.fetch::<TurnCounter>().turn;
+    let message = match bound {
+        Some(name) => format!("You have no {} left.", name),
+        None => format!(
+            "Nothing is bound to key {}; press it in the inventory to bind an item.",
+            slot + 1
+        ),
+    };
+    ecs.write_resource::<GameLog>().push(turn, message);
+    RunState::AwaitingInput
 }
 
 fn try_next_level(ecs: &mut World) -> bool {
@@ -107,13 +535,101 @@ fn try_next_level(ecs: &mut World) -> bool {
         true
     } else {
         let mut log = ecs.fetch_mut::<GameLog>();
-        log.entries
-            .push("There is no way down from here.".to_string());
+        let turn_counter = ecs.fetch::<TurnCounter>();
+        log.push(turn_counter.turn, "There is no way down from here.");
         false
     }
 }
 
-/// Skips the player's turn, healing 1 point if no monsters are around.
+/// Traps (hidden or not) on or next to the player's tile.
+fn adjacent_traps(ecs: &World) -> Vec<Entity> {
+    let player_pos = ecs.fetch::<Point>();
+    let entities = ecs.entities();
+    let triggers = ecs.read_storage::<EntryTrigger>();
+    let positions = ecs.read_storage::<Position>();
+    (&entities, &triggers, &positions)
+        .join()
+        .filter(|(_, _, pos)| {
+            (pos.x - player_pos.x).abs() <= 1 && (pos.y - player_pos.y).abs() <= 1
+        })
+        .map(|(trap, _, _)| trap)
+        .collect()
+}
+
+/// Spends a turn searching, revealing any hidden traps next to the player.
+fn search_for_traps(ecs: &mut World) -> RunState {
+    let traps = adjacent_traps(ecs);
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let names = ecs.read_storage::<Name>();
+    let mut log = ecs.fetch_mut::<GameLog>();
+    let turn = ecs.fetch::<TurnCounter>().turn;
+
+    let mut found = false;
+    for trap in traps.iter() {
+        if hidden.remove(*trap).is_some() {
+            found = true;
+            if let Some(name) = names.get(*trap) {
+                log.push(turn, format!("You find a {}!", &name.name));
+            }
+        }
+    }
+    if !found {
+        log.push(turn, "You search carefully, but find nothing.");
+    }
+    RunState::PlayerTurn
+}
+
+/// Tries to disarm a trap the player knows about on or next to their tile.
+///
+/// Success removes the trap; a botched attempt may set it off instead.
+fn disarm_trap(ecs: &mut World) -> RunState {
+    let turn = ecs.fetch::<TurnCounter>().turn;
+    let trap = {
+        let hidden = ecs.read_storage::<Hidden>();
+        adjacent_traps(ecs)
+            .into_iter()
+            .find(|trap| !hidden.contains(*trap))
+    };
+    let trap = match trap {
+        Some(trap) => trap,
+        None => {
+            ecs.fetch_mut::<GameLog>()
+                .push(turn, "There's no trap here to disarm.");
+            return RunState::AwaitingInput;
+        }
+    };
+
+    let name = ecs
+        .read_storage::<Name>()
+        .get(trap)
+        .map_or("trap".to_string(), |n| n.name.clone());
+    let (disarmed, sprung) = {
+        let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+        let disarmed = rng.roll_dice(1, 6) <= DISARM_ODDS;
+        (
+            disarmed,
+            !disarmed && rng.roll_dice(1, BOTCHED_DISARM_SPRINGS) == 1,
+        )
+    };
+
+    if disarmed {
+        ecs.fetch_mut::<GameLog>()
+            .push(turn, format!("You disarm the {}.", name));
+        ecs.delete_entity(trap).expect("Unable to delete trap");
+    } else {
+        ecs.fetch_mut::<GameLog>()
+            .push(turn, format!("You fail to disarm the {}.", name));
+        if sprung {
+            let player_ent = *ecs.fetch::<Entity>();
+            ecs.write_storage::<Sprung>()
+                .insert(trap, Sprung { victim: player_ent })
+                .expect("Unable to spring trap");
+        }
+    }
+    RunState::PlayerTurn
+}
+
+/// Skips the player's turn, healing a little (depending on hunger) if no monsters are around.
 fn skip_turn(ecs: &mut World) -> RunState {
     let player_ent = ecs.fetch::<Entity>();
     let viewshed_comp = ecs.read_storage::<Viewshed>();
@@ -135,20 +651,20 @@ fn skip_turn(ecs: &mut World) -> RunState {
         }
     }
 
-    // Stop skip-based healing if the player is hungry or starving.
+    // How much resting heals depends on how well fed the player is.
     let hunger_clocks = ecs.read_storage::<HungerClock>();
-    if let Some(hc) = hunger_clocks.get(*player_ent) {
-        match hc.state {
-            HungerState::Hungry => can_heal = false,
-            HungerState::Starving => can_heal = false,
-            _ => {}
-        }
+    let rest_heal = match hunger_clocks.get(*player_ent) {
+        Some(hc) => hunger_effects(hc.state).rest_heal,
+        None => 1,
+    };
+    if rest_heal < 1 {
+        can_heal = false;
     }
 
     if can_heal {
         let mut stats = ecs.write_storage::<CombatStats>();
         let player_stats = stats.get_mut(*player_ent).unwrap();
-        player_stats.hp = i32::min(player_stats.hp + 1, player_stats.max_hp);
+        player_stats.hp = i32::min(player_stats.hp + rest_heal, player_stats.max_hp);
     }
 
     RunState::PlayerTurn
@@ -156,64 +672,237 @@ fn skip_turn(ecs: &mut World) -> RunState {
 
 /// Parses player keyboard input into actions.
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    match ctx.key {
+    // Keep auto-exploring until a key is pressed or something interrupts the run.
+    if gs.ecs.fetch::<AutoExplore>().active {
+        if ctx.key.is_none() && auto_explore_step(&mut gs.ecs) {
+            return RunState::PlayerTurn;
+        }
+        gs.ecs.write_resource::<AutoExplore>().active = false;
+        return RunState::AwaitingInput;
+    }
+
+    // Likewise, keep walking to a clicked tile until something gets in the way.
+    if gs.ecs.fetch::<Travel>().destination.is_some() {
+        if ctx.key.is_none() && !ctx.left_click && travel_step(&mut gs.ecs) {
+            return RunState::PlayerTurn;
+        }
+        gs.ecs.write_resource::<Travel>().destination = None;
+        return RunState::AwaitingInput;
+    }
+
+    // Clicking on the map walks the player there.
+    if ctx.left_click {
+        if start_travel(&mut gs.ecs, ctx) && travel_step(&mut gs.ecs) {
+            return RunState::PlayerTurn;
+        }
+        gs.ecs.write_resource::<Travel>().destination = None;
+        return RunState::AwaitingInput;
+    }
+
+    let key = match ctx.key {
         None => return RunState::AwaitingInput,
-        Some(key) => match key {
-            VirtualKeyCode::Left
-            | VirtualKeyCode::Numpad4
-            | VirtualKeyCode::H
-            | VirtualKeyCode::A => try_move_player(-1, 0, &mut gs.ecs),
-
-            VirtualKeyCode::Right
-            | VirtualKeyCode::Numpad6
-            | VirtualKeyCode::L
-            | VirtualKeyCode::D => try_move_player(1, 0, &mut gs.ecs),
-
-            VirtualKeyCode::Up
-            | VirtualKeyCode::Numpad8
-            | VirtualKeyCode::K
-            | VirtualKeyCode::W => try_move_player(0, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Down
-            | VirtualKeyCode::Numpad2
-            | VirtualKeyCode::J
-            | VirtualKeyCode::S => try_move_player(0, 1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::U | VirtualKeyCode::E => {
-                try_move_player(1, -1, &mut gs.ecs)
-            }
-
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y | VirtualKeyCode::Q => {
-                try_move_player(-1, -1, &mut gs.ecs)
-            }
-
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::B | VirtualKeyCode::C => {
-                try_move_player(1, 1, &mut gs.ecs)
-            }
-
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N | VirtualKeyCode::Z => {
-                try_move_player(-1, 1, &mut gs.ecs)
-            }
-            // Picks up an item (if there is one).
-            VirtualKeyCode::G => get_item(&mut gs.ecs),
-            // Shows the inventory screen.
-            VirtualKeyCode::I => return RunState::ShowInventory,
-            // Shows item drop interface.
-            VirtualKeyCode::P => return RunState::ShowDropItem,
-            VirtualKeyCode::R => return RunState::ShowRemoveItem,
-            // Skip the player's current turn.
-            VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
-            // Level changes
-            VirtualKeyCode::Period => {
-                if try_next_level(&mut gs.ecs) {
-                    return RunState::NextLevel;
-                }
-            }
-            // Save and Quit.
-            VirtualKeyCode::Escape => return RunState::SaveGame,
+        Some(key) => key,
+    };
+    if let Some(state) = debug_input(&mut gs.ecs, key) {
+        return state;
+    }
+    let bound = gs.ecs.fetch::<KeyBindings>().action_for(key);
+    let action = match (bound, slot_for_key(key)) {
+        (Some(action), _) => action,
+        // Unbound number keys use whatever's on the hotbar.
+        (None, Some(slot)) => return quick_use(&mut gs.ecs, slot),
+        (None, None) => return RunState::AwaitingInput,
+    };
 
-            _ => return RunState::AwaitingInput,
-        },
+    match action {
+        Action::MoveWest => {
+            if let Some(state) = try_move_player(-1, 0, &mut gs.ecs) {
+                return state;
+            }
+        }
+        Action::MoveEast => {
+            if let Some(state) = try_move_player(1, 0, &mut gs.ecs) {
+                return state;
+            }
+        }
+        Action::MoveNorth => {
+            if let Some(state) = try_move_player(0, -1, &mut gs.ecs) {
+                return state;
+            }
+        }
+        Action::MoveSouth => {
+            if let Some(state) = try_move_player(0, 1, &mut gs.ecs) {
+                return state;
+            }
+        }
+        Action::MoveNorthEast => {
+            if let Some(state) = try_move_player(1, -1, &mut gs.ecs) {
+                return state;
+            }
+        }
+        Action::MoveNorthWest => {
+            if let Some(state) = try_move_player(-1, -1, &mut gs.ecs) {
+                return state;
+            }
+        }
+        Action::MoveSouthEast => {
+            if let Some(state) = try_move_player(1, 1, &mut gs.ecs) {
+                return state;
+            }
+        }
+        Action::MoveSouthWest => {
+            if let Some(state) = try_move_player(-1, 1, &mut gs.ecs) {
+                return state;
+            }
+        }
+        // Picks up an item (if there is one).
+        Action::PickUp => get_item(&mut gs.ecs),
+        // Shows the inventory screen.
+        Action::Inventory => {
+            return RunState::ShowInventory {
+                page: 0,
+                hotkey: None,
+            }
+        }
+        // Shows item drop interface.
+        Action::Drop => return RunState::ShowDropItem { page: 0 },
+        // Shows only the food in the backpack.
+        Action::Eat => {
+            if has_food(&gs.ecs) {
+                return RunState::ShowEat { page: 0 };
+            }
+            let turn = gs.ecs.fetch::<TurnCounter>().turn;
+            gs.ecs
+                .write_resource::<GameLog>()
+                .push(turn, "You have nothing to eat.");
+        }
+        // Aims the equipped ranged weapon.
+        Action::Fire => return fire_weapon(&mut gs.ecs),
+        Action::Remove => return RunState::ShowRemoveItem,
+        // Shows the character and equipment screen.
+        Action::Character => return RunState::ShowCharacter,
+        // Shows the full message log.
+        Action::MessageLog => return RunState::ShowLog { offset: 0 },
+        // Shows an overview of everything seen on the level, centered on the player.
+        Action::Overview => {
+            let player_pos = gs.ecs.fetch::<Point>();
+            return RunState::ShowMap {
+                x: player_pos.x,
+                y: player_pos.y,
+            };
+        }
+        // Examine the map, starting from the player's own tile.
+        Action::Look => {
+            let player_pos = gs.ecs.fetch::<Point>();
+            return RunState::Looking {
+                x: player_pos.x,
+                y: player_pos.y,
+            };
+        }
+        // Start auto-exploring the level.
+        Action::AutoExplore => {
+            if !auto_explore_step(&mut gs.ecs) {
+                return RunState::AwaitingInput;
+            }
+            gs.ecs.write_resource::<AutoExplore>().active = true;
+        }
+        // Skip the player's current turn.
+        Action::Wait => return skip_turn(&mut gs.ecs),
+        // Look for traps around the player, or try to take one apart.
+        Action::Search => return search_for_traps(&mut gs.ecs),
+        Action::Disarm => return disarm_trap(&mut gs.ecs),
+        // Level changes
+        Action::Descend => {
+            if try_next_level(&mut gs.ecs) {
+                return RunState::NextLevel;
+            }
+        }
+        // Opens the pause menu, where the player can save and/or quit.
+        Action::Menu => {
+            return RunState::InGameMenu {
+                menu_selection: gui::InGameMenuSelection::Continue,
+            }
+        }
     }
     RunState::PlayerTurn
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confused_players_stagger_in_random_directions() {
+        let mut ecs = World::new();
+        crate::register_components(&mut ecs);
+        ecs.insert(RandomNumberGenerator::seeded(7));
+        let player = ecs
+            .create_entity()
+            .with(Player {})
+            .with(Confusion { turns: 10 })
+            .build();
+        ecs.insert(player);
+
+        let steps: Vec<(i32, i32)> = (0..10).map(|_| stagger(1, 0, &mut ecs)).collect();
+        assert!(steps.iter().any(|step| *step != (1, 0)));
+        assert!(steps.iter().all(|step| DIRECTIONS.contains(step)));
+        // Picking a direction doesn't use the confusion up.
+        assert_eq!(
+            ecs.read_storage::<Confusion>().get(player).unwrap().turns,
+            10
+        );
+
+        // Once it wears off, moves go where they're meant to.
+        ecs.write_storage::<Confusion>().remove(player);
+        assert_eq!(stagger(1, 0, &mut ecs), (1, 0));
+    }
+
+    #[test]
+    fn bumping_a_monster_attacks_it_instead_of_moving() {
+        let rows = ["#####", "#@..#", "#####"];
+        let (mut map, start) = Map::from_ascii(&rows.join("\n")).unwrap();
+        let start = start.unwrap();
+        let mut ecs = World::new();
+        crate::register_components(&mut ecs);
+        ecs.insert(GameLog::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(GameSettings::default());
+        ecs.insert(PlayerStepped::default());
+        ecs.insert(Point::new(start.x, start.y));
+        let player = ecs
+            .create_entity()
+            .with(Player {})
+            .with(Position {
+                x: start.x,
+                y: start.y,
+            })
+            .with(Viewshed {
+                visible_tiles: Vec::new(),
+                range: 8,
+                dirty: false,
+            })
+            .build();
+        ecs.insert(player);
+        let orc = ecs
+            .create_entity()
+            .with(Monster {})
+            .with(CombatStats {
+                max_hp: 10,
+                hp: 10,
+                defense: 1,
+                power: 3,
+            })
+            .build();
+        let orc_idx = map.xy_idx(start.x + 1, start.y);
+        map.tile_content[orc_idx].push(orc);
+        ecs.insert(map);
+
+        assert!(try_move_player(1, 0, &mut ecs).is_none());
+
+        let melee = ecs.read_storage::<WantsToMelee>();
+        assert_eq!(melee.get(player).unwrap().targets, vec![orc]);
+        assert_eq!(*ecs.fetch::<Point>(), Point::new(start.x, start.y));
+        // The hit itself is reported by the melee system, not here.
+        assert!(ecs.fetch::<GameLog>().entries.is_empty());
+    }
+}
@@ -37,21 +37,85 @@ impl RandomTable {
         self
     }
 
+    /// True if nothing with a positive weight has been added.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sum of the weights of every entry.
+    pub fn total_weight(&self) -> i32 {
+        self.total_weight
+    }
+
+    /// Picks an entry's name, with each entry's chance proportional to its weight.
+    ///
+    /// Returns "None" if the table is empty.
     pub fn roll(&self, rng: &mut RandomNumberGenerator) -> String {
         if self.total_weight == 0 {
             return "None".to_string();
         }
         let mut roll = rng.roll_dice(1, self.total_weight) - 1;
-        let mut index: usize = 0;
 
-        while roll > 0 {
-            if roll < self.entries[index].weight {
-                return self.entries[index].name.clone();
+        for entry in self.entries.iter() {
+            if roll < entry.weight {
+                return entry.name.clone();
             }
-
-            roll -= self.entries[index].weight;
-            index += 1;
+            roll -= entry.weight;
         }
         "None".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const ROLLS: i32 = 10_000;
+
+    fn roll_counts(table: &RandomTable) -> HashMap<String, i32> {
+        let mut rng = RandomNumberGenerator::seeded(42);
+        let mut counts = HashMap::new();
+        for _ in 0..ROLLS {
+            *counts.entry(table.roll(&mut rng)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn rolls_are_proportional_to_weight() {
+        let table = RandomTable::new()
+            .add("Goblin", 6)
+            .add("Orc", 3)
+            .add("Troll", 1);
+        assert_eq!(table.total_weight(), 10);
+
+        let counts = roll_counts(&table);
+        assert!(!counts.contains_key("None"));
+        for (name, weight) in [("Goblin", 6), ("Orc", 3), ("Troll", 1)].iter() {
+            let expected = ROLLS * weight / 10;
+            let actual = counts[*name];
+            assert!(
+                (actual - expected).abs() < ROLLS / 50,
+                "{} rolled {} times, expected about {}",
+                name,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn first_entry_can_be_rolled() {
+        let table = RandomTable::new().add("Only", 1);
+        assert_eq!(roll_counts(&table)["Only"], ROLLS);
+    }
+
+    #[test]
+    fn non_positive_weights_are_skipped() {
+        let table = RandomTable::new().add("Never", 0).add("Nope", -3);
+        assert!(table.is_empty());
+        assert_eq!(table.total_weight(), 0);
+        assert_eq!(table.roll(&mut RandomNumberGenerator::seeded(1)), "None");
+    }
+}
@@ -0,0 +1,287 @@
+use super::{
+    components::*,
+    gamelog::{GameLog, TurnCounter},
+    melee_combat_system::defense_bonus,
+    particle_system::ParticleBuilder,
+    spawner::SpawnRequests,
+    Map,
+};
+use rltk::{RandomNumberGenerator, BLACK, ORANGE, RGB};
+use specs::{prelude::*, storage::MaskedStorage};
+use std::ops::Deref;
+
+/// The ranged [`Weapon`] `ent` has equipped, if it has one.
+pub fn ranged_weapon(
+    ent: Entity,
+    entities: &Entities,
+    weapons: &ReadStorage<Weapon>,
+    equipped: &ReadStorage<Equipped>,
+) -> Option<Entity> {
+    (entities, weapons, equipped)
+        .join()
+        .find(|(_, weapon, equipped_by)| {
+            equipped_by.owner == ent && weapon.attack_kind == AttackKind::Ranged
+        })
+        .map(|(item, _, _)| item)
+}
+
+/// A bundle of `kind` ammo in `owner`'s backpack, if they have any left.
+///
+/// Takes the ammo storage for reading or writing, so callers can pass whichever they hold.
+pub fn ammo_bundle<D: Deref<Target = MaskedStorage<Ammo>>>(
+    owner: Entity,
+    kind: AmmoKind,
+    entities: &Entities,
+    ammo: &Storage<Ammo, D>,
+    backpack: &ReadStorage<InBackpack>,
+) -> Option<Entity> {
+    (entities, ammo, backpack)
+        .join()
+        .find(|(_, bundle, pack)| pack.owner == owner && bundle.kind == kind && bundle.count > 0)
+        .map(|(bundle, _, _)| bundle)
+}
+
+/// One in this many spent shots can be picked up again where they landed.
+const RECOVERY_ODDS: i32 = 2;
+
+/// Fires equipped ranged weapons at the tiles their wielders picked out.
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Weapon>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, DefenseBonus>,
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, ParticleBuilder>,
+        WriteStorage<'a, Ammo>,
+        ReadStorage<'a, InBackpack>,
+        WriteExpect<'a, SpawnRequests>,
+        WriteExpect<'a, RandomNumberGenerator>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut log,
+            turn_counter,
+            map,
+            mut shooting,
+            weapons,
+            equipped,
+            names,
+            combat_stats,
+            defense_bonuses,
+            mut inflict_damage,
+            mut particle_builder,
+            mut ammo,
+            backpack,
+            mut spawn_requests,
+            mut rng,
+        ) = data;
+
+        for (ent, shot, name) in (&entities, &shooting, &names).join() {
+            let weapon = match ranged_weapon(ent, &entities, &weapons, &equipped) {
+                Some(weapon) => weapons.get(weapon).unwrap(),
+                None => continue,
+            };
+
+            // Use up a shot, if the weapon needs them.
+            if let Some(kind) = weapon.ammo {
+                let bundle = match ammo_bundle(ent, kind, &entities, &ammo, &backpack) {
+                    Some(bundle) => bundle,
+                    None => {
+                        log.push(
+                            turn_counter.turn,
+                            format!("{} is out of {}.", &name.name, kind.name().to_lowercase()),
+                        );
+                        continue;
+                    }
+                };
+                let left = {
+                    let bundle_ammo = ammo.get_mut(bundle).unwrap();
+                    bundle_ammo.count -= 1;
+                    bundle_ammo.count
+                };
+                if left == 0 {
+                    entities
+                        .delete(bundle)
+                        .expect("Unable to delete empty ammo");
+                }
+                if rng.roll_dice(1, RECOVERY_ODDS) == 1 {
+                    let spent = match kind {
+                        AmmoKind::Arrow => "Spent Arrow",
+                        AmmoKind::SlingStone => "Spent Sling Stone",
+                    };
+                    spawn_requests.request(shot.target.x, shot.target.y, spent);
+                }
+            }
+
+            particle_builder.request(
+                shot.target.x,
+                shot.target.y,
+                RGB::named(ORANGE),
+                RGB::named(BLACK),
+                rltk::to_cp437('*'),
+                200.0,
+            );
+
+            // Shoot whoever's standing on the tile, if anyone.
+            let idx = map.xy_idx(shot.target.x, shot.target.y);
+            let target = map.tile_content[idx]
+                .iter()
+                .find(|target| combat_stats.get(**target).map_or(false, |s| s.hp > 0));
+            let target = match target {
+                Some(target) => *target,
+                None => {
+                    log.push(
+                        turn_counter.turn,
+                        format!("{}'s shot hits nothing.", &name.name),
+                    );
+                    continue;
+                }
+            };
+
+            let target_name = &names.get(target).unwrap().name;
+            let target_stats = combat_stats.get(target).unwrap();
+            let damage = i32::max(
+                0,
+                weapon.damage
+                    - (target_stats.defense + defense_bonus(target, &defense_bonuses, &equipped)),
+            );
+            if damage == 0 {
+                log.push(
+                    turn_counter.turn,
+                    format!("{}'s shot glances off {}.", &name.name, target_name),
+                );
+            } else {
+                log.push(
+                    turn_counter.turn,
+                    format!(
+                        "{} shoots {} for {} {} damage.",
+                        &name.name,
+                        target_name,
+                        damage,
+                        DamageType::Physical.name()
+                    ),
+                );
+                SufferDamage::new_damage(&mut inflict_damage, target, damage, DamageType::Physical);
+            }
+        }
+        shooting.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 10x10 world with an archer holding a bow that shoots arrows.
+    ///
+    /// Returns the world, the archer and the bow.
+    fn world_with_archer() -> (World, Entity, Entity) {
+        let mut ecs = World::new();
+        crate::register_components(&mut ecs);
+        ecs.insert(Map::new(1, 10, 10));
+        ecs.insert(GameLog::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(ParticleBuilder::new());
+        ecs.insert(SpawnRequests::default());
+        ecs.insert(RandomNumberGenerator::seeded(1));
+
+        let archer = ecs
+            .create_entity()
+            .with(Name {
+                name: "Archer".to_string(),
+            })
+            .build();
+        let bow = ecs
+            .create_entity()
+            .with(Weapon {
+                attack_kind: AttackKind::Ranged,
+                damage: 4,
+                range: 6,
+                ammo: Some(AmmoKind::Arrow),
+            })
+            .with(Equipped {
+                owner: archer,
+                slot: EquipmentSlot::TwoHanded,
+            })
+            .build();
+        (ecs, archer, bow)
+    }
+
+    fn aim_at(ecs: &mut World, archer: Entity, x: i32, y: i32) {
+        ecs.write_storage::<WantsToShoot>()
+            .insert(
+                archer,
+                WantsToShoot {
+                    target: rltk::Point::new(x, y),
+                },
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn shots_hit_whoever_is_on_the_target_tile() {
+        let (mut ecs, archer, bow) = world_with_archer();
+        let orc = ecs
+            .create_entity()
+            .with(Name {
+                name: "Orc".to_string(),
+            })
+            .with(CombatStats {
+                max_hp: 10,
+                hp: 10,
+                defense: 1,
+                power: 3,
+            })
+            .build();
+        let quiver = ecs
+            .create_entity()
+            .with(Ammo {
+                kind: AmmoKind::Arrow,
+                count: 1,
+            })
+            .with(InBackpack { owner: archer })
+            .build();
+        {
+            let mut map = ecs.fetch_mut::<Map>();
+            let orc_idx = map.xy_idx(5, 5);
+            map.tile_content[orc_idx].push(orc);
+        }
+        aim_at(&mut ecs, archer, 5, 5);
+
+        RangedCombatSystem {}.run_now(&ecs);
+        ecs.maintain();
+
+        let damage = ecs.read_storage::<SufferDamage>();
+        assert_eq!(
+            damage.get(orc).unwrap().amount,
+            vec![(3, DamageType::Physical)]
+        );
+        assert!(ecs.read_storage::<WantsToShoot>().get(archer).is_none());
+        assert!(ecs.entities().is_alive(bow));
+        // The last arrow was used up.
+        assert!(!ecs.entities().is_alive(quiver));
+    }
+
+    #[test]
+    fn bows_without_arrows_do_not_fire() {
+        let (mut ecs, archer, _) = world_with_archer();
+        aim_at(&mut ecs, archer, 5, 5);
+
+        RangedCombatSystem {}.run_now(&ecs);
+
+        let log = ecs.fetch::<GameLog>();
+        assert_eq!(log.entries.last().unwrap().text, "Archer is out of arrows.");
+    }
+}
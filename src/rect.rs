@@ -1,5 +1,13 @@
+use rltk::Point;
 use serde::{Deserialize, Serialize};
 
+use super::Position;
+
+/// An axis-aligned rectangle running from `(x1, y1)` at the top left to `(x2, y2)` at the
+/// bottom right, edges included.
+///
+/// A room carved from a rect only takes the tiles inside its top and left edges (see
+/// [`Rect::iter`]), so those edges are left as the room's walls.
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub struct Rect {
     pub x1: i32,
@@ -18,12 +26,36 @@ impl Rect {
         }
     }
 
+    /// Whether the two rects overlap, counting edges: rects that only touch intersect too.
+    ///
+    /// Rooms that touch would be carved with no wall between them, so builders treat them
+    /// as overlapping.
     pub fn intersect(&self, other: &Rect) -> bool {
         self.x1 <= other.x2 && self.x2 >= other.x1 && self.y1 <= other.y2 && self.y2 >= other.y1
     }
 
-    pub fn center(&self) -> (i32, i32) {
-        ((self.x1 + self.x2) / 2, (self.y1 + self.y2) / 2)
+    /// Whether `(x, y)` lies within the rect, edges included.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x1 && x <= self.x2 && y >= self.y1 && y <= self.y2
+    }
+
+    /// The points a room carved from this rect covers, row by row: everything inside the top
+    /// and left edges, up to and including the bottom and right ones.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, i32)> {
+        let (x1, x2) = (self.x1, self.x2);
+        (self.y1 + 1..=self.y2).flat_map(move |y| (x1 + 1..=x2).map(move |x| (x, y)))
+    }
+
+    pub fn center(&self) -> Position {
+        Position {
+            x: (self.x1 + self.x2) / 2,
+            y: (self.y1 + self.y2) / 2,
+        }
+    }
+
+    /// The rect's center as a [`Point`], for handing to rltk.
+    pub fn center_point(&self) -> Point {
+        self.center().into()
     }
 
     pub fn width(&self) -> i32 {
@@ -34,3 +66,54 @@ impl Rect {
         i32::abs(self.y1 - self.y2)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touching_rects_intersect_but_separate_ones_do_not() {
+        let room = Rect::new(0, 0, 5, 5);
+        assert!(room.intersect(&Rect::new(2, 2, 5, 5)));
+        // Sharing an edge (or just a corner) still counts.
+        assert!(room.intersect(&Rect::new(5, 0, 5, 5)));
+        assert!(room.intersect(&Rect::new(5, 5, 3, 3)));
+        // One tile apart leaves room for a wall.
+        assert!(!room.intersect(&Rect::new(6, 0, 5, 5)));
+        assert!(!room.intersect(&Rect::new(0, 6, 5, 5)));
+        // Containment works both ways.
+        assert!(room.intersect(&Rect::new(1, 1, 2, 2)));
+        assert!(Rect::new(1, 1, 2, 2).intersect(&room));
+    }
+
+    #[test]
+    fn contains_includes_edges_and_iter_skips_the_top_left_walls() {
+        let room = Rect::new(2, 3, 3, 2);
+        assert!(room.contains(2, 3));
+        assert!(room.contains(5, 5));
+        assert!(!room.contains(6, 5));
+        assert!(!room.contains(5, 2));
+
+        let points: Vec<(i32, i32)> = room.iter().collect();
+        assert_eq!(points.len(), (room.width() * room.height()) as usize);
+        assert_eq!(points.first(), Some(&(3, 4)));
+        assert_eq!(points.last(), Some(&(5, 5)));
+        assert!(points.iter().all(|(x, y)| room.contains(*x, *y)));
+        assert!(!points.contains(&(2, 3)));
+    }
+
+    #[test]
+    fn centers_convert_between_positions_points_and_tuples() {
+        let room = Rect::new(2, 4, 6, 4);
+        let center = room.center();
+        assert_eq!(center, Position { x: 5, y: 6 });
+        assert_eq!(room.center_point(), Point::new(5, 6));
+
+        assert_eq!(Position::from(Point::from(center)), center);
+        assert_eq!(Position::from(<(i32, i32)>::from(center)), center);
+        assert_eq!(
+            Point::from(Position::from(Point::new(-3, 7))),
+            Point::new(-3, 7)
+        );
+    }
+}
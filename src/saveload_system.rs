@@ -1,4 +1,8 @@
-use super::{components::*, Map};
+use super::{
+    components::*, damage_system::GuardianSlain, difficulty::Difficulty, gamelog::TurnCounter,
+    hotbar, seed::GameSeed, Map,
+};
+use serde::{Deserialize, Serialize};
 use specs::{
     error::NoError,
     prelude::*,
@@ -7,7 +11,7 @@ use specs::{
         SimpleMarkerAllocator,
     },
 };
-use std::{fs, fs::File, path::Path};
+use std::io::Write;
 
 macro_rules! serialize_individually {
     ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
@@ -17,87 +21,374 @@ macro_rules! serialize_individually {
             &$data.0,
             &$data.1,
             &mut $ser,
-        )
-        .unwrap();
+        )?;
         )*
     };
 }
 
-pub fn does_save_exist() -> bool {
-    Path::new("./savegame.json").exists()
+/// Slot used when nothing else has been picked; it keeps the original `savegame.json` name.
+pub const DEFAULT_SLOT: usize = 0;
+/// Number of save slots offered by the load menu.
+pub const SAVE_SLOTS: usize = 5;
+
+/// Version of the save format written by this build.
+///
+/// Bump this whenever the saved components (or their fields) change, so older saves are
+/// turned away with a message rather than failing partway through loading.
+pub const SAVE_VERSION: u32 = 6;
+
+/// Resource tracking which slot the current run saves to.
+#[derive(Default)]
+pub struct SaveSlot {
+    pub slot: usize,
+}
+
+/// Summary written at the start of each save file, so slots can be listed without loading them.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct SaveHeader {
+    /// Save format the file was written with; saves from before versioning read as 0.
+    #[serde(default)]
+    pub version: u32,
+    pub depth: i32,
+    pub turn: i32,
+    pub seed: GameSeed,
+}
+
+impl SaveHeader {
+    /// Whether this build can load the save.
+    pub fn is_compatible(&self) -> bool {
+        self.version == SAVE_VERSION
+    }
+}
+
+/// Why a game couldn't be saved or loaded.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The save file couldn't be read or written.
+    Io(std::io::Error),
+    /// The save file isn't valid, e.g. because it was cut short.
+    Corrupt(String),
+    /// The save was written with a different version of the save format.
+    Incompatible { found: u32 },
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            SaveError::Io(e.into())
+        } else {
+            SaveError::Corrupt(e.to_string())
+        }
+    }
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "Unable to access save: {}", e),
+            SaveError::Corrupt(reason) => write!(f, "Corrupt save: {}", reason),
+            SaveError::Incompatible { found } => write!(
+                f,
+                "Incompatible save: written with format version {}, but this game reads version {}",
+                found, SAVE_VERSION
+            ),
+        }
+    }
+}
+
+/// Somewhere saves can be kept, by name.
+///
+/// Every backend stores exactly the JSON [`save_game`] writes, so a save can be carried from
+/// one to another.
+pub trait SaveBackend {
+    /// Contents of the save called `name`, or `None` if there isn't one.
+    fn read(&self, name: &str) -> Result<Option<String>, SaveError>;
+    /// Stores `data` as the save called `name`, replacing any that's already there.
+    fn write(&self, name: &str, data: &str) -> Result<(), SaveError>;
+    /// Deletes the save called `name`, if there is one.
+    fn remove(&self, name: &str) -> Result<(), SaveError>;
+
+    fn exists(&self, name: &str) -> bool {
+        matches!(self.read(name), Ok(Some(_)))
+    }
+}
+
+/// Keeps saves as files in the working directory.
+pub struct FileBackend;
+
+impl FileBackend {
+    fn path(name: &str) -> std::path::PathBuf {
+        std::path::Path::new(".").join(name)
+    }
+}
+
+impl SaveBackend for FileBackend {
+    fn read(&self, name: &str) -> Result<Option<String>, SaveError> {
+        match std::fs::read_to_string(FileBackend::path(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, name: &str, data: &str) -> Result<(), SaveError> {
+        Ok(std::fs::write(FileBackend::path(name), data)?)
+    }
+
+    fn remove(&self, name: &str) -> Result<(), SaveError> {
+        let path = FileBackend::path(name);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        FileBackend::path(name).exists()
+    }
 }
 
-pub fn delete_save() {
-    if Path::new("./savegame.json").exists() {
-        std::fs::remove_file("./savegame.json").expect("Unable to delete file");
+/// Keeps saves in the browser's local storage, for the web build.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorageBackend;
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorageBackend {
+    fn storage() -> Result<web_sys::Storage, SaveError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| LocalStorageBackend::error("local storage is unavailable"))
+    }
+
+    fn error<E: std::fmt::Debug>(e: E) -> SaveError {
+        SaveError::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("{:?}", e),
+        ))
     }
 }
 
 #[cfg(target_arch = "wasm32")]
-pub fn save_game(_ecs: &mut World) {}
+impl SaveBackend for LocalStorageBackend {
+    fn read(&self, name: &str) -> Result<Option<String>, SaveError> {
+        LocalStorageBackend::storage()?
+            .get_item(name)
+            .map_err(LocalStorageBackend::error)
+    }
+
+    fn write(&self, name: &str, data: &str) -> Result<(), SaveError> {
+        LocalStorageBackend::storage()?
+            .set_item(name, data)
+            .map_err(LocalStorageBackend::error)
+    }
 
+    fn remove(&self, name: &str) -> Result<(), SaveError> {
+        LocalStorageBackend::storage()?
+            .remove_item(name)
+            .map_err(LocalStorageBackend::error)
+    }
+}
+
+/// Where this build keeps its saves.
 #[cfg(not(target_arch = "wasm32"))]
-pub fn save_game(ecs: &mut World) {
+fn backend() -> FileBackend {
+    FileBackend
+}
+
+#[cfg(target_arch = "wasm32")]
+fn backend() -> LocalStorageBackend {
+    LocalStorageBackend
+}
+
+/// Name the save in `slot` is kept under.
+pub fn save_name(slot: usize) -> String {
+    if slot == DEFAULT_SLOT {
+        "savegame.json".to_string()
+    } else {
+        format!("savegame_{}.json", slot)
+    }
+}
+
+pub fn does_save_exist(slot: usize) -> bool {
+    backend().exists(&save_name(slot))
+}
+
+/// Whether any slot has a save in it.
+pub fn any_save_exists() -> bool {
+    (0..SAVE_SLOTS).any(does_save_exist)
+}
+
+/// First slot without a save in it, if there is one.
+pub fn first_free_slot() -> Option<usize> {
+    (0..SAVE_SLOTS).find(|slot| !does_save_exist(*slot))
+}
+
+/// Resource holding the headers of the filled save slots, read once when a slot menu opens
+/// rather than on every frame it's drawn.
+#[derive(Default)]
+pub struct SaveSlotHeaders {
+    pub headers: Vec<(usize, SaveHeader)>,
+}
+
+impl SaveSlotHeaders {
+    /// Reads the header of every slot that has a save in it.
+    pub fn read() -> SaveSlotHeaders {
+        SaveSlotHeaders {
+            headers: (0..SAVE_SLOTS)
+                .filter_map(|slot| read_save_header(slot).map(|header| (slot, header)))
+                .collect(),
+        }
+    }
+}
+
+/// Reads just the header of the save in `slot`.
+pub fn read_save_header(slot: usize) -> Option<SaveHeader> {
+    let data = backend().read(&save_name(slot)).ok()??;
+    let mut de = serde_json::Deserializer::from_str(&data);
+    SaveHeader::deserialize(&mut de).ok()
+}
+
+pub fn delete_save(slot: usize) {
+    backend()
+        .remove(&save_name(slot))
+        .expect("Unable to delete save");
+}
+
+pub fn save_game(ecs: &mut World, slot: usize) -> Result<(), SaveError> {
+    let mut data = Vec::new();
+    write_save(ecs, &mut data)?;
+    let data = String::from_utf8(data).expect("Save isn't valid UTF-8");
+    backend().write(&save_name(slot), &data)
+}
+
+/// Saves the game to `writer`, along with a helper entity carrying the map and resources.
+fn write_save<W: Write>(ecs: &mut World, writer: W) -> Result<(), SaveError> {
     // Create helper with copy of the game map
-    let mapcopy = ecs.get_mut::<Map>().unwrap().clone();
+    let mapcopy = (*ecs.fetch::<Map>()).clone();
+    let seed = *ecs.fetch::<GameSeed>();
+    let difficulty = *ecs.fetch::<Difficulty>();
+    let rng = (*ecs.fetch::<rltk::RandomNumberGenerator>()).clone();
+    let guardian_slain = ecs.fetch::<GuardianSlain>().slain;
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper {
+            map: mapcopy,
+            seed,
+            difficulty,
+            rng: Some(rng),
+            guardian_slain,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
+    hotbar::tag_bound_items(ecs);
 
     // Serialize
-    {
-        let data = (
-            ecs.entities(),
-            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
-        );
-        let writer = File::create("./savegame.json").unwrap();
-        let mut serializer = serde_json::Serializer::new(writer);
-        serialize_individually!(
-            ecs,
-            serializer,
-            data,
-            Position,
-            Renderable,
-            Player,
-            Viewshed,
-            Monster,
-            Name,
-            BlocksTile,
-            CombatStats,
-            SufferDamage,
-            WantsToMelee,
-            Item,
-            Consumable,
-            Ranged,
-            InflictsDamage,
-            AreaOfEffect,
-            Confusion,
-            ProvidesHealing,
-            InBackpack,
-            WantsToPickupItem,
-            WantsToUseItem,
-            WantsToDropItem,
-            WantsToRemoveItem,
-            SerializationHelper,
-            Equippable,
-            Equipped,
-            MeleePowerBonus,
-            DefenseBonus,
-            ParticleLifetime,
-            HungerClock,
-            ProvidesFood,
-            MagicMapper,
-            Hidden,
-            EntryTrigger,
-            EntityMoved,
-            SingleActivation
-        );
-    }
+    let written = serialize_world(ecs, writer);
 
-    // Clean up
+    // Clean up, whether or not the save went through.
     ecs.delete_entity(savehelper).expect("Crash on cleanup");
+    ecs.write_storage::<HotbarSlot>().clear();
+    written
+}
+
+/// Writes the header followed by every marked entity (including the helper) to `writer`.
+fn serialize_world<W: Write>(ecs: &World, writer: W) -> Result<(), SaveError> {
+    let data = (
+        ecs.entities(),
+        ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+    );
+    let header = SaveHeader {
+        version: SAVE_VERSION,
+        depth: ecs.fetch::<Map>().depth,
+        turn: ecs.fetch::<TurnCounter>().turn,
+        seed: *ecs.fetch::<GameSeed>(),
+    };
+    let mut serializer = serde_json::Serializer::new(writer);
+    header.serialize(&mut serializer)?;
+    serialize_individually!(
+        ecs,
+        serializer,
+        data,
+        Position,
+        Renderable,
+        Player,
+        Viewshed,
+        Monster,
+        Faction,
+        Name,
+        BlocksTile,
+        CombatStats,
+        SufferDamage,
+        WantsToMelee,
+        Item,
+        Consumable,
+        Ranged,
+        InflictsDamage,
+        AreaOfEffect,
+        Confusion,
+        ProvidesHealing,
+        InBackpack,
+        WantsToPickupItem,
+        WantsToUseItem,
+        WantsToDropItem,
+        WantsToRemoveItem,
+        SerializationHelper,
+        Equippable,
+        Equipped,
+        MeleePowerBonus,
+        DefenseBonus,
+        ParticleLifetime,
+        HungerClock,
+        ProvidesFood,
+        MagicMapper,
+        Hidden,
+        EntryTrigger,
+        EntityMoved,
+        SingleActivation,
+        TeleportsVictim,
+        RaisesAlarm,
+        Alerted,
+        CanFlee,
+        AiState,
+        Resistances,
+        Telepathy,
+        SummonsMinions,
+        WinsGame,
+        LeavesCorpse,
+        LifeDrain,
+        StatDrain,
+        SplitOnHit,
+        Cleave,
+        LootTable,
+        Speed,
+        Gold,
+        Currency,
+        Price,
+        Vendor,
+        Lingers,
+        Poisoned,
+        CuresStatus,
+        Durability,
+        RepairsEquipment,
+        PackCapacity,
+        TracksByScent,
+        Immunities,
+        Sprung,
+        AnimatedRenderable,
+        Knockback,
+        Weapon,
+        WantsToShoot,
+        Ammo,
+        Guardian,
+        HotbarSlot
+    );
+    Ok(())
 }
 
 macro_rules! deserialize_individually {
@@ -109,13 +400,55 @@ macro_rules! deserialize_individually {
             &mut $data.1, // marker
             &mut $data.2, // allocator
             &mut $de,
-        )
-        .unwrap();
+        )?;
         )*
     };
 }
 
-pub fn load_game(ecs: &mut World) {
+/// Loads the save in `slot`.
+///
+/// A corrupt save is moved aside (to the same name with `.corrupt` on the end) so it stops
+/// showing up in the load menu, but stays around to be looked at.
+pub fn load_game(ecs: &mut World, slot: usize) -> Result<(), SaveError> {
+    let backend = backend();
+    let name = save_name(slot);
+    let data = backend.read(&name)?.ok_or_else(|| {
+        SaveError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} doesn't exist", name),
+        ))
+    })?;
+    if let Err(e) = read_save(ecs, &data) {
+        if let SaveError::Corrupt(_) = e {
+            backend.write(&format!("{}.corrupt", name), &data)?;
+            backend.remove(&name)?;
+        }
+        return Err(e);
+    }
+    // Further saves from this run go back into the same slot.
+    ecs.write_resource::<SaveSlot>().slot = slot;
+    Ok(())
+}
+
+/// Replaces every entity in the world with the ones saved in `data`.
+///
+/// Saves written with another format version, or that aren't valid JSON, are refused
+/// before the world is touched.
+fn read_save(ecs: &mut World, data: &str) -> Result<(), SaveError> {
+    let mut de = serde_json::Deserializer::from_str(data);
+
+    // The header only matters for listing slots, except for the version and turn count.
+    let header = SaveHeader::deserialize(&mut de)?;
+    if !header.is_compatible() {
+        return Err(SaveError::Incompatible {
+            found: header.version,
+        });
+    }
+    // Check the rest of the file parses before throwing away the current game.
+    for value in serde_json::Deserializer::from_str(data).into_iter::<serde::de::IgnoredAny>() {
+        value?;
+    }
+
     // Two-step iteration of entities, deleting all entities in the game.
     {
         // Vec to store entities from the first pass, to delete from in the second.
@@ -130,9 +463,7 @@ pub fn load_game(ecs: &mut World) {
         }
     }
 
-    // Open the savegame path and attach the deserializer.
-    let data = fs::read_to_string("./savegame.json").unwrap();
-    let mut de = serde_json::Deserializer::from_str(&data);
+    ecs.write_resource::<TurnCounter>().turn = header.turn;
 
     {
         // Build deserialize macro tuple.
@@ -153,6 +484,7 @@ pub fn load_game(ecs: &mut World) {
             Player,
             Viewshed,
             Monster,
+            Faction,
             Name,
             BlocksTile,
             CombatStats,
@@ -182,7 +514,43 @@ pub fn load_game(ecs: &mut World) {
             Hidden,
             EntryTrigger,
             EntityMoved,
-            SingleActivation
+            SingleActivation,
+            TeleportsVictim,
+            RaisesAlarm,
+            Alerted,
+            CanFlee,
+            AiState,
+            Resistances,
+            Telepathy,
+            SummonsMinions,
+            WinsGame,
+            LeavesCorpse,
+            LifeDrain,
+            StatDrain,
+            SplitOnHit,
+            Cleave,
+            LootTable,
+            Speed,
+            Gold,
+            Currency,
+            Price,
+            Vendor,
+            Lingers,
+            Poisoned,
+            CuresStatus,
+            Durability,
+            RepairsEquipment,
+            PackCapacity,
+            TracksByScent,
+            Immunities,
+            Sprung,
+            AnimatedRenderable,
+            Knockback,
+            Weapon,
+            WantsToShoot,
+            Ammo,
+            Guardian,
+            HotbarSlot
         );
     }
 
@@ -199,7 +567,15 @@ pub fn load_game(ecs: &mut World) {
             let mut worldmap = ecs.write_resource::<Map>();
             *worldmap = h.map.clone();
             // `tile_content` isn't serialized, so replace with empty set of vectors.
-            worldmap.tile_content = vec![Vec::new(); super::MAPCOUNT];
+            worldmap.tile_content = vec![Vec::new(); (worldmap.width * worldmap.height) as usize];
+            // Keep track of the seed the saved run was started from.
+            *ecs.write_resource::<GameSeed>() = h.seed;
+            *ecs.write_resource::<Difficulty>() = h.difficulty;
+            // Saves from before the RNG was stored just keep rolling the current one.
+            if let Some(rng) = &h.rng {
+                *ecs.write_resource::<rltk::RandomNumberGenerator>() = rng.clone();
+            }
+            ecs.write_resource::<GuardianSlain>().slain = h.guardian_slain;
             deleteme = Some(e);
         }
 
@@ -213,6 +589,118 @@ pub fn load_game(ecs: &mut World) {
         }
     }
 
-    ecs.delete_entity(deleteme.unwrap())
-        .expect("Unable to delete helper");
+    let helper = deleteme.ok_or_else(|| SaveError::Corrupt("no map in save".to_string()))?;
+    ecs.delete_entity(helper).expect("Unable to delete helper");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rltk::RandomNumberGenerator;
+
+    fn rolls(ecs: &World) -> Vec<i32> {
+        let mut rng = ecs.fetch_mut::<RandomNumberGenerator>();
+        (0..10).map(|_| rng.roll_dice(1, 1000)).collect()
+    }
+
+    fn save_world() -> World {
+        let mut ecs = World::new();
+        crate::register_components(&mut ecs);
+        let seed = GameSeed::from_text("save me");
+        ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+        ecs.insert(Map::new(1, 20, 20));
+        ecs.insert(rltk::Point::new(3, 3));
+        ecs.insert(seed.rng());
+        ecs.insert(seed);
+        ecs.insert(Difficulty::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(GuardianSlain::default());
+        ecs.insert(hotbar::Hotbar::default());
+        let player = ecs
+            .create_entity()
+            .with(Player {})
+            .with(Position { x: 3, y: 3 })
+            .marked::<SimpleMarker<SerializeMe>>()
+            .build();
+        ecs.insert(player);
+        ecs
+    }
+
+    #[test]
+    fn rng_carries_on_after_loading() {
+        let mut ecs = save_world();
+
+        // Use up some of the sequence before saving.
+        rolls(&ecs);
+        let mut saved = Vec::new();
+        write_save(&mut ecs, &mut saved).unwrap();
+        ecs.maintain();
+        let control = rolls(&ecs);
+
+        read_save(&mut ecs, std::str::from_utf8(&saved).unwrap()).unwrap();
+        assert_eq!(rolls(&ecs), control);
+    }
+
+    #[test]
+    fn hotbar_bindings_survive_a_reload() {
+        let mut ecs = save_world();
+        let player = *ecs.fetch::<Entity>();
+        let potion = ecs
+            .create_entity()
+            .with(Name {
+                name: "Health Potion".to_string(),
+            })
+            .with(InBackpack { owner: player })
+            .marked::<SimpleMarker<SerializeMe>>()
+            .build();
+        ecs.write_resource::<hotbar::Hotbar>()
+            .assign(2, potion, "Health Potion");
+        let mut saved = Vec::new();
+        write_save(&mut ecs, &mut saved).unwrap();
+        ecs.maintain();
+
+        read_save(&mut ecs, std::str::from_utf8(&saved).unwrap()).unwrap();
+        hotbar::restore_bindings(&ecs);
+
+        assert_eq!(ecs.fetch::<hotbar::Hotbar>().name(2), Some("Health Potion"));
+        assert!(hotbar::hotbar_item(&ecs, 2).is_some());
+        assert!(ecs.read_storage::<HotbarSlot>().is_empty());
+    }
+
+    #[test]
+    fn saves_from_another_version_are_refused() {
+        let mut ecs = save_world();
+        let player = *ecs.fetch::<Entity>();
+        let header = SaveHeader {
+            version: SAVE_VERSION + 1,
+            depth: 3,
+            turn: 10,
+            seed: GameSeed::from_text("the future"),
+        };
+        let saved = serde_json::to_string(&header).unwrap();
+
+        match read_save(&mut ecs, &saved) {
+            Err(SaveError::Incompatible { found }) => assert_eq!(found, SAVE_VERSION + 1),
+            other => panic!("expected an incompatible save, got {:?}", other),
+        }
+        // The current game is left alone.
+        assert!(ecs.entities().is_alive(player));
+    }
+
+    #[test]
+    fn truncated_saves_are_reported_as_corrupt() {
+        let mut ecs = save_world();
+        let player = *ecs.fetch::<Entity>();
+        let mut saved = Vec::new();
+        write_save(&mut ecs, &mut saved).unwrap();
+        ecs.maintain();
+        let truncated = std::str::from_utf8(&saved[..saved.len() / 2]).unwrap();
+
+        match read_save(&mut ecs, truncated) {
+            Err(SaveError::Corrupt(_)) => {}
+            other => panic!("expected a corrupt save, got {:?}", other),
+        }
+        assert!(ecs.entities().is_alive(player));
+    }
 }
@@ -0,0 +1,125 @@
+use rltk::{BaseMap, DijkstraMap, Point};
+use specs::prelude::*;
+
+use super::{Map, RunState};
+
+/// How strong the scent is on the player's own tile.
+const SCENT_STRENGTH: f32 = 20.0;
+/// How much scent fades each turn.
+const SCENT_DECAY: f32 = 1.0;
+/// How far (in steps) fresh scent carries from the player.
+const SCENT_RANGE: f32 = 6.0;
+
+/// Resource holding how strongly the player can be smelled on each tile.
+///
+/// Every turn the old scent fades and fresh scent spreads out from the player, so the
+/// player leaves behind a trail that gets stronger the closer it is to where they are now.
+#[derive(Default, Clone, Debug)]
+pub struct ScentMap {
+    pub scent: Vec<f32>,
+}
+
+impl ScentMap {
+    /// Fades the old scent and lays down fresh scent around `origin`.
+    pub fn update(&mut self, map: &Map, origin: usize) {
+        if self.scent.len() != map.tiles.len() {
+            self.scent = vec![0.0; map.tiles.len()];
+        }
+        self.scent
+            .iter_mut()
+            .for_each(|scent| *scent = f32::max(0.0, *scent - SCENT_DECAY));
+
+        let fresh = DijkstraMap::new(
+            map.width as usize,
+            map.height as usize,
+            &[origin],
+            map,
+            SCENT_RANGE,
+        );
+        for (idx, distance) in fresh.map.iter().enumerate() {
+            if *distance <= SCENT_RANGE {
+                self.scent[idx] = f32::max(self.scent[idx], SCENT_STRENGTH - distance);
+            }
+        }
+    }
+
+    /// The open tile next to `idx` with the strongest scent, if it's stronger than at `idx`.
+    pub fn follow(&self, map: &Map, idx: usize) -> Option<usize> {
+        let here = *self.scent.get(idx)?;
+        map.get_available_exits(idx)
+            .iter()
+            .map(|(exit, _)| *exit)
+            .filter(|exit| self.scent[*exit] > here)
+            .fold(None, |best: Option<usize>, exit| match best {
+                Some(best) if self.scent[best] >= self.scent[exit] => Some(best),
+                _ => Some(exit),
+            })
+    }
+}
+
+/// Lays the player's scent down once per turn.
+pub struct ScentSystem {}
+
+impl<'a> System<'a> for ScentSystem {
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Point>,
+        WriteExpect<'a, ScentMap>,
+        ReadExpect<'a, RunState>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, player_pos, mut scent, runstate) = data;
+
+        // Systems run on both halves of a turn; only update once per turn.
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        scent.update(&map, map.xy_idx(player_pos.x, player_pos.y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracker_converges_on_a_hidden_player() {
+        // The tracker starts below the wall, around a corner from the player and out of sight.
+        let rows = [
+            "############",
+            "#@.........#",
+            "#..........#",
+            "#########..#",
+            "#..........#",
+            "############",
+        ];
+        let (mut map, start) = Map::from_ascii(&rows.join("\n")).unwrap();
+        let start = start.unwrap();
+        let player_idx = map.xy_idx(start.x, start.y);
+        let mut tracker_idx = map.xy_idx(5, 4);
+        map.blocked[tracker_idx] = true;
+
+        let mut scent = ScentMap::default();
+        // The player paces back along their route before settling, leaving a trail.
+        for x in (1..11).rev() {
+            scent.update(&map, map.xy_idx(x, 2));
+        }
+
+        for _ in 0..30 {
+            scent.update(&map, player_idx);
+            if let Some(step) = scent.follow(&map, tracker_idx) {
+                map.blocked[tracker_idx] = false;
+                tracker_idx = step;
+                map.blocked[tracker_idx] = true;
+            }
+        }
+
+        let (x, y) = (
+            tracker_idx as i32 % map.width,
+            tracker_idx as i32 / map.width,
+        );
+        assert!((x - start.x).abs() <= 1 && (y - start.y).abs() <= 1);
+    }
+}
@@ -0,0 +1,42 @@
+use rltk::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+/// Resource holding the seed the game's [`RandomNumberGenerator`] was started from.
+///
+/// Recording the seed lets a run (and its maps) be reproduced or shared.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct GameSeed {
+    pub seed: u64,
+}
+
+impl GameSeed {
+    /// Picks a fresh seed from entropy.
+    pub fn random() -> GameSeed {
+        GameSeed {
+            seed: RandomNumberGenerator::new().next_u64(),
+        }
+    }
+
+    /// Turns player-entered text into a seed.
+    ///
+    /// Numbers are used as-is; anything else is hashed with FNV-1a, which
+    /// (unlike `DefaultHasher`) is stable across builds and platforms.
+    pub fn from_text(text: &str) -> GameSeed {
+        let text = text.trim();
+        if let Ok(seed) = text.parse::<u64>() {
+            return GameSeed { seed };
+        }
+
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in text.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        GameSeed { seed: hash }
+    }
+
+    /// Creates a new RNG seeded from this seed.
+    pub fn rng(&self) -> RandomNumberGenerator {
+        RandomNumberGenerator::seeded(self.seed)
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+use crate::{difficulty::Difficulty, display::DisplaySettings, map_builder::MapgenVisualizer};
+
+const SETTINGS_FILE: &str = "./settings.json";
+
+/// Reads the on/off switch in environment variable `name`: `1` or `true` turns it on, `0` or
+/// `false` turns it off, and anything else (including leaving it unset) gives `default`.
+pub fn env_flag(name: &str, default: bool) -> bool {
+    match std::env::var(name) {
+        Ok(value) if value == "1" || value.eq_ignore_ascii_case("true") => true,
+        Ok(value) if value == "0" || value.eq_ignore_ascii_case("false") => false,
+        _ => default,
+    }
+}
+
+/// The lines of the options menu.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SettingsOption {
+    Scanlines,
+    StatusTint,
+    MapgenVisualizer,
+    Difficulty,
+    AutoPickup,
+}
+
+impl SettingsOption {
+    /// Every option, in the order the menu lists them.
+    pub const ALL: [SettingsOption; 5] = [
+        SettingsOption::Scanlines,
+        SettingsOption::StatusTint,
+        SettingsOption::MapgenVisualizer,
+        SettingsOption::Difficulty,
+        SettingsOption::AutoPickup,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SettingsOption::Scanlines => "Scanlines",
+            SettingsOption::StatusTint => "Status tint",
+            SettingsOption::MapgenVisualizer => "Show map generation",
+            SettingsOption::Difficulty => "Default difficulty",
+            SettingsOption::AutoPickup => "Auto-pickup",
+        }
+    }
+}
+
+/// Resource holding the player's preferences, set from the options menu and kept between runs.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct GameSettings {
+    pub display: DisplaySettings,
+    /// Play map generation back on screen before each level.
+    pub mapgen_visualizer: bool,
+    /// Difficulty picked out when starting a new game.
+    pub difficulty: Difficulty,
+    /// Pick up whatever's lying on a tile when walking onto it.
+    pub auto_pickup: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            display: DisplaySettings::default(),
+            mapgen_visualizer: MapgenVisualizer::default().enabled,
+            difficulty: Difficulty::default(),
+            auto_pickup: false,
+        }
+    }
+}
+
+impl GameSettings {
+    /// Flips `option` on or off; difficulty steps on to the next level instead.
+    pub fn toggle(&mut self, option: SettingsOption) {
+        match option {
+            SettingsOption::Scanlines => self.display.scanlines = !self.display.scanlines,
+            SettingsOption::StatusTint => self.display.status_tint = !self.display.status_tint,
+            SettingsOption::MapgenVisualizer => self.mapgen_visualizer = !self.mapgen_visualizer,
+            SettingsOption::Difficulty => self.difficulty = self.difficulty.next(),
+            SettingsOption::AutoPickup => self.auto_pickup = !self.auto_pickup,
+        }
+    }
+
+    /// How `option` is currently set, as shown in the menu.
+    pub fn value(&self, option: SettingsOption) -> &'static str {
+        let on_off = |on: bool| if on { "On" } else { "Off" };
+        match option {
+            SettingsOption::Scanlines => on_off(self.display.scanlines),
+            SettingsOption::StatusTint => on_off(self.display.status_tint),
+            SettingsOption::MapgenVisualizer => on_off(self.mapgen_visualizer),
+            SettingsOption::Difficulty => self.difficulty.name(),
+            SettingsOption::AutoPickup => on_off(self.auto_pickup),
+        }
+    }
+
+    /// Updates the resources the rest of the game reads its settings from.
+    pub fn apply(&self, ecs: &mut World) {
+        ecs.insert(self.display);
+        ecs.insert(MapgenVisualizer {
+            enabled: self.mapgen_visualizer,
+        });
+    }
+
+    /// Loads the settings file, falling back to the defaults if it's missing or unreadable.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> GameSettings {
+        std::fs::read_to_string(SETTINGS_FILE)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> GameSettings {
+        GameSettings::default()
+    }
+
+    /// Writes the settings file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) {
+        let json = serde_json::to_string_pretty(self).expect("Unable to serialize settings");
+        if let Err(e) = std::fs::write(SETTINGS_FILE, json) {
+            rltk::console::log(format!("Unable to write settings: {}", e));
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_settings_files_keep_defaults_for_new_options() {
+        let settings: GameSettings =
+            serde_json::from_str(r#"{ "display": { "scanlines": false } }"#).unwrap();
+        assert!(!settings.display.scanlines);
+        assert_eq!(settings.difficulty, Difficulty::Normal);
+        assert!(!settings.auto_pickup);
+    }
+}
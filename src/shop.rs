@@ -0,0 +1,70 @@
+use specs::prelude::*;
+
+use super::{
+    gamelog::{GameLog, TurnCounter},
+    inventory_system::pack_has_room,
+    spawner, Gold, InBackpack, Name, Position, Price,
+};
+
+/// Items sell for this fraction (one over) of their price.
+const SELL_DIVISOR: i32 = 2;
+
+/// What the shopkeeper will pay for `item`, if they want it at all.
+pub fn sale_price(ecs: &World, item: Entity) -> Option<i32> {
+    let names = ecs.read_storage::<Name>();
+    let name = names.get(item)?;
+    spawner::item_price(&name.name).map(|price| price / SELL_DIVISOR)
+}
+
+/// Buys the priced `item` for the player, if they can afford it and have room for it.
+pub fn buy(ecs: &mut World, item: Entity) {
+    let player_ent = *ecs.fetch::<Entity>();
+    let turn = ecs.fetch::<TurnCounter>().turn;
+    if !pack_has_room(ecs, player_ent) {
+        ecs.write_resource::<GameLog>()
+            .push(turn, "Your pack is full.");
+        return;
+    }
+    let mut log = ecs.write_resource::<GameLog>();
+    let mut prices = ecs.write_storage::<Price>();
+    let mut purses = ecs.write_storage::<Gold>();
+    let names = ecs.read_storage::<Name>();
+
+    let (price, purse) = match (prices.get(item), purses.get_mut(player_ent)) {
+        (Some(price), Some(purse)) => (price.value, purse),
+        _ => return,
+    };
+    let name = names.get(item).map_or("item", |n| n.name.as_str());
+    if purse.amount < price {
+        log.push(turn, format!("You can't afford the {}.", name));
+        return;
+    }
+
+    purse.amount -= price;
+    log.push(turn, format!("You buy the {} for {} gold.", name, price));
+    prices.remove(item);
+    ecs.write_storage::<Position>().remove(item);
+    ecs.write_storage::<InBackpack>()
+        .insert(item, InBackpack { owner: player_ent })
+        .expect("Unable to insert into backpack");
+}
+
+/// Sells `item` from the player's backpack to the shopkeeper.
+pub fn sell(ecs: &mut World, item: Entity) {
+    let price = match sale_price(ecs, item) {
+        Some(price) => price,
+        None => return,
+    };
+    {
+        let player_ent = *ecs.fetch::<Entity>();
+        let turn = ecs.fetch::<TurnCounter>().turn;
+        let names = ecs.read_storage::<Name>();
+        let name = names.get(item).map_or("item", |n| n.name.as_str());
+        if let Some(purse) = ecs.write_storage::<Gold>().get_mut(player_ent) {
+            purse.amount += price;
+        }
+        ecs.write_resource::<GameLog>()
+            .push(turn, format!("You sell the {} for {} gold.", name, price));
+    }
+    ecs.delete_entity(item).expect("Unable to delete sold item");
+}
@@ -3,17 +3,85 @@ use specs::{
     prelude::*,
     saveload::{MarkedBuilder, SimpleMarker},
 };
-use std::collections::HashMap;
 
-use crate::{Map, TileType};
+use crate::{
+    difficulty::Difficulty, hunger_system::WELL_FED_DURATION, monster_ai_system::NORMAL_SPEED, Map,
+    TileType,
+};
 
-use super::{components::*, random_table::RandomTable, Rect, MAPWIDTH};
+use super::{components::*, random_table::RandomTable, Rect};
 
 const MAX_MONSTERS: i32 = 4;
+/// Furthest (in either axis) a pack follower spawns from its leader.
+const PACK_RADIUS: i32 = 2;
+/// How many items the player can carry, unless `PACK_SIZE` says otherwise.
+const DEFAULT_PACK_SIZE: usize = 40;
 
-/// Spawns the player and returns its entity.
-pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
-    ecs.create_entity()
+/// Resource collecting entities that systems want spawned, since spawning needs the whole `World`.
+#[derive(Default)]
+pub struct SpawnRequests {
+    requests: Vec<(i32, i32, String)>,
+}
+
+impl SpawnRequests {
+    /// Asks for the entity called `name` to be spawned at `(x,y)`.
+    pub fn request<S: ToString>(&mut self, x: i32, y: i32, name: S) {
+        self.requests.push((x, y, name.to_string()));
+    }
+}
+
+/// Spawns everything requested through [`SpawnRequests`] since the last call.
+pub fn spawn_requested(ecs: &mut World) {
+    let requests = std::mem::take(&mut ecs.write_resource::<SpawnRequests>().requests);
+    for (x, y, name) in requests.iter() {
+        spawn_named(ecs, name, *x, *y);
+    }
+}
+
+/// What the player starts the game with.
+///
+/// Kept separate from [`player`] so different character classes can start differently.
+pub struct StartingLoadout {
+    pub max_hp: i32,
+    pub defense: i32,
+    pub power: i32,
+    /// Names of items (as used in the spawn table) the player starts with equipped.
+    pub equipped: Vec<&'static str>,
+    /// Names of items the player starts with in their backpack.
+    pub backpack: Vec<&'static str>,
+}
+
+impl Default for StartingLoadout {
+    fn default() -> Self {
+        StartingLoadout::for_difficulty(Difficulty::default())
+    }
+}
+
+impl StartingLoadout {
+    /// The standard loadout, with as many health potions as the difficulty allows.
+    pub fn for_difficulty(difficulty: Difficulty) -> Self {
+        StartingLoadout {
+            max_hp: 30,
+            defense: 2,
+            power: 5,
+            equipped: vec!["Dagger"],
+            backpack: vec!["Health Potion"; difficulty.starting_potions()],
+        }
+    }
+}
+
+/// The player's backpack size, taken from the `PACK_SIZE` environment variable if it's set.
+fn player_pack_size() -> usize {
+    std::env::var("PACK_SIZE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_PACK_SIZE)
+}
+
+/// Spawns the player with the given loadout and returns its entity.
+pub fn player(ecs: &mut World, player_x: i32, player_y: i32, loadout: &StartingLoadout) -> Entity {
+    let player = ecs
+        .create_entity()
         .with(Position {
             x: player_x,
             y: player_y,
@@ -22,9 +90,12 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             glyph: rltk::to_cp437('@'),
             fg: RGB::named(rltk::YELLOW),
             bg: RGB::named(rltk::BLACK),
-            render_order: 0,
+            render_order: RenderOrder::Player,
         })
         .with(Player {})
+        .with(Faction {
+            kind: FactionKind::Player,
+        })
         .with(Viewshed {
             visible_tiles: Vec::new(),
             range: 8,
@@ -34,17 +105,52 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             name: "Player".to_string(),
         })
         .with(CombatStats {
-            max_hp: 30,
-            hp: 30,
-            defense: 2,
-            power: 5,
+            max_hp: loadout.max_hp,
+            hp: loadout.max_hp,
+            defense: loadout.defense,
+            power: loadout.power,
         })
         .with(HungerClock {
             state: HungerState::WellFed,
-            duration: 20,
+            duration: WELL_FED_DURATION,
+        })
+        .with(Gold { amount: 0 })
+        .with(PackCapacity {
+            max_items: player_pack_size(),
         })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build()
+        .build();
+
+    // Starting gear is spawned like any other item, then taken off the map.
+    for name in loadout.equipped.iter() {
+        if let Some(item) = spawn_named(ecs, name, player_x, player_y) {
+            ecs.write_storage::<Position>().remove(item);
+            let slot = ecs
+                .read_storage::<Equippable>()
+                .get(item)
+                .expect("Starting equipment must be equippable")
+                .slot;
+            ecs.write_storage::<Equipped>()
+                .insert(
+                    item,
+                    Equipped {
+                        owner: player,
+                        slot,
+                    },
+                )
+                .expect("Unable to equip starting gear");
+        }
+    }
+    for name in loadout.backpack.iter() {
+        if let Some(item) = spawn_named(ecs, name, player_x, player_y) {
+            ecs.write_storage::<Position>().remove(item);
+            ecs.write_storage::<InBackpack>()
+                .insert(item, InBackpack { owner: player })
+                .expect("Unable to pack starting item");
+        }
+    }
+
+    player
 }
 
 /// Spawns a room with entities from the spawn table.
@@ -53,6 +159,7 @@ pub fn spawn_room(
     rng: &mut RandomNumberGenerator,
     room: &Rect,
     map_depth: i32,
+    difficulty: Difficulty,
     spawn_list: &mut Vec<(usize, String)>,
 ) {
     let mut possible_targets: Vec<usize> = Vec::new();
@@ -60,13 +167,20 @@ pub fn spawn_room(
         for y in room.y1 + 1..room.y2 {
             for x in room.x1 + 1..room.x2 {
                 let idx = map.xy_idx(x, y);
-                if map.tiles[idx] == TileType::Floor {
+                if map.tiles[idx].is_walkable() {
                     possible_targets.push(idx);
                 }
             }
         }
     }
-    spawn_region(map, rng, &possible_targets, map_depth, spawn_list);
+    spawn_region(
+        map,
+        rng,
+        &possible_targets,
+        map_depth,
+        difficulty,
+        spawn_list,
+    );
 }
 
 /// Spawns a contiguous area with entities from the spawn table.
@@ -75,23 +189,25 @@ pub fn spawn_region(
     rng: &mut RandomNumberGenerator,
     area: &[usize],
     map_depth: i32,
+    difficulty: Difficulty,
     spawn_list: &mut Vec<(usize, String)>,
 ) {
     // Get spawn table for the current depth.
     let spawn_table = room_table(map_depth);
-    // Map map indices to entity names for spawning.
-    let mut spawn_points: HashMap<usize, String> = HashMap::new();
+    // Map indices and the entity to spawn there, in the order they were rolled so packs stay
+    // together and a seed always spawns alike.
+    let mut spawn_points: Vec<(usize, String)> = Vec::new();
     // Copy to prevent modifying original slice.
     let mut areas: Vec<usize> = Vec::from(area);
     {
         // Cap the number of entities to spawn, so we don't spawn more than we have room for.
         let num_spawns = i32::min(
             areas.len() as i32,
-            rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3,
+            rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3 + difficulty.spawn_modifier(),
         );
 
         // If we're not spawning anything, might as well return.
-        if num_spawns == 0 {
+        if num_spawns <= 0 {
             return;
         }
         for _ in 0..num_spawns {
@@ -104,72 +220,451 @@ pub fn spawn_region(
                 (rng.roll_dice(1, areas.len() as i32) - 1) as usize
             };
             // Insert the new spawn point with a random entity to spawn.
-            spawn_points.insert(areas[array_index], spawn_table.roll(rng));
+            let leader = areas[array_index];
+            let name = spawn_table.roll(rng);
             // Already used as a spawn point, so take it out.
             areas.remove(array_index);
+
+            // Some monsters bring friends, who gather around them.
+            if let Some((follower, count)) = pack(rng, &name, map_depth) {
+                spawn_points.push((leader, name));
+                for idx in pack_positions(map, leader, &mut areas, count) {
+                    spawn_points.push((idx, follower.to_string()));
+                }
+            } else {
+                spawn_points.push((leader, name));
+            }
+            if areas.is_empty() {
+                break;
+            }
         }
     }
-    for spawn in spawn_points.iter() {
-        spawn_list.push((*spawn.0, spawn.1.to_string()));
+    spawn_list.extend(spawn_points);
+}
+
+/// Whether a freshly rolled `leader` turns up with a pack, and if so what follows it and
+/// how many. Packs get bigger deeper down.
+fn pack(
+    rng: &mut RandomNumberGenerator,
+    leader: &str,
+    map_depth: i32,
+) -> Option<(&'static str, usize)> {
+    match leader {
+        // Captains never go anywhere without their troops.
+        "Orc Captain" => Some(("Orc", (1 + map_depth / 3) as usize)),
+        // Most goblins wander alone, but now and then they hunt as a gang.
+        "Goblin" if rng.roll_dice(1, 4) == 1 => Some(("Goblin", (1 + map_depth / 4) as usize)),
+        _ => None,
     }
 }
 
-pub fn spawn_entity(ecs: &mut World, spawn: &(&usize, &String)) {
-    let (x, y) = ((*spawn.0 % MAPWIDTH) as i32, (*spawn.0 / MAPWIDTH) as i32);
-    match spawn.1.as_ref() {
-        "Goblin" => goblin(ecs, x, y),
-        "Orc" => orc(ecs, x, y),
-        "Health Potion" => potion_health(ecs, x, y),
-        "Fireball Scroll" => scroll_fireball(ecs, x, y),
-        "Confusion Scroll" => scroll_confusion(ecs, x, y),
-        "Magic Missile Scroll" => scroll_magic_missile(ecs, x, y),
-        "Dagger" => dagger(ecs, x, y),
-        "Shield" => shield(ecs, x, y),
-        "Longsword" => longsword(ecs, x, y),
-        "Tower Shield" => tower_shield(ecs, x, y),
-        "Rations" => rations(ecs, x, y),
-        "Magic Mapping Scroll" => scroll_magic_mapping(ecs, x, y),
-        "Bear Trap" => bear_trap(ecs, x, y),
-        _ => {}
+/// Takes up to `count` tiles from `areas` near `leader` (nearest first) for its followers.
+fn pack_positions(map: &Map, leader: usize, areas: &mut Vec<usize>, count: usize) -> Vec<usize> {
+    let (lx, ly) = (leader as i32 % map.width, leader as i32 / map.width);
+    let offset = |idx: usize| {
+        let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+        (x - lx, y - ly)
+    };
+
+    let mut nearby: Vec<usize> = areas
+        .iter()
+        .copied()
+        .filter(|idx| {
+            let (dx, dy) = offset(*idx);
+            dx.abs() <= PACK_RADIUS && dy.abs() <= PACK_RADIUS
+        })
+        .collect();
+    nearby.sort_by_key(|idx| {
+        let (dx, dy) = offset(*idx);
+        (dx * dx + dy * dy, *idx)
+    });
+    nearby.truncate(count);
+    areas.retain(|idx| !nearby.contains(idx));
+    nearby
+}
+
+pub fn spawn_entity(ecs: &mut World, spawn: &(&usize, &String)) -> Option<Entity> {
+    let width = ecs.fetch::<Map>().width;
+    let (x, y) = (*spawn.0 as i32 % width, *spawn.0 as i32 / width);
+    spawn_named(ecs, spawn.1, x, y)
+}
+
+/// Spawns the entity called `name` at `(x,y)`, returning it if the name is known.
+fn spawn_named(ecs: &mut World, name: &str, x: i32, y: i32) -> Option<Entity> {
+    match name {
+        "Goblin" => Some(goblin(ecs, x, y)),
+        "Vampire Bat" => Some(vampire_bat(ecs, x, y)),
+        "Wraith" => Some(wraith(ecs, x, y)),
+        "Hound" => Some(hound(ecs, x, y)),
+        "Slime" => Some(slime(ecs, x, y)),
+        "Lesser Slime" => Some(lesser_slime(ecs, x, y)),
+        "Amulet Guardian" => Some(amulet_guardian(ecs, x, y)),
+        "Amulet of the Depths" => Some(amulet(ecs, x, y)),
+        "Orc" => Some(orc(ecs, x, y)),
+        "Orc Captain" => Some(orc_captain(ecs, x, y)),
+        "Ogre" => Some(ogre(ecs, x, y)),
+        "Health Potion" => Some(potion_health(ecs, x, y)),
+        "Fireball Scroll" => Some(scroll_fireball(ecs, x, y)),
+        "Confusion Scroll" => Some(scroll_confusion(ecs, x, y)),
+        "Poison Gas Scroll" => Some(scroll_poison_gas(ecs, x, y)),
+        "Magic Missile Scroll" => Some(scroll_magic_missile(ecs, x, y)),
+        "Dagger" => Some(dagger(ecs, x, y)),
+        "Shield" => Some(shield(ecs, x, y)),
+        "Longsword" => Some(longsword(ecs, x, y)),
+        "Tower Shield" => Some(tower_shield(ecs, x, y)),
+        "Greatsword" => Some(greatsword(ecs, x, y)),
+        "Battle Axe" => Some(battle_axe(ecs, x, y)),
+        "Shortbow" => Some(shortbow(ecs, x, y)),
+        "Sling" => Some(sling(ecs, x, y)),
+        "Arrows" => Some(ammo(ecs, x, y, AmmoKind::Arrow, 12)),
+        "Sling Stones" => Some(ammo(ecs, x, y, AmmoKind::SlingStone, 15)),
+        // Shots that land where they can be picked up again.
+        "Spent Arrow" => Some(ammo(ecs, x, y, AmmoKind::Arrow, 1)),
+        "Spent Sling Stone" => Some(ammo(ecs, x, y, AmmoKind::SlingStone, 1)),
+        "Leather Cap" => Some(armor(ecs, x, y, "Leather Cap", EquipmentSlot::Head, 1)),
+        "Leather Armor" => Some(armor(ecs, x, y, "Leather Armor", EquipmentSlot::Body, 1)),
+        "Chain Mail" => Some(armor(ecs, x, y, "Chain Mail", EquipmentSlot::Body, 2)),
+        "Leather Gloves" => Some(armor(ecs, x, y, "Leather Gloves", EquipmentSlot::Hands, 1)),
+        "Leather Boots" => Some(armor(ecs, x, y, "Leather Boots", EquipmentSlot::Feet, 1)),
+        "Rations" => Some(rations(ecs, x, y)),
+        "Repair Kit" => Some(repair_kit(ecs, x, y)),
+        "Magic Mapping Scroll" => Some(scroll_magic_mapping(ecs, x, y)),
+        "Clairvoyance Scroll" => Some(scroll_clairvoyance(ecs, x, y)),
+        "Telepathy Potion" => Some(potion_telepathy(ecs, x, y)),
+        "Antidote" => Some(potion_cure(
+            ecs,
+            x,
+            y,
+            "Antidote",
+            rltk::GREEN,
+            vec![StatusEffect::Poison],
+        )),
+        "Clear Mind Potion" => Some(potion_cure(
+            ecs,
+            x,
+            y,
+            "Clear Mind Potion",
+            rltk::LIGHT_BLUE,
+            vec![StatusEffect::Confusion],
+        )),
+        "Gold" => Some(gold(ecs, x, y)),
+        "Shopkeeper" => Some(shopkeeper(ecs, x, y)),
+        "Shop Stock" => shop_stock(ecs, x, y),
+        "Rubble" => Some(decoration(ecs, x, y, "Rubble", ';', rltk::GREY, false)),
+        "Bones" => Some(decoration(ecs, x, y, "Bones", '~', rltk::WHEAT, false)),
+        "Mushrooms" => Some(decoration(ecs, x, y, "Mushrooms", '♣', rltk::PURPLE, false)),
+        "Statue" => Some(decoration(ecs, x, y, "Statue", '&', rltk::LIGHT_GREY, true)),
+        "Pillar" => Some(decoration(ecs, x, y, "Pillar", '○', rltk::GREY, true)),
+        "Brazier" => Some(brazier(ecs, x, y)),
+        "Bear Trap" => Some(bear_trap(ecs, x, y)),
+        "Teleport Trap" => Some(teleport_trap(ecs, x, y)),
+        "Alarm Trap" => Some(alarm_trap(ecs, x, y)),
+        "Confusion Gas Trap" => Some(confusion_gas_trap(ecs, x, y)),
+        _ => None,
     }
 }
 
-fn room_table(map_depth: i32) -> RandomTable {
+/// Picks a monster to wander onto a level at `map_depth`.
+pub fn wandering_monster(rng: &mut RandomNumberGenerator, map_depth: i32) -> String {
+    monster_table(map_depth).roll(rng)
+}
+
+/// The monsters that can turn up at `map_depth`, whether placed in rooms or wandering in.
+fn monster_table(map_depth: i32) -> RandomTable {
     RandomTable::new()
         .add("Goblin", 10)
         .add("Orc", 1 + map_depth)
+        .add("Orc Captain", map_depth - 2)
+        .add("Vampire Bat", map_depth - 1)
+        .add("Wraith", map_depth - 3)
+        .add("Hound", map_depth - 2)
+        .add("Slime", 1 + map_depth / 2)
+}
+
+fn room_table(map_depth: i32) -> RandomTable {
+    monster_table(map_depth)
         .add("Health Potion", 7)
         .add("Fireball Scroll", 2 + map_depth)
         .add("Confusion Scroll", 2 + map_depth)
+        .add("Poison Gas Scroll", map_depth - 1)
         .add("Magic Missile Scroll", 4)
         .add("Dagger", 3)
         .add("Shield", 3)
         .add("Longsword", map_depth - 3)
         .add("Tower Shield", map_depth - 3)
+        .add("Greatsword", map_depth - 4)
+        .add("Battle Axe", map_depth - 3)
+        .add("Shortbow", map_depth - 1)
+        .add("Sling", 2)
+        .add("Arrows", map_depth)
+        .add("Sling Stones", 2)
+        .add("Leather Cap", 2)
+        .add("Leather Armor", 2)
+        .add("Chain Mail", map_depth - 2)
+        .add("Leather Gloves", 2)
+        .add("Leather Boots", 2)
         .add("Rations", 6)
+        .add("Repair Kit", 2)
+        .add("Gold", 6)
         .add("Magic Mapping Scroll", 2)
+        .add("Clairvoyance Scroll", map_depth / 3)
+        .add("Telepathy Potion", 1 + map_depth / 2)
+        .add("Antidote", map_depth - 1)
+        .add("Clear Mind Potion", 2)
         .add("Bear Trap", 5)
+        .add("Teleport Trap", 2)
+        .add("Alarm Trap", 1 + map_depth)
+        .add("Confusion Gas Trap", 2)
 }
 
 /// Makes an orc.
-fn orc(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc");
+fn orc(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let orc = monster(ecs, x, y, rltk::to_cp437('o'), "Orc");
+    ecs.write_storage::<LeavesCorpse>()
+        .insert(orc, LeavesCorpse {})
+        .expect("Unable to insert corpse marker");
+    with_loot(ecs, orc, "Orc")
+}
+
+/// Makes an ogre: slow-witted, but it hits hard enough to send you flying. Only placed by
+/// prefabs.
+fn ogre(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let ogre = monster(ecs, x, y, rltk::to_cp437('G'), "Ogre");
+    let difficulty = *ecs.fetch::<Difficulty>();
+    ecs.write_storage::<CombatStats>()
+        .insert(
+            ogre,
+            CombatStats {
+                max_hp: difficulty.monster_hp(30),
+                hp: difficulty.monster_hp(30),
+                defense: 1,
+                power: difficulty.monster_power(8),
+            },
+        )
+        .expect("Unable to insert ogre stats");
+    ecs.write_storage::<LeavesCorpse>()
+        .insert(ogre, LeavesCorpse {})
+        .expect("Unable to insert corpse marker");
+    ecs.write_storage::<Knockback>()
+        .insert(ogre, Knockback { distance: 2 })
+        .expect("Unable to insert knockback");
+    ogre
+}
+
+/// Makes an orc captain, tougher than its troops and carrying their spare gear.
+fn orc_captain(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let captain = monster(ecs, x, y, rltk::to_cp437('O'), "Orc Captain");
+    let difficulty = *ecs.fetch::<Difficulty>();
+    ecs.write_storage::<CombatStats>()
+        .insert(
+            captain,
+            CombatStats {
+                max_hp: difficulty.monster_hp(24),
+                hp: difficulty.monster_hp(24),
+                defense: 2,
+                power: difficulty.monster_power(6),
+            },
+        )
+        .expect("Unable to insert captain stats");
+    ecs.write_storage::<LeavesCorpse>()
+        .insert(captain, LeavesCorpse {})
+        .expect("Unable to insert corpse marker");
+    with_loot(ecs, captain, "Captain")
 }
 
 /// Makes a goblin.
-fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin");
+fn goblin(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let goblin = monster(ecs, x, y, rltk::to_cp437('g'), "Goblin");
+    // Goblins are cowards, and run once they're badly hurt.
+    ecs.write_storage::<CanFlee>()
+        .insert(goblin, CanFlee { hp_threshold: 0.25 })
+        .expect("Unable to insert flee behavior");
+    ecs.write_storage::<LeavesCorpse>()
+        .insert(goblin, LeavesCorpse {})
+        .expect("Unable to insert corpse marker");
+    with_loot(ecs, goblin, "Goblin")
+}
+
+/// Makes `ent` shrug off each of the status `effects`.
+fn with_immunities(ecs: &mut World, ent: Entity, effects: Vec<StatusEffect>) {
+    ecs.write_storage::<Immunities>()
+        .insert(ent, Immunities { effects })
+        .expect("Unable to insert immunities");
+}
+
+/// Makes `ent` act at `speed`, where [`NORMAL_SPEED`] is once per player turn.
+fn with_speed(ecs: &mut World, ent: Entity, speed: i32) {
+    ecs.write_storage::<Speed>()
+        .insert(ent, Speed { speed, energy: 0 })
+        .expect("Unable to insert speed");
+}
+
+/// Gives `ent` the loot table called `table`.
+fn with_loot(ecs: &mut World, ent: Entity, table: &str) -> Entity {
+    ecs.write_storage::<LootTable>()
+        .insert(
+            ent,
+            LootTable {
+                table: table.to_string(),
+            },
+        )
+        .expect("Unable to insert loot table");
+    ent
+}
+
+/// What a monster with the loot table called `name` might drop; "None" drops nothing.
+fn loot_table(name: &str) -> RandomTable {
+    match name {
+        "Goblin" => RandomTable::new().add("None", 18).add("Health Potion", 2),
+        "Orc" => RandomTable::new()
+            .add("None", 12)
+            .add("Health Potion", 2)
+            .add("Rations", 2),
+        "Captain" => RandomTable::new()
+            .add("None", 4)
+            .add("Longsword", 2)
+            .add("Chain Mail", 1)
+            .add("Tower Shield", 1)
+            .add("Health Potion", 2),
+        _ => RandomTable::new(),
+    }
+}
+
+/// Rolls on the loot table called `table`, dropping whatever comes up at `(x,y)`.
+pub fn drop_loot(ecs: &mut World, x: i32, y: i32, table: &str) -> Option<Entity> {
+    let item = loot_table(table).roll(&mut ecs.write_resource::<RandomNumberGenerator>());
+    if item == "None" {
+        return None;
+    }
+    let idx = ecs.fetch::<Map>().xy_idx(x, y);
+    spawn_entity(ecs, &(&idx, &item))
+}
+
+/// Makes a vampire bat, which heals by biting.
+fn vampire_bat(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let bat = monster(ecs, x, y, rltk::to_cp437('b'), "Vampire Bat");
+    with_speed(ecs, bat, 150);
+    ecs.write_storage::<LifeDrain>()
+        .insert(bat, LifeDrain { fraction: 0.5 })
+        .expect("Unable to insert life drain");
+    ecs.write_storage::<LeavesCorpse>()
+        .insert(bat, LeavesCorpse {})
+        .expect("Unable to insert corpse marker");
+    bat
+}
+
+/// Makes a hound, which sniffs out the player even when it can't see them.
+fn hound(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let hound = monster(ecs, x, y, rltk::to_cp437('d'), "Hound");
+    with_speed(ecs, hound, 120);
+    ecs.write_storage::<TracksByScent>()
+        .insert(hound, TracksByScent {})
+        .expect("Unable to insert scent tracking");
+    ecs.write_storage::<LeavesCorpse>()
+        .insert(hound, LeavesCorpse {})
+        .expect("Unable to insert corpse marker");
+    hound
+}
+
+/// Makes a wraith, whose touch permanently weakens its victims.
+fn wraith(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let wraith = monster(ecs, x, y, rltk::to_cp437('W'), "Wraith");
+    ecs.write_storage::<StatDrain>()
+        .insert(wraith, StatDrain { amount: 1 })
+        .expect("Unable to insert stat drain");
+    // There's no blood left in it to poison.
+    with_immunities(ecs, wraith, vec![StatusEffect::Poison]);
+    wraith
+}
+
+/// Makes a slime, which splits into lesser slimes when struck.
+fn slime(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let slime = monster(ecs, x, y, rltk::to_cp437('j'), "Slime");
+    with_speed(ecs, slime, 50);
+    ecs.write_storage::<SplitOnHit>()
+        .insert(
+            slime,
+            SplitOnHit {
+                spawn: "Lesser Slime".to_string(),
+            },
+        )
+        .expect("Unable to insert split");
+    // Nothing in there to confuse.
+    with_immunities(ecs, slime, vec![StatusEffect::Confusion]);
+    slime
+}
+
+/// Makes a lesser slime: the weaker, non-splitting copy a slime sheds.
+fn lesser_slime(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let slime = monster(ecs, x, y, rltk::to_cp437('j'), "Lesser Slime");
+    with_speed(ecs, slime, 50);
+    let difficulty = *ecs.fetch::<Difficulty>();
+    ecs.write_storage::<CombatStats>()
+        .insert(
+            slime,
+            CombatStats {
+                max_hp: difficulty.monster_hp(6),
+                hp: difficulty.monster_hp(6),
+                defense: 0,
+                power: difficulty.monster_power(2),
+            },
+        )
+        .expect("Unable to insert slime stats");
+    with_immunities(ecs, slime, vec![StatusEffect::Confusion]);
+    slime
+}
+
+/// Makes the boss guarding the amulet on the final depth.
+fn amulet_guardian(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let boss = monster(ecs, x, y, rltk::to_cp437('Ω'), "Amulet Guardian");
+    let difficulty = *ecs.fetch::<Difficulty>();
+    ecs.write_storage::<Renderable>()
+        .get_mut(boss)
+        .expect("Monster must be renderable")
+        .fg = RGB::named(rltk::MAGENTA);
+    ecs.write_storage::<CombatStats>()
+        .insert(
+            boss,
+            CombatStats {
+                max_hp: difficulty.monster_hp(60),
+                hp: difficulty.monster_hp(60),
+                defense: 3,
+                power: difficulty.monster_power(8),
+            },
+        )
+        .expect("Unable to insert boss stats");
+    ecs.write_storage::<SummonsMinions>()
+        .insert(
+            boss,
+            SummonsMinions {
+                minion: "Goblin".to_string(),
+                cooldown: 8,
+                ready_in: 0,
+            },
+        )
+        .expect("Unable to insert summoning");
+    ecs.write_storage::<Guardian>()
+        .insert(boss, Guardian {})
+        .expect("Unable to insert guardian");
+    boss
 }
 
 /// Spawns a monster at `(x,y)` with a given glyph and name.
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, name: S) {
+fn monster<S: ToString>(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    glyph: rltk::FontCharType,
+    name: S,
+) -> Entity {
+    let difficulty = *ecs.fetch::<Difficulty>();
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: glyph,
             fg: RGB::named(rltk::RED),
             bg: RGB::named(rltk::BLACK),
-            render_order: 1,
+            render_order: RenderOrder::Monster,
         })
         .with(Viewshed {
             visible_tiles: Vec::new(),
@@ -177,28 +672,56 @@ fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharTy
             dirty: true,
         })
         .with(Monster {})
+        .with(AiState { mode: AiMode::Idle })
+        .with(Speed {
+            speed: NORMAL_SPEED,
+            energy: 0,
+        })
+        .with(Faction {
+            kind: FactionKind::Hostile,
+        })
         .with(Name {
             name: name.to_string(),
         })
         .with(BlocksTile {})
         .with(CombatStats {
-            max_hp: 16,
-            hp: 16,
+            max_hp: difficulty.monster_hp(16),
+            hp: difficulty.monster_hp(16),
             defense: 1,
-            power: 4,
+            power: difficulty.monster_power(4),
         })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+/// A kit for patching up worn gear, restoring everything equipped to full durability.
+fn repair_kit(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('≡'),
+            fg: RGB::named(rltk::LIGHT_GREY),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Repair Kit".to_string(),
+        })
+        .with(Item {})
+        .with(RepairsEquipment {})
+        .with(Consumable {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
-fn rations(ecs: &mut World, x: i32, y: i32) {
+fn rations(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('%'),
             fg: RGB::named(rltk::GREEN),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Rations".to_string(),
@@ -207,18 +730,63 @@ fn rations(ecs: &mut World, x: i32, y: i32) {
         .with(ProvidesFood {})
         .with(Consumable {})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+/// Spawns the corpse of the monster called `name` at `(x,y)`.
+///
+/// Corpses fill you up like rations, but eating one makes you a little sick.
+pub fn corpse(ecs: &mut World, x: i32, y: i32, name: &str) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('%'),
+            fg: RGB::named(rltk::DARK_RED),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: format!("{} Corpse", name),
+        })
+        .with(Item {})
+        .with(ProvidesFood {})
+        .with(Consumable {})
+        .with(InflictsDamage {
+            damage: 2,
+            damage_type: DamageType::Poison,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Spawns the amulet that wins the game when picked up.
+fn amulet(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('"'),
+            fg: RGB::named(rltk::GOLD),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Amulet of the Depths".to_string(),
+        })
+        .with(Item {})
+        .with(WinsGame {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
 /// Spawns a health potion at `(x,y)`.
-fn potion_health(ecs: &mut World, x: i32, y: i32) {
+fn potion_health(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437(';'),
             fg: RGB::named(rltk::MAGENTA),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Health Potion".to_string(),
@@ -227,20 +795,67 @@ fn potion_health(ecs: &mut World, x: i32, y: i32) {
         .with(Consumable {})
         .with(ProvidesHealing { heal_amount: 8 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+/// Spawns a potion at `(x,y)` that cures the drinker of each of the given status `effects`.
+fn potion_cure(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    color: (u8, u8, u8),
+    effects: Vec<StatusEffect>,
+) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(';'),
+            fg: RGB::named(color),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Consumable {})
+        .with(CuresStatus { effects })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Spawns a Telepathy Potion at `(x,y)`, letting the drinker sense monsters for a while.
+fn potion_telepathy(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(';'),
+            fg: RGB::named(rltk::PURPLE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Telepathy Potion".to_string(),
+        })
+        .with(Item {})
+        .with(Consumable {})
+        .with(Telepathy { turns: 50 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
 /// Spawns a Magic Missile Scroll at `(x,y)`.
 ///
 /// Magic missile scrolls target a single entity, and are consumed on use.
-fn scroll_magic_missile(ecs: &mut World, x: i32, y: i32) {
+fn scroll_magic_missile(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437(')'),
             fg: RGB::named(rltk::CYAN),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Magic Missile Scroll".to_string(),
@@ -248,23 +863,26 @@ fn scroll_magic_missile(ecs: &mut World, x: i32, y: i32) {
         .with(Item {})
         .with(Consumable {})
         .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
+        .with(InflictsDamage {
+            damage: 8,
+            damage_type: DamageType::Physical,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
 /// Spawns a fireball scroll at `(x,y)`.
 ///
 /// Fireball is an area-of-effect ability, hitting all entities within range
 /// of the targeted location. Like other scrolls, these are consumed on use.
-fn scroll_fireball(ecs: &mut World, x: i32, y: i32) {
+fn scroll_fireball(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437(')'),
             fg: RGB::named(rltk::ORANGE),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Fireball Scroll".to_string(),
@@ -272,24 +890,56 @@ fn scroll_fireball(ecs: &mut World, x: i32, y: i32) {
         .with(Item {})
         .with(Consumable {})
         .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 20 })
+        .with(InflictsDamage {
+            damage: 20,
+            damage_type: DamageType::Physical,
+        })
         .with(AreaOfEffect { radius: 3 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+/// Spawns a poison gas scroll at `(x,y)`.
+///
+/// Poison gas settles over an area at range, hurting anything that stands in it each turn
+/// until it clears.
+fn scroll_poison_gas(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: RGB::named(rltk::GREEN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Poison Gas Scroll".to_string(),
+        })
+        .with(Item {})
+        .with(Consumable {})
+        .with(Ranged { range: 6 })
+        .with(InflictsDamage {
+            damage: 4,
+            damage_type: DamageType::Poison,
+        })
+        .with(AreaOfEffect { radius: 2 })
+        .with(Lingers { turns: 6 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
 /// Spawns a confusion scroll at `(x,y)`.
 ///
 /// Confusion targets a single entity at range, and confuses them for a number
 /// of turns. During this time, the entity is unable to perform any actions.
-fn scroll_confusion(ecs: &mut World, x: i32, y: i32) {
+fn scroll_confusion(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437(')'),
             fg: RGB::named(rltk::PINK),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Confusion Scroll".to_string(),
@@ -299,36 +949,62 @@ fn scroll_confusion(ecs: &mut World, x: i32, y: i32) {
         .with(Ranged { range: 6 })
         .with(Confusion { turns: 4 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn scroll_magic_mapping(ecs: &mut World, x: i32, y: i32) {
+fn scroll_magic_mapping(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('◙'),
             fg: RGB::named(rltk::SANDY_BROWN),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
-            name: "Scroll of Magic Mapping".to_string(),
+            name: "Magic Mapping Scroll".to_string(),
         })
         .with(Item {})
-        .with(MagicMapper {})
+        .with(MagicMapper {
+            reveal_traps: false,
+            reachable_only: true,
+        })
         .with(Consumable {})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+/// Like magic mapping, but the traps on the level are revealed as well.
+fn scroll_clairvoyance(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('◙'),
+            fg: RGB::named(rltk::CYAN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Clairvoyance Scroll".to_string(),
+        })
+        .with(Item {})
+        .with(MagicMapper {
+            reveal_traps: true,
+            reachable_only: false,
+        })
+        .with(Consumable {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
-fn dagger(ecs: &mut World, x: i32, y: i32) {
+fn dagger(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('/'),
             fg: RGB::named(rltk::RED),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Dagger".to_string(),
@@ -337,19 +1013,25 @@ fn dagger(ecs: &mut World, x: i32, y: i32) {
         .with(Equippable {
             slot: EquipmentSlot::Melee,
         })
-        .with(MeleePowerBonus { power: 2 })
+        .with(Weapon {
+            attack_kind: AttackKind::Melee,
+            damage: 2,
+            range: 1,
+            ammo: None,
+        })
+        .with(Durability::new(30))
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn longsword(ecs: &mut World, x: i32, y: i32) {
+fn longsword(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('/'),
             fg: RGB::named(rltk::RED),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Longsword".to_string(),
@@ -358,19 +1040,25 @@ fn longsword(ecs: &mut World, x: i32, y: i32) {
         .with(Equippable {
             slot: EquipmentSlot::Melee,
         })
-        .with(MeleePowerBonus { power: 4 })
+        .with(Weapon {
+            attack_kind: AttackKind::Melee,
+            damage: 4,
+            range: 1,
+            ammo: None,
+        })
+        .with(Durability::new(60))
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn shield(ecs: &mut World, x: i32, y: i32) {
+fn shield(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('('),
             fg: RGB::named(rltk::RED),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Shield".to_string(),
@@ -380,18 +1068,19 @@ fn shield(ecs: &mut World, x: i32, y: i32) {
             slot: EquipmentSlot::Shield,
         })
         .with(DefenseBonus { defense: 1 })
+        .with(Durability::new(40))
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn tower_shield(ecs: &mut World, x: i32, y: i32) {
+fn tower_shield(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('('),
             fg: RGB::named(rltk::RED),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Tower Shield".to_string(),
@@ -401,18 +1090,344 @@ fn tower_shield(ecs: &mut World, x: i32, y: i32) {
             slot: EquipmentSlot::Shield,
         })
         .with(DefenseBonus { defense: 3 })
+        .with(Durability::new(80))
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+fn greatsword(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('/'),
+            fg: RGB::named(rltk::ORANGE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Greatsword".to_string(),
+        })
+        .with(Item {})
+        .with(Equippable {
+            slot: EquipmentSlot::TwoHanded,
+        })
+        .with(Weapon {
+            attack_kind: AttackKind::Melee,
+            damage: 6,
+            range: 1,
+            ammo: None,
+        })
+        .with(Durability::new(80))
+        .with(Knockback { distance: 1 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// A heavy axe whose swings cleave into the foes beside the one you hit.
+fn battle_axe(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('P'),
+            fg: RGB::named(rltk::ORANGE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Battle Axe".to_string(),
+        })
+        .with(Item {})
+        .with(Equippable {
+            slot: EquipmentSlot::TwoHanded,
+        })
+        .with(Weapon {
+            attack_kind: AttackKind::Melee,
+            damage: 3,
+            range: 1,
+            ammo: None,
+        })
+        .with(Durability::new(70))
+        .with(Cleave {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// A bow, fired at foes out of reach instead of swung at those next to you.
+fn shortbow(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('}'),
+            fg: RGB::named(rltk::BROWN1),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Shortbow".to_string(),
+        })
+        .with(Item {})
+        .with(Equippable {
+            slot: EquipmentSlot::TwoHanded,
+        })
+        .with(Weapon {
+            attack_kind: AttackKind::Ranged,
+            damage: 4,
+            range: 6,
+            ammo: Some(AmmoKind::Arrow),
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// A weak but cheap launcher for stones.
+fn sling(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('}'),
+            fg: RGB::named(rltk::GREY),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Sling".to_string(),
+        })
+        .with(Item {})
+        .with(Equippable {
+            slot: EquipmentSlot::Melee,
+        })
+        .with(Weapon {
+            attack_kind: AttackKind::Ranged,
+            damage: 2,
+            range: 5,
+            ammo: Some(AmmoKind::SlingStone),
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// A bundle of `count` shots of `kind` ammunition.
+fn ammo(ecs: &mut World, x: i32, y: i32, kind: AmmoKind, count: i32) -> Entity {
+    let glyph = match kind {
+        AmmoKind::Arrow => '↑',
+        AmmoKind::SlingStone => '•',
+    };
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(glyph),
+            fg: RGB::named(rltk::BROWN1),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: kind.name().to_string(),
+        })
+        .with(Item {})
+        .with(Ammo { kind, count })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Blows any piece of armor can take before it breaks.
+const ARMOR_DURABILITY_BASE: i32 = 30;
+/// Extra blows armor can take for each point of defense it gives.
+const ARMOR_DURABILITY_PER_DEFENSE: i32 = 15;
+
+/// Spawns a piece of armor at `(x,y)` worn in `slot` and granting `defense`.
+fn armor<S: ToString>(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: S,
+    slot: EquipmentSlot,
+    defense: i32,
+) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('['),
+            fg: RGB::named(rltk::CYAN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Equippable { slot })
+        .with(DefenseBonus { defense })
+        .with(Durability::new(
+            ARMOR_DURABILITY_BASE + ARMOR_DURABILITY_PER_DEFENSE * defense,
+        ))
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// What an item costs in a shop, if it's for sale at all.
+pub fn item_price(name: &str) -> Option<i32> {
+    match name {
+        "Rations" => Some(10),
+        "Repair Kit" => Some(30),
+        "Health Potion" => Some(25),
+        "Telepathy Potion" => Some(40),
+        "Antidote" => Some(20),
+        "Clear Mind Potion" => Some(20),
+        "Magic Missile Scroll" => Some(35),
+        "Confusion Scroll" => Some(40),
+        "Fireball Scroll" => Some(60),
+        "Poison Gas Scroll" => Some(55),
+        "Magic Mapping Scroll" => Some(50),
+        "Clairvoyance Scroll" => Some(70),
+        "Dagger" => Some(20),
+        "Shield" => Some(20),
+        "Longsword" => Some(60),
+        "Tower Shield" => Some(60),
+        "Battle Axe" => Some(90),
+        "Greatsword" => Some(100),
+        "Shortbow" => Some(50),
+        "Sling" => Some(20),
+        "Arrows" => Some(15),
+        "Sling Stones" => Some(5),
+        "Leather Cap" | "Leather Gloves" | "Leather Boots" => Some(15),
+        "Leather Armor" => Some(20),
+        "Chain Mail" => Some(60),
+        _ => None,
+    }
 }
 
-fn bear_trap(ecs: &mut World, x: i32, y: i32) {
+/// Spawns a pile of gold, bigger the deeper it's found.
+fn gold(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let depth = ecs.fetch::<Map>().depth;
+    let amount = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(2, 6)
+        * depth;
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('$'),
+            fg: RGB::named(rltk::GOLD),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Gold".to_string(),
+        })
+        .with(Item {})
+        .with(Currency { amount })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Spawns the shopkeeper, who trades with the player when bumped into.
+fn shopkeeper(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('@'),
+            fg: RGB::named(rltk::GOLD),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Monster,
+        })
+        .with(Name {
+            name: "Shopkeeper".to_string(),
+        })
+        .with(Faction {
+            kind: FactionKind::Neutral,
+        })
+        .with(Vendor {})
+        .with(BlocksTile {})
+        .with(CombatStats {
+            max_hp: 100,
+            hp: 100,
+            defense: 5,
+            power: 10,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Spawns a random item for sale, priced for the shop.
+fn shop_stock(ecs: &mut World, x: i32, y: i32) -> Option<Entity> {
+    let name = RandomTable::new()
+        .add("Health Potion", 6)
+        .add("Rations", 4)
+        .add("Repair Kit", 2)
+        .add("Telepathy Potion", 2)
+        .add("Magic Missile Scroll", 3)
+        .add("Confusion Scroll", 2)
+        .add("Fireball Scroll", 2)
+        .add("Magic Mapping Scroll", 2)
+        .add("Longsword", 1)
+        .add("Tower Shield", 1)
+        .add("Chain Mail", 1)
+        .add("Battle Axe", 1)
+        .add("Shortbow", 1)
+        .add("Arrows", 2)
+        .roll(&mut ecs.write_resource::<RandomNumberGenerator>());
+    let item = spawn_named(ecs, &name, x, y)?;
+    if let Some(value) = item_price(&name) {
+        ecs.write_storage::<Price>()
+            .insert(item, Price { value })
+            .expect("Unable to insert price");
+    }
+    Some(item)
+}
+
+/// Spawns a piece of scenery, which stops movement if `blocks` is set.
+fn decoration(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    glyph: char,
+    color: (u8, u8, u8),
+    blocks: bool,
+) -> Entity {
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(glyph),
+            fg: RGB::named(color),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Decoration,
+        })
+        .with(Name {
+            name: name.to_string(),
+        });
+    if blocks {
+        builder = builder.with(BlocksTile {});
+    }
+    builder.marked::<SimpleMarker<SerializeMe>>().build()
+}
+
+/// A blocking decoration whose flames flicker.
+fn brazier(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let brazier = decoration(ecs, x, y, "Brazier", '♠', rltk::ORANGE, true);
+    ecs.write_storage::<AnimatedRenderable>()
+        .insert(
+            brazier,
+            AnimatedRenderable::new(vec![
+                (rltk::to_cp437('♠'), 300.0),
+                (rltk::to_cp437('♣'), 150.0),
+                (rltk::to_cp437('♠'), 200.0),
+                (rltk::to_cp437('¥'), 100.0),
+            ]),
+        )
+        .expect("Unable to insert animation");
+    brazier
+}
+
+fn bear_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('^'),
             fg: RGB::named(rltk::GREY),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2,
+            render_order: RenderOrder::Item,
         })
         .with(Name {
             name: "Bear Trap".to_string(),
@@ -420,7 +1435,103 @@ fn bear_trap(ecs: &mut World, x: i32, y: i32) {
         .with(Hidden {})
         .with(EntryTrigger {})
         .with(SingleActivation {})
-        .with(InflictsDamage { damage: 6 })
+        .with(InflictsDamage {
+            damage: 6,
+            damage_type: DamageType::Physical,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+fn teleport_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('^'),
+            fg: RGB::named(rltk::MAGENTA),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Teleport Trap".to_string(),
+        })
+        .with(Hidden {})
+        .with(EntryTrigger {})
+        .with(TeleportsVictim {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn alarm_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('^'),
+            fg: RGB::named(rltk::YELLOW),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Alarm Trap".to_string(),
+        })
+        .with(Hidden {})
+        .with(EntryTrigger {})
+        .with(SingleActivation {})
+        .with(RaisesAlarm {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn confusion_gas_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('^'),
+            fg: RGB::named(rltk::PINK),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RenderOrder::Item,
+        })
+        .with(Name {
+            name: "Confusion Gas Trap".to_string(),
+        })
+        .with(Hidden {})
+        .with(EntryTrigger {})
+        .with(SingleActivation {})
+        .with(Confusion { turns: 4 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_followers_cluster_around_the_leader() {
+        let map = Map::new(1, 20, 20);
+        let leader = map.xy_idx(10, 10);
+        let mut areas: Vec<usize> = (0..map.tiles.len()).filter(|idx| *idx != leader).collect();
+
+        let followers = pack_positions(&map, leader, &mut areas, 8);
+
+        // The eight tiles touching the leader come first.
+        assert_eq!(followers.len(), 8);
+        for idx in followers.iter() {
+            let (x, y) = (*idx as i32 % map.width, *idx as i32 / map.width);
+            assert!((x - 10).abs() <= 1 && (y - 10).abs() <= 1);
+            assert!(!areas.contains(idx));
+        }
+    }
+
+    #[test]
+    fn packs_stay_within_the_area() {
+        let map = Map::new(1, 20, 20);
+        let leader = map.xy_idx(10, 10);
+        let mut areas = vec![map.xy_idx(11, 10), map.xy_idx(15, 15)];
+
+        let followers = pack_positions(&map, leader, &mut areas, 3);
+
+        assert_eq!(followers, vec![map.xy_idx(11, 10)]);
+        assert_eq!(areas, vec![map.xy_idx(15, 15)]);
+    }
 }
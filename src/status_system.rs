@@ -0,0 +1,131 @@
+use rltk::RGB;
+use specs::prelude::*;
+
+use super::{
+    gamelog::{GameLog, TurnCounter},
+    particle_system::ParticleBuilder,
+    Confusion, DamageType, EntryTrigger, Item, Poisoned, Position, RunState, SufferDamage,
+    Telepathy,
+};
+
+/// How long a status effect's particle hangs over an afflicted entity, in ms.
+const STATUS_PARTICLE_LIFETIME: f32 = 200.0;
+
+/// Shows what's afflicting each entity, and counts down timed status effects, removing them
+/// when they run out.
+pub struct StatusSystem {}
+
+impl<'a> System<'a> for StatusSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Telepathy>,
+        ReadStorage<'a, Item>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Confusion>,
+        WriteExpect<'a, ParticleBuilder>,
+        WriteStorage<'a, Poisoned>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, EntryTrigger>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut telepathy,
+            items,
+            player_ent,
+            runstate,
+            mut log,
+            turn_counter,
+            positions,
+            mut confusion,
+            mut particle_builder,
+            mut poisoned,
+            mut suffering,
+            triggers,
+        ) = data;
+
+        // Float a particle over everything with an effect on it, taking turns when there's
+        // more than one. Items and traps only carry effects to hand out, so they're skipped.
+        for (ent, pos, _, _) in (&entities, &positions, !&items, !&triggers).join() {
+            let mut effects: Vec<(char, (u8, u8, u8))> = Vec::new();
+            if confusion.contains(ent) {
+                effects.push(('?', rltk::MAGENTA));
+            }
+            if telepathy.contains(ent) {
+                effects.push(('*', rltk::CYAN));
+            }
+            if poisoned.contains(ent) {
+                effects.push(('♣', rltk::GREEN));
+            }
+            if effects.is_empty() {
+                continue;
+            }
+            let (glyph, color) = effects[turn_counter.turn as usize % effects.len()];
+            particle_builder.request(
+                pos.x,
+                pos.y,
+                RGB::named(color),
+                RGB::named(rltk::BLACK),
+                rltk::to_cp437(glyph),
+                STATUS_PARTICLE_LIFETIME,
+            );
+        }
+
+        // Effects tick once per turn.
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        // Items carry telepathy to hand out, so they don't count down.
+        let mut expired: Vec<Entity> = Vec::new();
+        for (ent, status, _) in (&entities, &mut telepathy, !&items).join() {
+            status.turns -= 1;
+            if status.turns < 1 {
+                expired.push(ent);
+            }
+        }
+
+        for ent in expired.iter() {
+            telepathy.remove(*ent);
+            if *ent == *player_ent {
+                log.push(turn_counter.turn, "Your telepathic senses fade.");
+            }
+        }
+
+        // Monsters shake off confusion as they act, but the player's wears off here.
+        let cleared = match confusion.get_mut(*player_ent) {
+            Some(status) => {
+                status.turns -= 1;
+                status.turns < 1
+            }
+            None => false,
+        };
+        if cleared {
+            confusion.remove(*player_ent);
+            log.push(turn_counter.turn, "You are no longer confused.");
+        }
+
+        // Poison hurts every turn until it works its way out.
+        let mut recovered: Vec<Entity> = Vec::new();
+        for (ent, poison) in (&entities, &mut poisoned).join() {
+            SufferDamage::new_damage(&mut suffering, ent, poison.damage, DamageType::Poison);
+            poison.turns -= 1;
+            if poison.turns < 1 {
+                recovered.push(ent);
+            }
+        }
+
+        for ent in recovered.iter() {
+            poisoned.remove(*ent);
+            if *ent == *player_ent {
+                log.push(turn_counter.turn, "The poison wears off.");
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
-use crate::{InflictsDamage, SingleActivation, SufferDamage};
+use crate::{Immunities, InflictsDamage, SingleActivation, StatusEffect, SufferDamage};
+use rltk::{Point, RandomNumberGenerator};
 use specs::prelude::*;
 
 use super::{
-    gamelog::GameLog, particle_system::ParticleBuilder, EntityMoved, EntryTrigger, Hidden, Map,
-    Name, Position,
+    gamelog::{GameLog, TurnCounter},
+    particle_system::ParticleBuilder,
+    Alerted, Confusion, EntityMoved, EntryTrigger, Hidden, Map, Monster, Name, Position,
+    RaisesAlarm, Sprung, TeleportsVictim, TileType, Viewshed,
 };
 
 pub struct TriggerSystem {}
@@ -13,7 +16,7 @@ impl<'a> System<'a> for TriggerSystem {
     type SystemData = (
         ReadExpect<'a, Map>,
         WriteStorage<'a, EntityMoved>,
-        ReadStorage<'a, Position>,
+        WriteStorage<'a, Position>,
         ReadStorage<'a, EntryTrigger>,
         ReadStorage<'a, SingleActivation>,
         WriteStorage<'a, Hidden>,
@@ -23,13 +26,25 @@ impl<'a> System<'a> for TriggerSystem {
         WriteStorage<'a, SufferDamage>,
         WriteExpect<'a, ParticleBuilder>,
         WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
+        ReadStorage<'a, TeleportsVictim>,
+        ReadStorage<'a, RaisesAlarm>,
+        WriteStorage<'a, Confusion>,
+        ReadStorage<'a, Monster>,
+        WriteStorage<'a, Alerted>,
+        WriteStorage<'a, Viewshed>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, Point>,
+        ReadExpect<'a, Entity>,
+        ReadStorage<'a, Immunities>,
+        WriteStorage<'a, Sprung>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             map,
             mut ent_moved,
-            position,
+            mut position,
             entry_trigger,
             activation,
             mut hidden,
@@ -39,41 +54,147 @@ impl<'a> System<'a> for TriggerSystem {
             mut suffering,
             mut particle_builder,
             mut log,
+            turn_counter,
+            teleports,
+            alarms,
+            mut confusion,
+            monsters,
+            mut alerted,
+            mut viewsheds,
+            mut rng,
+            mut player_pos,
+            player_ent,
+            immunities,
+            mut sprung,
         ) = data;
 
         let mut remove_entities: Vec<Entity> = Vec::new();
+        let mut teleported: Vec<Entity> = Vec::new();
+        let mut confused: Vec<(Entity, i32)> = Vec::new();
+        let mut alarm_raised = false;
+
+        // Traps go off when something steps onto them, or when something else springs them.
+        let mut activations: Vec<(Entity, Entity, Position)> = Vec::new();
         for (ent, _, pos) in (&entities, &ent_moved, &position).join() {
             let idx = map.xy_idx(pos.x, pos.y);
-            map.tile_content[idx]
+            for trap in map.tile_content[idx]
                 .iter()
                 .filter(|ent_id| ent != **ent_id)
-                .for_each(|ent_id| match entry_trigger.get(*ent_id) {
-                    None => {}
-                    Some(_) => {
-                        if let Some(name) = names.get(*ent_id) {
-                            log.entries.push(format!("{} triggers!", &name.name));
-                        }
-
-                        hidden.remove(*ent_id);
-
-                        if let Some(damage) = inflicts_damage.get(*ent_id) {
-                            particle_builder.request(
-                                pos.x,
-                                pos.y,
-                                rltk::RGB::named(rltk::ORANGE),
-                                rltk::RGB::named(rltk::BLACK),
-                                rltk::to_cp437('‼'),
-                                200.0,
-                            );
-                            SufferDamage::new_damage(&mut suffering, ent, damage.damage);
-                        }
-
-                        if let Some(_) = activation.get(*ent_id) {
-                            remove_entities.push(*ent_id);
-                        }
+            {
+                if entry_trigger.get(*trap).is_some() {
+                    activations.push((ent, *trap, *pos));
+                }
+            }
+        }
+        for (trap, spring) in (&entities, &sprung).join() {
+            if let Some(pos) = position.get(spring.victim) {
+                activations.push((spring.victim, trap, *pos));
+            }
+        }
+        sprung.clear();
+
+        for (ent, trap, pos) in activations.into_iter() {
+            if let Some(name) = names.get(trap) {
+                log.push(turn_counter.turn, format!("{} triggers!", &name.name));
+            }
+
+            // Traps are revealed once they've gone off.
+            hidden.remove(trap);
+
+            if let Some(damage) = inflicts_damage.get(trap) {
+                particle_builder.request(
+                    pos.x,
+                    pos.y,
+                    rltk::RGB::named(rltk::ORANGE),
+                    rltk::RGB::named(rltk::BLACK),
+                    rltk::to_cp437('‼'),
+                    200.0,
+                );
+                SufferDamage::new_damage(&mut suffering, ent, damage.damage, damage.damage_type);
+            }
+
+            if teleports.get(trap).is_some() {
+                particle_builder.request(
+                    pos.x,
+                    pos.y,
+                    rltk::RGB::named(rltk::MAGENTA),
+                    rltk::RGB::named(rltk::BLACK),
+                    rltk::to_cp437('*'),
+                    200.0,
+                );
+                teleported.push(ent);
+            }
+
+            if alarms.get(trap).is_some() {
+                alarm_raised = true;
+            }
+
+            if let Some(gas) = confusion.get(trap) {
+                particle_builder.request(
+                    pos.x,
+                    pos.y,
+                    rltk::RGB::named(rltk::MAGENTA),
+                    rltk::RGB::named(rltk::BLACK),
+                    rltk::to_cp437('?'),
+                    200.0,
+                );
+                if Immunities::blocks(&immunities, ent, StatusEffect::Confusion) {
+                    if let Some(name) = names.get(ent) {
+                        log.push(turn_counter.turn, format!("{} is unaffected.", &name.name));
                     }
-                });
+                } else {
+                    confused.push((ent, gas.turns));
+                }
+            }
+
+            if activation.get(trap).is_some() {
+                remove_entities.push(trap);
+            }
         }
+
+        // Send teleported entities to a random open tile.
+        let open_tiles: Vec<usize> = map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(idx, tile)| tile.is_walkable() && !map.blocked[*idx])
+            .map(|(idx, _)| idx)
+            .collect();
+        for victim in teleported.iter() {
+            if let Some(dest) = rng.random_slice_entry(&open_tiles) {
+                if let Some(pos) = position.get_mut(*victim) {
+                    pos.x = *dest as i32 % map.width;
+                    pos.y = *dest as i32 / map.width;
+                    if *victim == *player_ent {
+                        player_pos.x = pos.x;
+                        player_pos.y = pos.y;
+                    }
+                }
+                if let Some(viewshed) = viewsheds.get_mut(*victim) {
+                    viewshed.dirty = true;
+                }
+            }
+        }
+
+        for (victim, turns) in confused.iter() {
+            confusion
+                .insert(*victim, Confusion { turns: *turns })
+                .expect("Unable to insert confusion");
+        }
+
+        // Wake up everything on the level.
+        if alarm_raised {
+            log.push(
+                turn_counter.turn,
+                "A shrill alarm echoes through the level!",
+            );
+            for (monster, _) in (&entities, &monsters).join() {
+                alerted
+                    .insert(monster, Alerted {})
+                    .expect("Unable to insert alert");
+            }
+        }
+
         // Removed traps with single-activation.
         remove_entities.iter().for_each(|trap| {
             entities.delete(*trap).expect("Unable to delete trap.");
@@ -1,6 +1,22 @@
-use super::{gamelog::GameLog, Hidden, Map, Name, Player, Position, Viewshed};
+use super::{
+    gamelog::{GameLog, TurnCounter},
+    Hidden, Item, Map, Name, Player, Position, RememberedItem, Renderable, Viewshed,
+};
 use rltk::{field_of_view, Point};
 use specs::prelude::*;
+use std::collections::HashMap;
+
+/// Resource remembering where each entity last had its field of view computed.
+///
+/// A dirty viewshed is only recomputed when its owner's depth, position or range
+/// differs from the cached entry; otherwise the previous visible tiles still hold.
+/// Reset it whenever a new map is installed, since the old entries describe the old map.
+#[derive(Default)]
+pub struct FovCache {
+    computed_at: HashMap<Entity, (i32, i32, i32, i32)>,
+    /// Number of times `field_of_view` has actually been run.
+    pub fov_calls: usize,
+}
 
 pub struct VisibilitySystem {}
 
@@ -15,30 +31,54 @@ impl<'a> System<'a> for VisibilitySystem {
         WriteStorage<'a, Hidden>,
         WriteExpect<'a, rltk::RandomNumberGenerator>,
         WriteExpect<'a, GameLog>,
+        ReadExpect<'a, TurnCounter>,
         ReadStorage<'a, Name>,
+        WriteExpect<'a, FovCache>,
+        ReadStorage<'a, Item>,
+        ReadStorage<'a, Renderable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player, mut hidden, mut rng, mut log, names) =
-            data;
+        let (
+            mut map,
+            entities,
+            mut viewshed,
+            pos,
+            player,
+            mut hidden,
+            mut rng,
+            mut log,
+            turn_counter,
+            names,
+            mut cache,
+            items,
+            renderables,
+        ) = data;
 
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             // If player has been moved, update the viewshed.
             if viewshed.dirty {
                 viewshed.dirty = false;
-                // Start each loop by clearing the list of visible tiles.
-                viewshed.visible_tiles.clear();
-                // Get visible tiles for the current entity at position `pos` using
-                // its visibility range from its viewshed.
-                viewshed.visible_tiles =
-                    field_of_view(Point::new(pos.x, pos.y), viewshed.range, &*map);
-                // Deletes entries that don't meet the specified criteria; that is,
-                // confines the visible tiles to only those within the map bounds.
-                viewshed
-                    .visible_tiles
-                    .retain(|p| p.x >= 0 && p.x < map.width && p.y >= 0 && p.y < map.height);
-
-                // If this is the player, reveal the tiles they can see.
+                // Nothing relevant changed since last time, so the old tiles still hold.
+                let key = (map.depth, pos.x, pos.y, viewshed.range);
+                if cache.computed_at.get(&ent) != Some(&key) {
+                    cache.computed_at.insert(ent, key);
+                    cache.fov_calls += 1;
+                    // Start each loop by clearing the list of visible tiles.
+                    viewshed.visible_tiles.clear();
+                    // Get visible tiles for the current entity at position `pos` using
+                    // its visibility range from its viewshed.
+                    viewshed.visible_tiles =
+                        field_of_view(Point::new(pos.x, pos.y), viewshed.range, &*map);
+                    // Deletes entries that don't meet the specified criteria; that is,
+                    // confines the visible tiles to only those within the map bounds.
+                    viewshed
+                        .visible_tiles
+                        .retain(|p| p.x >= 0 && p.x < map.width && p.y >= 0 && p.y < map.height);
+                }
+
+                // If this is the player, reveal the tiles they can see. This runs even when
+                // the tiles came from the cache, so hidden things still get a spotting roll.
                 if let Some(_) = player.get(ent) {
                     // Set all to non-visible to start.
                     for t in map.visible_tiles.iter_mut() {
@@ -56,7 +96,10 @@ impl<'a> System<'a> for VisibilitySystem {
                             if hidden.get(*e).is_some() && rng.roll_dice(1, 24) == 1 {
                                 // They've spotted it--let them know and reveal it.
                                 if let Some(name) = names.get(*e) {
-                                    log.entries.push(format!("You spotted a {}", &name.name));
+                                    log.push(
+                                        turn_counter.turn,
+                                        format!("You spotted a {}", &name.name),
+                                    );
                                 }
                                 hidden.remove(*e);
                             }
@@ -65,5 +108,216 @@ impl<'a> System<'a> for VisibilitySystem {
                 }
             }
         }
+
+        // Remember the items in view, forgetting any that are no longer where they were.
+        let Map {
+            visible_tiles,
+            remembered_items,
+            ..
+        } = &mut *map;
+        remembered_items.retain(|idx, _| !visible_tiles[*idx]);
+        for (pos, render, _, _) in (&pos, &renderables, &items, !&hidden).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            if map.visible_tiles[idx] {
+                map.remembered_items.insert(
+                    idx,
+                    RememberedItem {
+                        glyph: render.glyph,
+                        fg: render.fg,
+                    },
+                );
+            }
+        }
+
+        // Forget entities that no longer exist.
+        cache.computed_at.retain(|ent, _| entities.is_alive(*ent));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Monster, TileType};
+
+    const MONSTERS: i32 = 50;
+
+    fn world_with_monsters() -> World {
+        let mut ecs = World::new();
+        ecs.register::<Viewshed>();
+        ecs.register::<Position>();
+        ecs.register::<Player>();
+        ecs.register::<Hidden>();
+        ecs.register::<Name>();
+        ecs.register::<Monster>();
+        ecs.register::<Item>();
+        ecs.register::<Renderable>();
+
+        let mut map = Map::new(1, 60, 30);
+        for y in 1..map.height - 1 {
+            for x in 1..map.width - 1 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+        ecs.insert(map);
+        ecs.insert(rltk::RandomNumberGenerator::seeded(1));
+        ecs.insert(GameLog::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(FovCache::default());
+
+        for i in 0..MONSTERS {
+            ecs.create_entity()
+                .with(Monster {})
+                .with(Position {
+                    x: 1 + i % 10 * 5,
+                    y: 1 + i / 10 * 5,
+                })
+                .with(Viewshed {
+                    visible_tiles: Vec::new(),
+                    range: 8,
+                    dirty: true,
+                })
+                .build();
+        }
+        ecs
+    }
+
+    fn mark_all_dirty(ecs: &mut World) {
+        for viewshed in (&mut ecs.write_storage::<Viewshed>()).join() {
+            viewshed.dirty = true;
+        }
+    }
+
+    #[test]
+    fn fov_is_not_recomputed_when_nobody_moves() {
+        let mut ecs = world_with_monsters();
+        let mut vis = VisibilitySystem {};
+
+        vis.run_now(&ecs);
+        assert_eq!(ecs.fetch::<FovCache>().fov_calls, MONSTERS as usize);
+
+        // Every turn the monsters stand still costs nothing.
+        for _ in 0..10 {
+            mark_all_dirty(&mut ecs);
+            vis.run_now(&ecs);
+        }
+        assert_eq!(ecs.fetch::<FovCache>().fov_calls, MONSTERS as usize);
+        assert!((&ecs.read_storage::<Viewshed>())
+            .join()
+            .all(|vs| !vs.visible_tiles.is_empty()));
+
+        // Only the one that moved is recomputed.
+        if let Some(pos) = (&mut ecs.write_storage::<Position>()).join().next() {
+            pos.x += 1;
+        }
+        mark_all_dirty(&mut ecs);
+        vis.run_now(&ecs);
+        assert_eq!(ecs.fetch::<FovCache>().fov_calls, MONSTERS as usize + 1);
+    }
+
+    #[test]
+    fn standing_still_still_rolls_to_spot_hidden_things() {
+        let mut ecs = world_with_monsters();
+        ecs.create_entity()
+            .with(Player {})
+            .with(Position { x: 3, y: 3 })
+            .with(Viewshed {
+                visible_tiles: Vec::new(),
+                range: 8,
+                dirty: true,
+            })
+            .build();
+        let trap = ecs
+            .create_entity()
+            .with(Hidden {})
+            .with(Position { x: 4, y: 3 })
+            .build();
+        let mut vis = VisibilitySystem {};
+
+        // Every pass after the first is a cache hit, but each one gets a 1 in 24 roll.
+        for _ in 0..200 {
+            {
+                let mut map = ecs.fetch_mut::<Map>();
+                let idx = map.xy_idx(4, 3);
+                map.tile_content[idx] = vec![trap];
+            }
+            mark_all_dirty(&mut ecs);
+            vis.run_now(&ecs);
+        }
+        assert_eq!(ecs.fetch::<FovCache>().fov_calls, MONSTERS as usize + 1);
+        assert!(ecs.read_storage::<Hidden>().get(trap).is_none());
+    }
+
+    #[test]
+    fn items_are_remembered_out_of_sight_but_monsters_are_not() {
+        let rows = ["#########", "#@......#", "#########"];
+        let (map, start) = Map::from_ascii(&rows.join("\n")).unwrap();
+        let start = start.unwrap();
+        let mut ecs = World::new();
+        crate::register_components(&mut ecs);
+        ecs.insert(map);
+        ecs.insert(rltk::RandomNumberGenerator::seeded(1));
+        ecs.insert(GameLog::default());
+        ecs.insert(TurnCounter::default());
+        ecs.insert(FovCache::default());
+
+        let player = ecs
+            .create_entity()
+            .with(Player {})
+            .with(Position {
+                x: start.x,
+                y: start.y,
+            })
+            .with(Viewshed {
+                visible_tiles: Vec::new(),
+                range: 8,
+                dirty: true,
+            })
+            .build();
+        let glyph = |c| Renderable {
+            glyph: rltk::to_cp437(c),
+            fg: rltk::RGB::named(rltk::YELLOW),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: crate::RenderOrder::Item,
+        };
+        let potion = ecs
+            .create_entity()
+            .with(Item {})
+            .with(glyph('!'))
+            .with(Position { x: 6, y: 1 })
+            .build();
+        ecs.create_entity()
+            .with(Monster {})
+            .with(glyph('o'))
+            .with(Position { x: 5, y: 1 })
+            .build();
+        VisibilitySystem {}.run_now(&ecs);
+
+        // Go blind, as if the player had walked away.
+        {
+            let mut viewsheds = ecs.write_storage::<Viewshed>();
+            let viewshed = viewsheds.get_mut(player).unwrap();
+            viewshed.range = 0;
+            viewshed.dirty = true;
+        }
+        VisibilitySystem {}.run_now(&ecs);
+        {
+            let map = ecs.fetch::<Map>();
+            assert!(!map.visible_tiles[map.xy_idx(6, 1)]);
+            let remembered = map.remembered_items.get(&map.xy_idx(6, 1)).unwrap();
+            assert_eq!(remembered.glyph, rltk::to_cp437('!'));
+            assert!(map.remembered_items.get(&map.xy_idx(5, 1)).is_none());
+        }
+
+        // Coming back to find the potion gone clears the memory of it.
+        ecs.delete_entity(potion).unwrap();
+        {
+            let mut viewsheds = ecs.write_storage::<Viewshed>();
+            let viewshed = viewsheds.get_mut(player).unwrap();
+            viewshed.range = 8;
+            viewshed.dirty = true;
+        }
+        VisibilitySystem {}.run_now(&ecs);
+        assert!(ecs.fetch::<Map>().remembered_items.is_empty());
     }
 }
@@ -0,0 +1,76 @@
+use rltk::{DijkstraMap, Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use super::{
+    gamelog::TurnCounter, spawner, spawner::SpawnRequests, Map, Monster, RunState, TileType,
+    SHOP_DEPTH,
+};
+
+/// Furthest walking distance a wandering monster can arrive from.
+const WANDER_SPAWN_DEPTH: f32 = 200.0;
+
+/// Turns between wandering monster arrivals at `depth`; deeper levels are busier.
+fn spawn_interval(depth: i32) -> i32 {
+    i32::max(20, 80 - depth * 6)
+}
+
+/// Most monsters a level at `depth` can hold before wanderers stop arriving.
+fn monster_cap(depth: i32) -> usize {
+    (4 + depth * 2) as usize
+}
+
+/// Brings a new monster onto the level every so often, out of the player's sight.
+pub struct WanderingSpawnSystem {}
+
+impl<'a> System<'a> for WanderingSpawnSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Point>,
+        ReadExpect<'a, RunState>,
+        ReadExpect<'a, TurnCounter>,
+        ReadStorage<'a, Monster>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, SpawnRequests>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, player_pos, runstate, turn_counter, monsters, mut rng, mut spawn_requests) = data;
+
+        // Only once per turn, only every so often, and never in the shop.
+        if *runstate != RunState::PlayerTurn
+            || map.depth == SHOP_DEPTH
+            || turn_counter.turn % spawn_interval(map.depth) != 0
+            || (&monsters).join().count() >= monster_cap(map.depth)
+        {
+            return;
+        }
+
+        // Anywhere the player could walk to, but can't currently see.
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        let reachable = DijkstraMap::new(
+            map.width as usize,
+            map.height as usize,
+            &[player_idx],
+            &*map,
+            WANDER_SPAWN_DEPTH,
+        );
+        let candidates: Vec<usize> = reachable
+            .map
+            .iter()
+            .enumerate()
+            .filter(|(idx, distance)| {
+                **distance < f32::MAX
+                    && map.tiles[*idx].is_walkable()
+                    && !map.blocked[*idx]
+                    && !map.visible_tiles[*idx]
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+
+        if let Some(idx) = rng.random_slice_entry(&candidates) {
+            let name = spawner::wandering_monster(&mut rng, map.depth);
+            spawn_requests.request(*idx as i32 % map.width, *idx as i32 / map.width, name);
+        }
+    }
+}